@@ -0,0 +1,72 @@
+/*
+ * C interface of ytorrent, see src/ffi.rs for the documentation of each function.
+ *
+ * Build the library with `cargo rustc --release --features ffi --crate-type cdylib`.
+ */
+#ifndef YTORRENT_H
+#define YTORRENT_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque handle to a bencode value */
+typedef struct YtValue YtValue;
+
+typedef enum YtErrorCode {
+    YT_ERROR_CODE_OK = 0,
+    YT_ERROR_CODE_DECODE = 1,
+    YT_ERROR_CODE_INVALID_ARGUMENT = 2,
+} YtErrorCode;
+
+typedef struct YtError {
+    YtErrorCode code;
+    bool has_offset;
+    size_t offset;
+    char message[256];
+} YtError;
+
+typedef enum YtType {
+    YT_TYPE_INT = 0,
+    YT_TYPE_BYTES = 1,
+    YT_TYPE_LIST = 2,
+    YT_TYPE_DICT = 3,
+} YtType;
+
+typedef struct YtSlice {
+    const uint8_t *data;
+    size_t len;
+} YtSlice;
+
+typedef struct YtBuffer {
+    uint8_t *data;
+    size_t len;
+} YtBuffer;
+
+YtValue *yt_parse(const uint8_t *data, size_t len, YtError *error);
+
+void yt_value_free(YtValue *value);
+
+const YtValue *yt_get_path(const YtValue *value, const char *path);
+
+YtType yt_value_type(const YtValue *value);
+
+bool yt_value_int(const YtValue *value, int64_t *out);
+
+bool yt_value_bytes(const YtValue *value, YtSlice *out);
+
+size_t yt_value_len(const YtValue *value);
+
+YtBuffer yt_encode(const YtValue *value);
+
+void yt_buffer_free(YtBuffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* YTORRENT_H */
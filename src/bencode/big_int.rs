@@ -0,0 +1,92 @@
+use core::fmt;
+use core::str::FromStr;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::*;
+
+/// A bencode integer kept as its digits, so any integer fits.
+///
+/// For fields that buggy peers or trackers fill with integers beyond `i64`. Like [RawValue],
+/// it only works with [crate::de] and [crate::ser] of this crate.
+///
+/// Example:
+/// ```
+/// use serde::Deserialize;
+/// use ytorrent::{de, BigIntString};
+///
+/// #[derive(Deserialize)]
+/// struct Stats {
+///     downloaded: BigIntString,
+/// }
+/// let stats: Stats = de::from_bytes(b"d10:downloadedi123456789012345678901234567890ee").unwrap();
+/// assert_eq!(stats.downloaded.as_str(), "123456789012345678901234567890");
+/// assert!(stats.downloaded.parse::<i64>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigIntString(String);
+
+impl BigIntString {
+    /// The digits, with a leading `-` for negative integers
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Parse the digits into a number type
+    pub fn parse<T: FromStr>(&self) -> core::result::Result<T, T::Err> {
+        self.0.parse()
+    }
+}
+
+impl From<i64> for BigIntString {
+    fn from(value: i64) -> Self {
+        BigIntString(value.to_string())
+    }
+}
+
+impl fmt::Display for BigIntString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for BigIntString {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        RawValue(format!("i{}e", self.0).as_bytes()).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for BigIntString {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match RawValue::deserialize(deserializer)?.get() {
+            // The parser checked the digits already
+            [b'i', digits @ .., b'e'] => {
+                Ok(BigIntString(String::from_utf8_lossy(digits).into_owned()))
+            }
+            _ => Err(D::Error::custom("expect integer for BigIntString")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{de, ser};
+
+    #[test]
+    fn test_big_int_string() {
+        let data = b"li-99999999999999999999ei42ee";
+        let ints: Vec<BigIntString> = de::from_bytes(data).unwrap();
+        assert_eq!(ints[0].as_str(), "-99999999999999999999");
+        assert_eq!(ints[1], BigIntString::from(42));
+        assert_eq!(ser::to_bytes(&ints).unwrap(), data);
+        assert!(de::from_bytes::<BigIntString>(b"2:42").is_err());
+    }
+}
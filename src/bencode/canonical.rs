@@ -0,0 +1,87 @@
+//! Canonical form of bencode: dict keys sorted and unique, nothing after the value.
+//!
+//! Two encodings of the same document only hash the same in canonical form, which matters for
+//! the info hash of a torrent written by a sloppy encoder.
+use super::*;
+
+/// Re-encode `data` in canonical form. Of duplicated keys, the last one is kept.
+///
+/// ```
+/// use ytorrent::{canonicalize, is_canonical};
+///
+/// let canonical = canonicalize(b"d1:bi2e1:ai1ee").unwrap();
+/// assert_eq!(canonical, b"d1:ai1e1:bi2ee");
+/// assert!(is_canonical(&canonical));
+/// ```
+pub fn canonicalize(data: &[u8]) -> Result<Vec<u8>> {
+    de::from_bytes_exact::<BencodeValue>(data).map(|value| value.to_bytes())
+}
+
+/// Whether `data` is valid bencode already in canonical form
+pub fn is_canonical(data: &[u8]) -> bool {
+    canonicalize(data).is_ok_and(|canonical| canonical == data)
+}
+
+/// Whether `a` and `b` encode the same value, ignoring the order of dict keys
+///
+/// ```
+/// use ytorrent::semantically_equal;
+///
+/// assert!(semantically_equal(b"d1:ai1e1:bi2ee", b"d1:bi2e1:ai1ee").unwrap());
+/// assert!(!semantically_equal(b"li1ei2ee", b"li2ei1ee").unwrap());
+/// ```
+pub fn semantically_equal(a: &[u8], b: &[u8]) -> Result<bool> {
+    Ok(de::from_bytes_exact::<BencodeValue>(a)? == de::from_bytes_exact::<BencodeValue>(b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize() {
+        let cases: [(&[u8], &[u8]); 4] = [
+            (b"i42e", b"i42e"),
+            (
+                b"d1:bl1:y1:xe1:ad1:di1e1:ci2eee",
+                b"d1:ad1:ci2e1:di1ee1:bl1:y1:xee",
+            ),
+            (b"d1:ai1e1:ai2ee", b"d1:ai2ee"),
+            (b"le", b"le"),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(canonicalize(data).unwrap(), expected);
+            assert_eq!(is_canonical(data), data == expected);
+        }
+
+        assert!(canonicalize(b"i42ee").is_err());
+        assert!(canonicalize(b"i042e").is_err());
+        assert!(!is_canonical(b"d1:a"));
+    }
+
+    #[test]
+    fn test_semantically_equal() {
+        let a = b"d4:infod6:lengthi1e4:name1:xe3:urlli1ei2eee";
+        let b = b"d3:urlli1ei2ee4:infod4:name1:x6:lengthi1eee";
+        assert!(semantically_equal(a, b).unwrap());
+        assert!(!semantically_equal(a, b"d3:urlli1ei2eee").unwrap());
+        assert!(semantically_equal(a, b"d4:info").is_err());
+    }
+
+    #[test]
+    fn test_ord() {
+        let mut values: Vec<BencodeValue> = [
+            "d1:bi1ee", "d1:ai2ee", "le", "li1ee", "1:b", "0:", "i10e", "i-1e", "i9e",
+        ]
+        .iter()
+        .map(|data| BencodeValue::from_bytes(data.as_bytes()).unwrap())
+        .collect();
+        values.sort();
+        let sorted: Vec<Vec<u8>> = values.iter().map(BencodeValue::to_bytes).collect();
+        assert_eq!(
+            sorted,
+            ["i-1e", "i9e", "i10e", "0:", "1:b", "le", "li1ee", "d1:ai2ee", "d1:bi1ee",]
+                .map(|data| data.as_bytes().to_vec())
+        );
+    }
+}
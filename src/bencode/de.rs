@@ -1,18 +1,60 @@
-use log::trace;
+#[cfg(feature = "std")]
+use std::io::{BufRead, Read};
+
+#[cfg(feature = "std")]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+use serde::de::value::SeqDeserializer;
 use serde::de::{
     DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
 };
-use serde::Deserializer;
+use serde::{forward_to_deserialize_any, Deserializer};
 
+#[cfg(feature = "std")]
+use super::stream::StreamDeserializer;
 use super::*;
 use super::Error::*;
 
+/// Numbers read from the digits of a bencode integer
+pub(super) trait FromDigits: Sized {
+    type Err: core::fmt::Debug;
+
+    /// Out of range integers are clamped with `saturate`
+    fn from_digits(digits: &str, saturate: bool) -> core::result::Result<Self, Self::Err>;
+}
+
+macro_rules! impl_from_digits {
+    (int: $($int_type:ty),*; float: $($float_type:ty),*) => {
+        $(impl FromDigits for $int_type {
+            type Err = core::num::ParseIntError;
+
+            fn from_digits(digits: &str, saturate: bool) -> core::result::Result<Self, Self::Err> {
+                // The parser only lets valid integers through, so a failure means out of range
+                digits.parse().or_else(|e| match (saturate, digits.starts_with('-')) {
+                    (true, true) => Ok(<$int_type>::MIN),
+                    (true, false) => Ok(<$int_type>::MAX),
+                    (false, _) => Err(e),
+                })
+            }
+        })*
+        $(impl FromDigits for $float_type {
+            type Err = core::num::ParseFloatError;
+
+            fn from_digits(digits: &str, _: bool) -> core::result::Result<Self, Self::Err> {
+                digits.parse()
+            }
+        })*
+    };
+}
+
+impl_from_digits!(int: i8, i16, i32, i64, u8, u16, u32, u64; float: f32, f64);
+
 macro_rules! deserialize_integer {
     ($self:ident, $int_type:ty, $target_type:literal) => {{
         let cur_position = $self.offset;
+        let saturate = $self.options().int_overflow == IntOverflowPolicy::Saturate;
         trace!("deserialize_integer for {}", $target_type);
         match $self.parse()? {
-            Some(Object::Int(value)) => value.parse::<$int_type>().map_err(|e| {
+            Some(Object::Int(value)) => <$int_type>::from_digits(value, saturate).map_err(|e| {
                 SerdeCustom(format!(
                     "invalid integer when parse {} at {}, {:?}",
                     $target_type, cur_position, e
@@ -30,12 +72,32 @@ macro_rules! deserialize_integer {
     }};
 }
 
+/// An integer, or a string of a float if [ParserOptions::float_strings] is set
+macro_rules! deserialize_float {
+    ($self:ident, $float_type:ty, $target_type:literal) => {{
+        let cur_position = $self.offset;
+        match $self.peek_token()? {
+            Token::String(_) if $self.options().float_strings => {
+                deserialize_string!($self, $target_type)?
+                    .parse::<$float_type>()
+                    .map_err(|e| {
+                        SerdeCustom(format!(
+                            "invalid float when parse {} at {}, {:?}",
+                            $target_type, cur_position, e
+                        ))
+                    })
+            }
+            _ => deserialize_integer!($self, $float_type, $target_type),
+        }
+    }};
+}
+
 macro_rules! deserialize_string {
     ($self:ident, $target_type:literal) => {{
         let cur_position = $self.offset;
         trace!("deserialize_string for {}", $target_type);
         match $self.parse()? {
-            Some(Object::Bytes(bytes)) => std::str::from_utf8(bytes).map_err(|e| {
+            Some(Object::Bytes(bytes)) => core::str::from_utf8(bytes).map_err(|e| {
                 SerdeCustom(format!(
                     "UTF-8 error: {} when parse {} at {}",
                     e, $target_type, cur_position
@@ -71,7 +133,7 @@ macro_rules! deserialize_bytes {
     }};
 }
 
-impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
+impl<'de> Deserializer<'de> for &mut BencodeParser<'de> {
     type Error = Error;
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
@@ -80,11 +142,20 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     {
         trace!("deserialize_any");
         let cur_position = self.offset;
-        match *self.peek_token()? {
+        match self.peek_token()? {
             Token::Dict => self.deserialize_map(visitor),
             Token::List => self.deserialize_seq(visitor),
             Token::Num(_) => self.deserialize_i64(visitor),
-            Token::String(_) => self.deserialize_bytes(visitor),
+            Token::String(_) => {
+                // Strings are told apart from bytes for the sake of types buffering the data
+                // through `deserialize_any`, such as the fields of a `#[serde(flatten)]` struct,
+                // which wouldn't accept bytes for an enum or a `&str`
+                let bytes = deserialize_bytes!(self, "any")?;
+                match core::str::from_utf8(bytes) {
+                    Ok(str) => visitor.visit_borrowed_str(str),
+                    Err(_) => visitor.visit_borrowed_bytes(bytes),
+                }
+            }
             Token::End => Err(SerdeCustom(format!(
                 "unexpected EOF at {} deserialize_any",
                 cur_position
@@ -96,7 +167,15 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     where
         V: Visitor<'de>,
     {
-        match deserialize_integer!(self, i64, "bool")? {
+        let cur_position = self.offset;
+        let value = deserialize_integer!(self, i64, "bool")?;
+        match value {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            other if self.options().strict_bools => Err(SerdeCustom(format!(
+                "expect 0 or 1 for bool but get {} at {}",
+                other, cur_position
+            ))),
             positive if positive > 0 => visitor.visit_bool(true),
             _ => visitor.visit_bool(false),
         }
@@ -123,28 +202,28 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
         visitor.visit_i32(deserialize_integer!(self, i32, "i32")?)
     }
 
-    fn deserialize_i64<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_i64<V>(self, visitor: V) -> core::result::Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         visitor.visit_i64(deserialize_integer!(self, i64, "i64")?)
     }
 
-    fn deserialize_u8<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u8<V>(self, visitor: V) -> core::result::Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         visitor.visit_u8(deserialize_integer!(self, u8, "u8")?)
     }
 
-    fn deserialize_u16<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u16<V>(self, visitor: V) -> core::result::Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         visitor.visit_u16(deserialize_integer!(self, u16, "u16")?)
     }
 
-    fn deserialize_u32<V>(self, visitor: V) -> std::result::Result<V::Value, Self::Error>
+    fn deserialize_u32<V>(self, visitor: V) -> core::result::Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
@@ -162,14 +241,14 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f32(deserialize_integer!(self, f32, "f32")?)
+        visitor.visit_f32(deserialize_float!(self, f32, "f32")?)
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        visitor.visit_f64(deserialize_integer!(self, f64, "f64")?)
+        visitor.visit_f64(deserialize_float!(self, f64, "f64")?)
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
@@ -239,10 +318,13 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
         self.deserialize_unit(visitor)
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == RAW_VALUE_TOKEN {
+            return visitor.visit_borrowed_bytes(self.take_raw_value()?);
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -252,16 +334,37 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     {
         trace!("deserialize_seq");
         self.expect_list_begin("seq/tuple/tuple_struct")?;
-        let value = visitor.visit_seq(&mut *self)?;
+        let len = self.count_items();
+        let value = self.nested(|parser| {
+            visitor.visit_seq(SeqReader {
+                parser,
+                index: 0,
+                len,
+            })
+        })?;
         self.expect_end("seq/tuple/tuple_struct")?;
         Ok(value)
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
         trace!("deserialize_tuple");
+        let cur_position = self.offset;
+        if let Token::String(_) = self.peek_token()? {
+            // A byte array such as `[u8; 20]`, read from a string of that length
+            let bytes = deserialize_bytes!(self, "tuple")?;
+            if bytes.len() != len {
+                return Err(SerdeCustom(format!(
+                    "expect {} bytes for tuple but get {} at {}",
+                    len,
+                    bytes.len(),
+                    cur_position
+                )));
+            }
+            return visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied()));
+        }
         self.deserialize_any(visitor)
     }
 
@@ -283,11 +386,7 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
         V: Visitor<'de>,
     {
         trace!("deserialize_map");
-        self.expect_dict_begin("map/struct")?;
-        let value = visitor.visit_map(&mut *self)?;
-        self.expect_end("map/struct")?;
-        trace!("end deserialize_map");
-        Ok(value)
+        self.visit_dict(visitor, true)
     }
 
     fn deserialize_struct<V>(
@@ -299,7 +398,9 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        trace!("deserialize_struct");
+        // Structs know their fields, counting them is no use
+        self.visit_dict(visitor, false)
     }
 
     fn deserialize_enum<V>(
@@ -313,15 +414,15 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     {
         trace!("deserialize_enum");
         let cur_position = self.offset;
-        match &*self.peek_token()? {
+        match self.peek_token()? {
             Token::Dict => {
                 self.expect_dict_begin("enum")?;
-                visitor.visit_enum(&mut *self)
+                self.nested(|parser| visitor.visit_enum(parser))
             }
             Token::String(bytes) => {
                 // consume the peeked token
                 self.next_token()?;
-                let str = std::str::from_utf8(bytes).map_err(|e| {
+                let str = core::str::from_utf8(bytes).map_err(|e| {
                     SerdeCustom(format!(
                         "UTF-8 error: {} when parse enum at {}",
                         e, cur_position
@@ -348,23 +449,52 @@ impl<'de, 'a> Deserializer<'de> for &'a mut BencodeParser<'de> {
     where
         V: Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        trace!("deserialize_ignored_any");
+        self.skip_value()?;
+        visitor.visit_unit()
     }
 }
 
-impl<'a, 'de: 'a> MapAccess<'de> for BencodeParser<'de> {
+/// Reads the pairs of a dict, remembering the key to report the path of errors
+struct MapReader<'a, 'de> {
+    parser: &'a mut BencodeParser<'de>,
+    key: Option<&'de [u8]>,
+    /// Pairs left, with [ParserOptions::size_hints]
+    remaining: Option<usize>,
+}
+
+impl<'a, 'de> MapAccess<'de> for MapReader<'a, 'de> {
     type Error = Error;
 
     fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
     where
         K: DeserializeSeed<'de>,
     {
-        let token = self.peek_token()?;
-        if *token == Token::End {
-            return Ok(None);
-        }
+        let token = self.parser.peek_token()?;
         trace!("visit map key {}", token);
-        seed.deserialize(self).map(Some)
+        if token != Token::End {
+            self.remaining = self.remaining.map(|remaining| remaining.saturating_sub(1));
+        }
+        match token {
+            Token::End => Ok(None),
+            Token::String(key) => {
+                self.key = Some(key);
+                let start = self.parser.next_token_offset();
+                self.parser.next_token()?;
+                let raw = &self.parser.data[start..self.parser.offset];
+                seed.deserialize(KeyDeserializer {
+                    key,
+                    raw: Some(raw),
+                    lenient_ints: self.parser.options().lenient_int_keys,
+                })
+                .map(Some)
+            }
+            _ => {
+                // Not a valid key, let the parser report it
+                self.key = None;
+                seed.deserialize(&mut *self.parser).map(Some)
+            }
+        }
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
@@ -372,23 +502,476 @@ impl<'a, 'de: 'a> MapAccess<'de> for BencodeParser<'de> {
         V: DeserializeSeed<'de>,
     {
         trace!("visit map value");
-        seed.deserialize(self)
+        let offset = self.parser.next_token_offset();
+        let key = self.key.take();
+        let value = seed.deserialize(&mut *self.parser).map_err(|e| match key {
+            Some(key) => e.with_path_segment(&String::from_utf8_lossy(key), offset),
+            None => e,
+        })?;
+        if let Some(key) = key {
+            self.parser.observe_dict_value(key, offset);
+        }
+        Ok(value)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.remaining
+    }
+}
+
+impl<'de> BencodeParser<'de> {
+    /// Read a dict with `visitor`, passing the number of pairs if `size_hint` and
+    /// [ParserOptions::size_hints] are set
+    fn visit_dict<V>(&mut self, visitor: V, size_hint: bool) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_dict_begin("map/struct")?;
+        let remaining = match size_hint {
+            true => self.count_items().map(|count| count / 2),
+            false => None,
+        };
+        let value = self.nested(|parser| {
+            visitor.visit_map(MapReader {
+                parser,
+                key: None,
+                remaining,
+            })
+        })?;
+        self.expect_end("map/struct")?;
+        trace!("end deserialize_map");
+        Ok(value)
+    }
+}
+
+/// Integers parsed by the `parse` method of a deserializer
+macro_rules! deserialize_digits {
+    ($($method:ident => $visit:ident: $number_type:ty),*) => {
+        $(fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.$visit(self.parse::<$number_type>()?)
+        })*
+    };
+}
+
+/// Deserializer of a dict key, or of a string already read as an [Object].
+///
+/// Keys are byte strings, they can be read as strings, bytes, or sequences of `u8` such as
+/// `Vec<u8>` and `[u8; 20]`. Identifiers which aren't UTF-8 are passed as bytes, so unknown
+/// binary keys of a struct are ignored like any other unknown key. Integers are read from
+/// their digits, for maps such as `BTreeMap<u64, V>`.
+struct KeyDeserializer<'de> {
+    key: &'de [u8],
+    /// The key as it is in the data, with the length prefix, unknown for an [Object]
+    raw: Option<&'de [u8]>,
+    /// See [ParserOptions::lenient_int_keys]
+    lenient_ints: bool,
+}
+
+impl<'de> KeyDeserializer<'de> {
+    fn as_str(&self) -> Result<&'de str> {
+        core::str::from_utf8(self.key)
+            .map_err(|e| SerdeCustom(format!("UTF-8 error: {} when parse dict key", e)))
+    }
+
+    fn parse<T: FromDigits>(&self) -> Result<T> {
+        parse_key_digits(self.key, self.lenient_ints)
+    }
+}
+
+/// Read an integer from the digits of a dict key, trimmed if `lenient`
+pub(super) fn parse_key_digits<T: FromDigits>(key: &[u8], lenient: bool) -> Result<T> {
+    let str = core::str::from_utf8(key)
+        .map_err(|e| SerdeCustom(format!("UTF-8 error: {} when parse dict key", e)))?;
+    let digits = match lenient {
+        true => str.trim(),
+        false if is_canonical_int(str) => str,
+        false => return Err(SerdeCustom(format!("dict key {} is not an integer", str))),
+    };
+    T::from_digits(digits, false)
+        .map_err(|e| SerdeCustom(format!("invalid integer dict key {}, {:?}", str, e)))
+}
+
+/// Digits as a bencode integer has them: no sign but `-`, no leading zero, no `-0`
+fn is_canonical_int(str: &str) -> bool {
+    let digits = str.strip_prefix('-').unwrap_or(str);
+    let valid_start = match digits.as_bytes() {
+        [b'0'] => digits.len() == str.len(),
+        [b'0', ..] | [] => false,
+        _ => true,
+    };
+    valid_start && digits.bytes().all(|b| b.is_ascii_digit())
+}
+
+impl<'de> Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match core::str::from_utf8(self.key) {
+            Ok(str) => visitor.visit_borrowed_str(str),
+            Err(_) => visitor.visit_borrowed_bytes(self.key),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_str(self.as_str()?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.key)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_borrowed_bytes(self.key)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer::new(self.key.iter().copied()))
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == RAW_VALUE_TOKEN {
+            return match self.raw {
+                Some(raw) => visitor.visit_borrowed_bytes(raw),
+                None => Err(SerdeCustom(
+                    "raw value of a decoded string is unknown".to_string(),
+                )),
+            };
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.as_str()?.into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_digits!(
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64
+    );
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char unit unit_struct tuple_struct map struct
     }
 }
 
-impl<'de> SeqAccess<'de> for BencodeParser<'de> {
+/// Reads the items of a list, counting them to report the path of errors
+struct SeqReader<'a, 'de> {
+    parser: &'a mut BencodeParser<'de>,
+    index: usize,
+    /// Number of items, with [ParserOptions::size_hints]
+    len: Option<usize>,
+}
+
+impl<'a, 'de> SeqAccess<'de> for SeqReader<'a, 'de> {
     type Error = Error;
 
     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
     where
         T: DeserializeSeed<'de>,
     {
-        let token = self.peek_token()?;
-        if *token == Token::End {
+        let token = self.parser.peek_token()?;
+        if token == Token::End {
             return Ok(None);
         }
-        seed.deserialize(self).map(Some)
+        let offset = self.parser.next_token_offset();
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(&mut *self.parser)
+            .map(Some)
+            .map_err(|e| e.with_path_segment(&format!("[{}]", index), offset))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.len.map(|len| len.saturating_sub(self.index))
+    }
+}
+
+/// Deserializer of the digits of an integer already read as an [Object].
+///
+/// Integers are checked as [from_bytes] does with the default [ParserOptions].
+struct IntDeserializer<'de>(&'de str);
+
+impl IntDeserializer<'_> {
+    fn parse<T: FromDigits>(&self) -> Result<T> {
+        T::from_digits(self.0, false)
+            .map_err(|e| SerdeCustom(format!("invalid integer {}, {:?}", self.0, e)))
+    }
+}
+
+impl<'de> Deserializer<'de> for IntDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.parse::<i64>() {
+            Ok(int) => visitor.visit_i64(int),
+            Err(_) => visitor.visit_u64(self.parse()?),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bool(self.parse::<i64>()? > 0)
+    }
+
+    deserialize_digits!(
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64,
+        deserialize_f32 => visit_f32: f32,
+        deserialize_f64 => visit_f64: f64
+    );
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        i128 u128 char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct enum identifier ignored_any
+    }
+}
+
+/// Scalars go to [IntDeserializer] and [KeyDeserializer], lists and dicts to
+/// [Deserializer::deserialize_any]
+macro_rules! forward_object {
+    ($($method:ident($($arg:ident: $arg_type:ty),*)),*) => {
+        $(fn $method<V>(self, $($arg: $arg_type,)* visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                Object::Int(digits) => IntDeserializer(digits).$method($($arg,)* visitor),
+                Object::Bytes(key) => KeyDeserializer { key, raw: None, lenient_ints: false }.$method($($arg,)* visitor),
+                container => container.deserialize_any(visitor),
+            }
+        })*
+    };
+}
+
+/// Hydrate a value met while decoding manually, e.g. one subtree of a document into a struct:
+///
+/// ```
+/// use serde::Deserialize;
+/// use ytorrent::{BencodeParser, FileInfo, Object};
+///
+/// let data = b"d5:filesld6:lengthi1e4:pathl1:aeeee";
+/// let mut parser = BencodeParser::new(data);
+/// let Some(Object::Dict(mut torrent)) = parser.parse().unwrap() else { unreachable!() };
+/// let files = torrent.find_key(b"files").unwrap().unwrap();
+/// let files = Vec::<FileInfo>::deserialize(files).unwrap();
+/// assert_eq!(files[0].path, ["a"]);
+/// ```
+///
+/// A list or dict is read from where its decoder is, so items already read with it are left
+/// out.
+impl<'obj, 'de: 'obj> Deserializer<'de> for Object<'obj, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Object::Int(digits) => IntDeserializer(digits).deserialize_any(visitor),
+            Object::Bytes(key) => KeyDeserializer {
+                key,
+                raw: None,
+                lenient_ints: false,
+            }
+            .deserialize_any(visitor),
+            Object::List(mut decoder) => decoder.read_rest(|parser| {
+                let value = visitor.visit_seq(SeqReader {
+                    parser: &mut *parser,
+                    index: 0,
+                    len: None,
+                })?;
+                parser.expect_end("seq")?;
+                Ok(value)
+            }),
+            Object::Dict(mut decoder) => decoder.read_rest(|parser| {
+                let value = visitor.visit_map(MapReader {
+                    parser: &mut *parser,
+                    key: None,
+                    remaining: None,
+                })?;
+                parser.expect_end("map/struct")?;
+                Ok(value)
+            }),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Object::Int(digits) => {
+                IntDeserializer(digits).deserialize_newtype_struct(name, visitor)
+            }
+            Object::Bytes(key) => KeyDeserializer {
+                key,
+                raw: None,
+                lenient_ints: false,
+            }
+            .deserialize_newtype_struct(name, visitor),
+            container => visitor.visit_newtype_struct(container),
+        }
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self {
+            Object::Bytes(key) => KeyDeserializer {
+                key,
+                raw: None,
+                lenient_ints: false,
+            }
+            .deserialize_enum(name, variants, visitor),
+            // The variant access reads the end of the dict
+            Object::Dict(mut decoder) => decoder.read_rest(|parser| visitor.visit_enum(parser)),
+            other => other.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        // Decoders read the rest of their value when dropped
+        drop(self);
+        visitor.visit_unit()
     }
+
+    forward_object!(
+        deserialize_bool(),
+        deserialize_i8(),
+        deserialize_i16(),
+        deserialize_i32(),
+        deserialize_i64(),
+        deserialize_u8(),
+        deserialize_u16(),
+        deserialize_u32(),
+        deserialize_u64(),
+        deserialize_f32(),
+        deserialize_f64(),
+        deserialize_char(),
+        deserialize_str(),
+        deserialize_string(),
+        deserialize_bytes(),
+        deserialize_byte_buf(),
+        deserialize_unit(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_seq(),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_map(),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_identifier()
+    );
 }
 
 impl<'de> VariantAccess<'de> for &mut BencodeParser<'de> {
@@ -450,16 +1033,551 @@ where
     serde::de::Deserialize::deserialize(&mut BencodeParser::new(b))
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+/// Same as [from_bytes], deserializing with a stateful `seed` instead of a type.
+///
+/// ```
+/// use std::collections::HashSet;
+/// use serde::de::{Deserialize, DeserializeSeed, Deserializer};
+/// use ytorrent::de;
+///
+/// /// Collects every distinct string of a list
+/// struct Intern<'a>(&'a mut HashSet<String>);
+///
+/// impl<'de> DeserializeSeed<'de> for Intern<'_> {
+///     type Value = usize;
+///
+///     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+///         let list = Vec::<String>::deserialize(deserializer)?;
+///         let count = list.len();
+///         self.0.extend(list);
+///         Ok(count)
+///     }
+/// }
+///
+/// let mut strings = HashSet::new();
+/// assert_eq!(de::from_bytes_seed(Intern(&mut strings), b"l1:a1:be").unwrap(), 2);
+/// assert_eq!(de::from_bytes_seed(Intern(&mut strings), b"l1:b1:ce").unwrap(), 2);
+/// assert_eq!(strings.len(), 3);
+/// ```
+pub fn from_bytes_seed<'de, S>(seed: S, b: &'de [u8]) -> Result<S::Value>
+where
+    S: DeserializeSeed<'de>,
+{
+    seed.deserialize(&mut BencodeParser::new(b))
+}
 
-    use log::{LevelFilter, Metadata, Record};
+/// Same as [from_bytes], parsing with the given `options`.
+///
+/// In [ParserOptions::strict] mode, trailing bytes after the value are rejected as with
+/// [from_bytes_exact].
+pub fn from_bytes_with_options<'de, T>(b: &'de [u8], options: ParserOptions) -> Result<T>
+where
+    T: serde::de::Deserialize<'de>,
+{
+    let strict = options.strict;
+    let error_context = options.error_context;
+    let mut parser = BencodeParser::with_options(b, options);
+    let ret = serde::de::Deserialize::deserialize(&mut parser).and_then(|value| {
+        if strict {
+            check_trailing(b, parser.bytes_consumed())?;
+        }
+        Ok(value)
+    });
+    match error_context {
+        0 => ret,
+        window => ret.map_err(|e| e.with_hex_context(b, window)),
+    }
+}
+
+/// Deserialize an instance of `T` from the first bencode value of `data`, returning the bytes
+/// following that value.
+pub fn from_bytes_partial<'de, T>(data: &'de [u8]) -> Result<(T, &'de [u8])>
+where
+    T: serde::de::Deserialize<'de>,
+{
+    let mut parser = BencodeParser::new(data);
+    let value = serde::de::Deserialize::deserialize(&mut parser)?;
+    Ok((value, &data[parser.bytes_consumed()..]))
+}
+
+/// Same as [from_bytes], but fails if anything follows the value
+pub fn from_bytes_exact<'de, T>(data: &'de [u8]) -> Result<T>
+where
+    T: serde::de::Deserialize<'de>,
+{
+    let (value, remaining) = from_bytes_partial(data)?;
+    check_trailing(data, data.len() - remaining.len())?;
+    Ok(value)
+}
+
+/// Deserialize only the value at `path` in `data`, `None` if there is no such value.
+///
+/// The path is written as in [Error::Path]: dict keys separated by dots, and list indices in
+/// brackets, e.g. `info.files[0].length`. The empty path is the whole value. Values before
+/// the target are skipped token by token without being decoded, values after it aren't read
+/// at all, so picking the name of a torrent never touches its pieces. Keys holding `.` or `[`,
+/// or which aren't UTF-8, can't be written in `path`, see [from_bytes_at_path] for them.
+///
+/// ```
+/// use ytorrent::de;
+///
+/// let torrent = b"d4:infod5:filesld6:lengthi7e4:pathl1:aeee4:name3:dir6:pieces0:ee";
+/// assert_eq!(de::from_bytes_at::<&str>(torrent, "info.name").unwrap(), Some("dir"));
+/// assert_eq!(de::from_bytes_at::<u64>(torrent, "info.files[0].length").unwrap(), Some(7));
+/// assert_eq!(de::from_bytes_at::<u64>(torrent, "info.length").unwrap(), None);
+/// ```
+pub fn from_bytes_at<'de, T>(data: &'de [u8], path: &str) -> Result<Option<T>>
+where
+    T: serde::de::Deserialize<'de>,
+{
+    from_bytes_at_path(data, &path_segments(path)?)
+}
+
+/// Same as [from_bytes_at], with the path given as segments, so any key can be used.
+///
+/// ```
+/// use ytorrent::de::{self, PathSegment};
+///
+/// let data = b"d3:a.bd2:\xff\x00i1eee";
+/// let path = [PathSegment::Key(b"a.b"), PathSegment::Key(b"\xff\x00")];
+/// assert_eq!(de::from_bytes_at_path::<i64>(data, &path).unwrap(), Some(1));
+/// ```
+pub fn from_bytes_at_path<'de, T>(data: &'de [u8], path: &[PathSegment<'_>]) -> Result<Option<T>>
+where
+    T: serde::de::Deserialize<'de>,
+{
+    let mut parser = BencodeParser::new(data);
+    for segment in path {
+        let found = match *segment {
+            PathSegment::Key(key) => parser.seek_key(key)?,
+            PathSegment::Index(index) => parser.seek_index(index)?,
+        };
+        if !found {
+            return Ok(None);
+        }
+    }
+    let offset = parser.next_token_offset();
+    match serde::de::Deserialize::deserialize(&mut parser) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if path.is_empty() => Err(e),
+        Err(e) => Err(e.with_path_segment(&format_path(path), offset)),
+    }
+}
+
+/// A step of the path of [from_bytes_at_path]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// The value of a dict key
+    Key(&'a [u8]),
+    /// An item of a list
+    Index(usize),
+}
+
+/// Write `path` as [Error::Path] does
+fn format_path(path: &[PathSegment<'_>]) -> String {
+    let mut formatted = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => {
+                if !formatted.is_empty() {
+                    formatted.push('.');
+                }
+                formatted.push_str(&String::from_utf8_lossy(key));
+            }
+            PathSegment::Index(index) => formatted.push_str(&format!("[{}]", index)),
+        }
+    }
+    formatted
+}
+
+fn path_segments(path: &str) -> Result<Vec<PathSegment<'_>>> {
+    let invalid = || InvalidArgument(format!("invalid path {}", path));
+    let mut segments = vec![];
+    if path.is_empty() {
+        return Ok(segments);
+    }
+    for part in path.split('.') {
+        let (key, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        if !key.is_empty() {
+            segments.push(PathSegment::Key(key.as_bytes()));
+        } else if indices.is_empty() {
+            return Err(invalid());
+        }
+        while !indices.is_empty() {
+            let (index, rest) = indices
+                .strip_prefix('[')
+                .and_then(|indices| indices.split_once(']'))
+                .ok_or_else(invalid)?;
+            segments.push(PathSegment::Index(index.parse().map_err(|_| invalid())?));
+            indices = rest;
+        }
+    }
+    Ok(segments)
+}
+
+/// Moves to a value inside the current one, for [from_bytes_at]
+impl BencodeParser<'_> {
+    /// Move to the value of `key` if the next value is a dict with that key
+    fn seek_key(&mut self, key: &[u8]) -> Result<bool> {
+        if self.peek_token()? != Token::Dict {
+            return Ok(false);
+        }
+        self.next_token()?;
+        self.enter_nested()?;
+        loop {
+            match self.next_token()? {
+                Token::String(current) if current == key => return Ok(true),
+                Token::String(_) => self.skip_value()?,
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    /// Move to the item at `index` if the next value is a list that long
+    fn seek_index(&mut self, index: usize) -> Result<bool> {
+        if self.peek_token()? != Token::List {
+            return Ok(false);
+        }
+        self.next_token()?;
+        self.enter_nested()?;
+        for _ in 0..index {
+            if self.peek_token()? == Token::End {
+                return Ok(false);
+            }
+            self.skip_value()?;
+        }
+        Ok(self.peek_token()? != Token::End)
+    }
+}
+
+/// Deserialize an instance of `T` from the file at `path`, mapped into memory instead of read.
+///
+/// Saves copying each file when reading many. Use [MappedFile] directly for types borrowing
+/// from the data.
+///
+/// # Safety
+///
+/// The file must not be modified or truncated while it's read, see [MappedFile::open].
+#[cfg(feature = "mmap")]
+pub unsafe fn from_path_mmap<P, T>(path: P) -> Result<T>
+where
+    P: AsRef<std::path::Path>,
+    T: serde::de::DeserializeOwned,
+{
+    // SAFETY: the caller guarantees the file isn't modified while it's mapped
+    from_bytes(&unsafe { MappedFile::open(path) }?)
+}
+
+/// Fail if `data` doesn't end at `consumed`
+pub(crate) fn check_trailing(data: &[u8], consumed: usize) -> Result<()> {
+    if consumed < data.len() {
+        return Err(BencodeDecode {
+            message: format!("trailing {} bytes", data.len() - consumed),
+            offset: consumed,
+        });
+    }
+    Ok(())
+}
+
+/// Deserialize an instance of `T` from the next bencode value of `reader`.
+///
+/// The value is decoded as it is read, only the string being read is held in memory, and
+/// strings the type ignores, such as the `pieces` of an info dict read into a struct without
+/// that field, are skipped without being stored. Only the bytes of the value are consumed from
+/// the reader, so consecutive values can be read from the same stream. Wrap files and sockets
+/// in a [std::io::BufReader].
+///
+/// ```
+/// use serde::Deserialize;
+/// use ytorrent::de;
+///
+/// #[derive(Deserialize)]
+/// struct Info {
+///     name: String,
+/// }
+///
+/// let mut stream = &b"d4:name4:demo6:pieces20:aaaaaaaaaaaaaaaaaaaaei42e"[..];
+/// let info: Info = de::from_reader(&mut stream).unwrap();
+/// assert_eq!(info.name, "demo");
+/// assert_eq!(de::from_reader::<_, u8>(&mut stream).unwrap(), 42);
+/// ```
+#[cfg(feature = "std")]
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: BufRead,
+    T: serde::de::DeserializeOwned,
+{
+    from_reader_with_options(reader, ParserOptions::default())
+}
+
+/// Same as [from_reader], parsing with the given `options`.
+///
+/// The size limits are checked while reading, a huge string length fails before its content
+/// is read. [ParserOptions::size_hints] and [ParserOptions::error_context] don't apply, the
+/// data isn't known in advance.
+#[cfg(feature = "std")]
+pub fn from_reader_with_options<R, T>(reader: R, options: ParserOptions) -> Result<T>
+where
+    R: BufRead,
+    T: serde::de::DeserializeOwned,
+{
+    T::deserialize(&mut StreamDeserializer::new(reader, options))
+}
+
+/// Same as [from_reader], but the value is read into `buffer` and `T` may borrow from it.
+///
+/// Types holding `Cow<'de, str>` or `Cow<'de, [u8]>` fields marked `#[serde(borrow)]` borrow
+/// their strings from the data with [from_bytes], and from `buffer` here, instead of copying
+/// them; the same types can't be read with [from_reader], which needs owned types.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use serde::Deserialize;
+/// use ytorrent::de;
+///
+/// #[derive(Deserialize)]
+/// struct Info<'a> {
+///     #[serde(borrow)]
+///     name: Cow<'a, str>,
+/// }
+///
+/// let mut buffer = vec![];
+/// let info: Info = de::from_reader_in(&b"d4:name4:demoe"[..], &mut buffer).unwrap();
+/// assert!(matches!(info.name, Cow::Borrowed("demo")));
+/// ```
+#[cfg(feature = "std")]
+pub fn from_reader_in<'b, R, T>(reader: R, buffer: &'b mut Vec<u8>) -> Result<T>
+where
+    R: BufRead,
+    T: serde::de::Deserialize<'b>,
+{
+    let options = ParserOptions::default();
+    *buffer = read_value(reader, &options)?;
+    from_bytes_with_options(buffer, options)
+}
+
+/// Deserialize an instance of `T` from the next bencode value of an async `reader`, like
+/// [from_reader].
+///
+/// The value is scanned in the buffer of the reader, wrap sockets and files in a
+/// [tokio::io::BufReader]. Only the bytes of the value are consumed from it, so consecutive
+/// values can be read through the same reader.
+#[cfg(feature = "std")]
+pub async fn from_async_reader<R, T>(reader: R) -> Result<T>
+where
+    R: AsyncBufRead + Unpin,
+    T: serde::de::DeserializeOwned,
+{
+    from_async_reader_with_options(reader, ParserOptions::default()).await
+}
+
+/// Same as [from_async_reader], parsing with the given `options`, see
+/// [from_reader_with_options]
+#[cfg(feature = "std")]
+pub async fn from_async_reader_with_options<R, T>(reader: R, options: ParserOptions) -> Result<T>
+where
+    R: AsyncBufRead + Unpin,
+    T: serde::de::DeserializeOwned,
+{
+    let buffer = read_value_async(reader, &options).await?;
+    from_bytes_with_options(&buffer, options)
+}
+
+/// Read the raw bytes of the next complete value, the content is validated by the parser later
+#[cfg(feature = "std")]
+pub(super) fn read_value<R: BufRead>(mut reader: R, options: &ParserOptions) -> Result<Vec<u8>> {
+    let mut scanner = ValueScanner::new(options);
+    let mut step = Step::Byte;
+    loop {
+        step = match step {
+            Step::Byte => {
+                let available = reader.fill_buf()?;
+                if available.is_empty() {
+                    return Err(scanner.eof_error());
+                }
+                let (used, next) = scanner.push_bytes(available)?;
+                reader.consume(used);
+                next
+            }
+            Step::Bytes(len) => {
+                let read = (&mut reader).take(len).read_to_end(&mut scanner.buffer)?;
+                scanner.push_string(read as u64, len)?
+            }
+            Step::Done => return Ok(scanner.buffer),
+        }
+    }
+}
+
+/// Same as [read_value], for async readers
+#[cfg(feature = "std")]
+async fn read_value_async<R: AsyncBufRead + Unpin>(
+    mut reader: R,
+    options: &ParserOptions,
+) -> Result<Vec<u8>> {
+    let mut scanner = ValueScanner::new(options);
+    let mut step = Step::Byte;
+    loop {
+        step = match step {
+            Step::Byte => {
+                let available = reader.fill_buf().await?;
+                if available.is_empty() {
+                    return Err(scanner.eof_error());
+                }
+                let (used, next) = scanner.push_bytes(available)?;
+                reader.consume(used);
+                next
+            }
+            Step::Bytes(len) => {
+                let read = (&mut reader)
+                    .take(len)
+                    .read_to_end(&mut scanner.buffer)
+                    .await?;
+                scanner.push_string(read as u64, len)?
+            }
+            Step::Done => return Ok(scanner.buffer),
+        }
+    }
+}
+
+/// What [ValueScanner] needs next
+#[cfg(feature = "std")]
+enum Step {
+    Byte,
+    /// The content of a string of this length
+    Bytes(u64),
+    Done,
+}
+
+/// Finds the end of the next value of a stream, shared by the sync and async readers
+#[cfg(feature = "std")]
+struct ValueScanner<'a> {
+    options: &'a ParserOptions,
+    buffer: Vec<u8>,
+    depth: usize,
+    /// Start of the integer or string length being read, and its terminator
+    digits: Option<(usize, u8)>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> ValueScanner<'a> {
+    /// Long enough for any i64 or u64
+    const MAX_DIGITS: usize = 21;
+
+    fn new(options: &'a ParserOptions) -> Self {
+        ValueScanner {
+            options,
+            buffer: vec![],
+            depth: 0,
+            digits: None,
+        }
+    }
+
+    /// Scan buffered bytes up to the content of a string or the end of the value, returns how
+    /// many were used
+    fn push_bytes(&mut self, bytes: &[u8]) -> Result<(usize, Step)> {
+        for (used, &byte) in bytes.iter().enumerate() {
+            let next = self.push_byte(byte)?;
+            if !matches!(next, Step::Byte) {
+                return Ok((used + 1, next));
+            }
+        }
+        Ok((bytes.len(), Step::Byte))
+    }
+
+    fn push_byte(&mut self, byte: u8) -> Result<Step> {
+        let position = self.buffer.len();
+        self.options
+            .check_total_size(position as u64 + 1, position)?;
+        self.buffer.push(byte);
+        if let Some((start, terminator)) = self.digits {
+            if byte != terminator {
+                if position - start > Self::MAX_DIGITS {
+                    return Err(BencodeDecode {
+                        message: format!("expect {} but get too many digits", terminator as char),
+                        offset: start,
+                    });
+                }
+                return Ok(Step::Byte);
+            }
+            self.digits = None;
+            if terminator == b'e' {
+                return Ok(self.value_end());
+            }
+            let len = core::str::from_utf8(&self.buffer[start..position])
+                .ok()
+                .and_then(|str| str.parse::<u64>().ok())
+                .ok_or_else(|| BencodeDecode {
+                    message: "invalid integer".to_string(),
+                    offset: start,
+                })?;
+            self.options.check_string_length(len, start)?;
+            self.options
+                .check_total_size(self.buffer.len() as u64 + len, start)?;
+            return Ok(Step::Bytes(len));
+        }
+        match byte {
+            b'l' | b'd' => {
+                self.depth += 1;
+                Ok(Step::Byte)
+            }
+            b'e' if self.depth > 0 => {
+                self.depth -= 1;
+                Ok(self.value_end())
+            }
+            b'i' => {
+                self.digits = Some((position + 1, b'e'));
+                Ok(Step::Byte)
+            }
+            b'0'..=b'9' => {
+                self.digits = Some((position, b':'));
+                Ok(Step::Byte)
+            }
+            tok => Err(BencodeDecode {
+                message: format!("invalid token {}", tok as char),
+                offset: position,
+            }),
+        }
+    }
+
+    /// `read` bytes of a string of `len` bytes were appended to the buffer
+    fn push_string(&mut self, read: u64, len: u64) -> Result<Step> {
+        if read < len {
+            return Err(BencodeDecode {
+                message: "unexpected EOF when read bytes".to_string(),
+                offset: self.buffer.len(),
+            });
+        }
+        Ok(self.value_end())
+    }
+
+    fn value_end(&self) -> Step {
+        if self.depth == 0 {
+            Step::Done
+        } else {
+            Step::Byte
+        }
+    }
+
+    fn eof_error(&self) -> Error {
+        BencodeDecode {
+            message: "unexpected EOF when parse token".to_string(),
+            offset: self.buffer.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::collections::{BTreeMap, HashMap};
+
+    use log::{LevelFilter, Metadata, Record};
     use serde::{Deserialize, Serialize};
     use serde_with::{Bytes, serde_as};
     use serde_with::rust::unwrap_or_skip;
 
-    use crate::de;
+    use crate::{de, ser};
 
     struct Logger;
 
@@ -469,7 +1587,9 @@ mod tests {
         }
 
         fn log(&self, record: &Record) {
-            println!("{}", record.args())
+            if !MUTE_LOG.with(Cell::get) {
+                println!("{}", record.args())
+            }
         }
 
         fn flush(&self) {}
@@ -477,6 +1597,38 @@ mod tests {
 
     static LOGGER: Logger = Logger;
 
+    /// Counts allocations of the current thread, so tests running in parallel don't interfere
+    struct CountingAlloc;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+        static MUTE_LOG: Cell<bool> = const { Cell::new(false) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+    /// Count allocations made by `f`, log output is muted as it allocates
+    fn count_allocations<F: FnOnce()>(f: F) -> usize {
+        MUTE_LOG.with(|mute| mute.set(true));
+        let before = ALLOCATIONS.with(Cell::get);
+        f();
+        let count = ALLOCATIONS.with(Cell::get) - before;
+        MUTE_LOG.with(|mute| mute.set(false));
+        count
+    }
+
     #[derive(Deserialize, Serialize, PartialEq, Debug)]
     enum Enum {
         Unit,
@@ -567,6 +1719,38 @@ mod tests {
         enum_key: Enum,
     }
 
+    #[test]
+    fn test_de_ignored_any() {
+        #[derive(Deserialize)]
+        struct Wanted {
+            wanted: i64,
+        }
+
+        let build_data = |count: usize| {
+            let mut data = b"d4:skipl".to_vec();
+            for _ in 0..count {
+                data.extend_from_slice(b"4096:");
+                data.extend_from_slice(&[b'x'; 4096]);
+                data.extend_from_slice(b"ld1:ai1eee");
+            }
+            data.extend_from_slice(b"e6:wantedi42ee");
+            data
+        };
+        let small = build_data(1);
+        let large = build_data(1000);
+
+        let small_allocations = count_allocations(|| {
+            let ret: Wanted = de::from_bytes(&small).unwrap();
+            assert_eq!(ret.wanted, 42);
+        });
+        let large_allocations = count_allocations(|| {
+            let ret: Wanted = de::from_bytes(&large).unwrap();
+            assert_eq!(ret.wanted, 42);
+        });
+        // Skipped values don't allocate, no matter how many of them there are
+        assert_eq!(small_allocations, large_allocations);
+    }
+
     #[test]
     fn test_de() {
         log::set_logger(&LOGGER)
@@ -592,22 +1776,22 @@ mod tests {
             ]),
             enum_key: Enum::Unit,
         };
-        let bytes = serde_bencode::to_bytes(&s).unwrap();
+        let bytes = ser::to_bytes(&s).unwrap();
         let s_copy: Struct = de::from_bytes(&bytes).unwrap();
         assert_eq!(s_copy, s);
 
         s.enum_key = Enum::Int(13);
-        let bytes = serde_bencode::to_bytes(&s).unwrap();
+        let bytes = ser::to_bytes(&s).unwrap();
         let s_copy: Struct = de::from_bytes(&bytes).unwrap();
         assert_eq!(s_copy, s);
 
         s.enum_key = Enum::Str("abc".to_string());
-        let bytes = serde_bencode::to_bytes(&s).unwrap();
+        let bytes = ser::to_bytes(&s).unwrap();
         let s_copy: Struct = de::from_bytes(&bytes).unwrap();
         assert_eq!(s_copy, s);
 
         s.enum_key = Enum::Tuple((1, 2));
-        let bytes = serde_bencode::to_bytes(&s).unwrap();
+        let bytes = ser::to_bytes(&s).unwrap();
         let s_copy: Struct = de::from_bytes(&bytes).unwrap();
         assert_eq!(s_copy, s);
 
@@ -615,8 +1799,611 @@ mod tests {
             "key1".to_string(),
             ("abc".to_string(), 12),
         )]));
-        let bytes = serde_bencode::to_bytes(&s).unwrap();
+        let bytes = ser::to_bytes(&s).unwrap();
         let s_copy: Struct = de::from_bytes(&bytes).unwrap();
         assert_eq!(s_copy, s);
     }
+
+    #[test]
+    fn test_from_bytes_partial() {
+        let data = b"i42ei-7e";
+        let (first, remaining) = de::from_bytes_partial::<i64>(data).unwrap();
+        assert_eq!(first, 42);
+        assert_eq!(remaining, b"i-7e");
+        let (second, remaining) = de::from_bytes_partial::<i64>(remaining).unwrap();
+        assert_eq!(second, -7);
+        assert!(remaining.is_empty());
+        assert!(de::from_bytes_partial::<i64>(remaining).is_err());
+
+        let (list, remaining) = de::from_bytes_partial::<Vec<String>>(b"l1:ae1:b").unwrap();
+        assert_eq!(list, vec!["a"]);
+        assert_eq!(remaining, b"1:b");
+    }
+
+    #[test]
+    fn test_from_bytes_exact() {
+        assert_eq!(de::from_bytes_exact::<i64>(b"i42e").unwrap(), 42);
+        let err = de::from_bytes_exact::<i64>(b"i42ei-7e").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: trailing 4 bytes at offset 4"
+        );
+        assert_eq!(err.decode_offset(), Some(4));
+    }
+
+    #[test]
+    fn test_from_reader() {
+        let mut data = ser::to_bytes(&("abc".to_string(), vec![1, 2, 3])).unwrap();
+        data.extend_from_slice(b"d1:ai-1ee");
+        let mut cursor = std::io::Cursor::new(data.clone());
+        let first: (String, Vec<i32>) = de::from_reader(&mut cursor).unwrap();
+        assert_eq!(first, de::from_bytes::<(String, Vec<i32>)>(&data).unwrap());
+        let second: HashMap<String, i64> = de::from_reader(&mut cursor).unwrap();
+        assert_eq!(second, HashMap::from([("a".to_string(), -1)]));
+        assert!(de::from_reader::<_, i64>(&mut cursor).is_err());
+
+        let truncated = std::io::Cursor::new(b"l5:abce".to_vec());
+        assert!(de::from_reader::<_, Vec<String>>(truncated).is_err());
+    }
+
+    #[test]
+    fn test_cow() {
+        use std::borrow::Cow;
+
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            #[serde(borrow)]
+            bytes: Cow<'a, [u8]>,
+            #[serde(borrow)]
+            str: Cow<'a, str>,
+        }
+
+        let data = b"d5:bytes3:\x00\x01\x023:str4:demoe";
+        let value: Borrowed = de::from_bytes(data).unwrap();
+        assert!(matches!(value.bytes, Cow::Borrowed(&[0, 1, 2])));
+        assert!(matches!(value.str, Cow::Borrowed("demo")));
+
+        let mut cursor = std::io::Cursor::new(data.to_vec());
+        let mut buffer = vec![];
+        let value: Borrowed = de::from_reader_in(&mut cursor, &mut buffer).unwrap();
+        assert!(matches!(value.str, Cow::Borrowed("demo")));
+        assert!(de::from_reader_in::<_, Borrowed>(&mut cursor, &mut buffer).is_err());
+
+        // Without `borrow` the field is owned, and the type works with any source
+        let value: HashMap<Cow<str>, Cow<str>> = de::from_reader(&b"d1:a1:be"[..]).unwrap();
+        assert!(matches!(value.get("a"), Some(Cow::Owned(_))));
+    }
+
+    #[tokio::test]
+    async fn test_from_async_reader() {
+        let data = b"l3:abci1ei2eei-1e1:x".to_vec();
+        let mut reader = data.as_slice();
+        let first: (String, i32, i32) = de::from_async_reader(&mut reader).await.unwrap();
+        assert_eq!(first, ("abc".to_string(), 1, 2));
+        let second: i64 = de::from_async_reader(&mut reader).await.unwrap();
+        assert_eq!(second, -1);
+        assert_eq!(reader, b"1:x");
+
+        let (client, mut server) = tokio::io::duplex(4);
+        let writer = tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            let data = std::fs::read("./resources/debian-12.5.0-amd64-netinst.iso.torrent");
+            server.write_all(&data.unwrap()).await.unwrap();
+            server.write_all(b"i7e").await.unwrap();
+        });
+        let mut client = tokio::io::BufReader::new(client);
+        let meta: crate::MetaInfo = de::from_async_reader(&mut client).await.unwrap();
+        assert_eq!(meta.info.piece_length, 262144);
+        let second: i64 = de::from_async_reader(&mut client).await.unwrap();
+        assert_eq!(second, 7);
+        writer.await.unwrap();
+
+        for truncated in [b"l5:abce".as_slice(), b"li1e", b"i12", b"x"] {
+            assert!(de::from_async_reader::<_, crate::BencodeValue>(truncated)
+                .await
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_from_reader_file() {
+        let path = "./resources/debian-12.5.0-amd64-netinst.iso.torrent";
+        let file = std::fs::File::open(path).unwrap();
+        let meta: crate::MetaInfo = de::from_reader(std::io::BufReader::new(file)).unwrap();
+        let bytes = std::fs::read(path).unwrap();
+        let expected: crate::MetaInfo = de::from_bytes(&bytes).unwrap();
+        assert_eq!(meta.announce, expected.announce);
+        assert_eq!(meta.info.pieces, expected.info.pieces);
+        assert_eq!(
+            ser::to_bytes(&meta.info).unwrap(),
+            ser::to_bytes(&expected.info).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_max_depth() {
+        #[derive(Deserialize, Debug)]
+        struct Nested(#[allow(dead_code)] Vec<Nested>);
+
+        let nested = |depth: usize| [vec![b'l'; depth], vec![b'e'; depth]].concat();
+        assert!(de::from_bytes::<Nested>(&nested(64)).is_ok());
+        let err = de::from_bytes::<Nested>(&nested(100)).unwrap_err();
+        assert!(err.to_string().contains("max recursion depth 64 exceeded"));
+        assert_eq!(err.decode_offset(), Some(65));
+        assert!(matches!(
+            err.root_cause(),
+            crate::Error::DepthLimitExceeded {
+                max_depth: 64,
+                offset: 65
+            }
+        ));
+
+        assert!(crate::BencodeValue::from_bytes(&nested(64)).is_ok());
+        let err = crate::BencodeValue::from_bytes(&nested(100)).unwrap_err();
+        assert!(err.to_string().contains("max recursion depth 64 exceeded"));
+
+        let data = nested(100);
+        let mut parser = crate::BencodeParser::with_max_depth(&data, 100);
+        assert!(<Nested as Deserialize>::deserialize(&mut parser).is_ok());
+        let data = nested(4);
+        let mut parser = crate::BencodeParser::with_max_depth(&data, 3);
+        assert!(<Nested as Deserialize>::deserialize(&mut parser).is_err());
+    }
+
+    #[test]
+    fn test_strict() {
+        let strict = || crate::ParserOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let parse = |data: &[u8]| {
+            de::from_bytes_with_options::<crate::BencodeValue>(data, strict())
+                .map_err(|e| e.to_string())
+        };
+
+        assert!(parse(b"d1:ai1e1:bd1:xi0e1:yi0eee").is_ok());
+        assert_eq!(
+            parse(b"d1:bi1e1:ai2ee").unwrap_err(),
+            "Decode error: unsorted dict key a at offset 7"
+        );
+        assert_eq!(
+            parse(b"d1:ai1e1:ai2ee").unwrap_err(),
+            "Decode error: duplicate dict key a at offset 7"
+        );
+        assert!(parse(b"d1:ai1e1:bd1:yi0e1:xi0eee").is_err());
+        assert!(parse(b"i03e").is_err());
+        assert!(parse(b"i-0e").is_err());
+        assert_eq!(
+            parse(b"i1ei2e").unwrap_err(),
+            "Decode error: trailing 3 bytes at offset 3"
+        );
+        // Accepted by default, the last value wins
+        assert!(de::from_bytes::<crate::BencodeValue>(b"d1:bi1e1:ai2ee").is_ok());
+
+        // Values which are skipped are checked as well
+        #[derive(Deserialize, Debug)]
+        struct Wanted {
+            #[allow(dead_code)]
+            a: i64,
+        }
+        let data = b"d1:ai1e1:bd1:yi0e1:xi0eee";
+        assert!(de::from_bytes::<Wanted>(data).is_ok());
+        assert!(de::from_bytes_with_options::<Wanted>(data, strict()).is_err());
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        use crate::{BencodeValue, DuplicateKeyPolicy, ParserOptions};
+
+        let parse = |data: &[u8], policy: DuplicateKeyPolicy| {
+            let options = ParserOptions {
+                duplicate_keys: policy,
+                ..Default::default()
+            };
+            de::from_bytes_with_options::<BTreeMap<String, BencodeValue>>(data, options)
+        };
+
+        let data = b"d1:bi1e1:ai2e1:bli3eee";
+        let map = parse(data, DuplicateKeyPolicy::LastWins).unwrap();
+        assert_eq!(map["b"], BencodeValue::List(vec![BencodeValue::Int(3)]));
+        let map = parse(data, DuplicateKeyPolicy::FirstWins).unwrap();
+        assert_eq!(map["a"], BencodeValue::Int(2));
+        assert_eq!(map["b"], BencodeValue::Int(1));
+        assert_eq!(
+            parse(data, DuplicateKeyPolicy::Error)
+                .unwrap_err()
+                .to_string(),
+            "Decode error: duplicate dict key b at offset 13"
+        );
+        // Keys of nested dicts are independent
+        assert!(parse(b"d1:ad1:ai0ee1:bd1:ai0eee", DuplicateKeyPolicy::Error).is_ok());
+    }
+
+    #[test]
+    fn test_size_limits() {
+        use crate::{BencodeValue, ParserOptions};
+
+        let options = ParserOptions::default().with_max_string_length(4);
+        let value: BencodeValue =
+            de::from_bytes_with_options(b"l4:spam4:eggse", options.clone()).unwrap();
+        assert_eq!(
+            value,
+            BencodeValue::List(vec![
+                BencodeValue::Bytes(b"spam".to_vec()),
+                BencodeValue::Bytes(b"eggs".to_vec())
+            ])
+        );
+        let err =
+            de::from_bytes_with_options::<BencodeValue>(b"l4:spam5:eggs!e", options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: string of 5 bytes exceeds the limit of 4 at offset 7"
+        );
+
+        let options = ParserOptions::default().with_max_total_size(8);
+        assert!(de::from_bytes_with_options::<BencodeValue>(b"li1ei2ee", options.clone()).is_ok());
+        let err = de::from_bytes_with_options::<BencodeValue>(b"li1ei2ei3ee", options.clone())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: data exceeds the limit of 8 bytes at offset 7"
+        );
+
+        // A reader claiming a 4 GB string fails without reading it
+        let data = std::io::BufReader::new(std::io::Read::chain(
+            b"d4:body4294967296:".as_slice(),
+            std::io::repeat(0),
+        ));
+        let options = ParserOptions::default().with_max_string_length(1 << 20);
+        let err = de::from_reader_with_options::<_, BencodeValue>(data, options).unwrap_err();
+        assert_eq!(err.decode_offset(), Some(7));
+        let data = std::io::BufReader::new(std::io::Read::chain(
+            b"d4:body4294967296:".as_slice(),
+            std::io::repeat(0),
+        ));
+        let options = ParserOptions::default().with_max_total_size(1 << 20);
+        assert!(de::from_reader_with_options::<_, BencodeValue>(data, options).is_err());
+    }
+
+    #[test]
+    fn test_error_path() {
+        let data = b"d13:announce-listll1:aeli1eeee";
+        let err = de::from_bytes::<crate::MetaInfo>(data).unwrap_err();
+        assert_eq!(err.path(), Some("announce-list[1][0]"));
+        assert_eq!(err.decode_offset(), Some(24));
+        assert!(matches!(err.root_cause(), crate::Error::SerdeCustom(_)));
+        assert!(err.to_string().ends_with(" at path announce-list[1][0]"));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Item {
+            a: i64,
+            b: i64,
+        }
+        let err = de::from_bytes::<Vec<Item>>(b"ld1:ai1eee").unwrap_err();
+        assert_eq!(err.path(), Some("[0]"));
+        assert_eq!(
+            err.to_string(),
+            "Serde custom error: missing field `b` at path [0]"
+        );
+
+        // Errors outside of any dict or list have no path
+        let err = de::from_bytes::<i64>(b"1:a").unwrap_err();
+        assert_eq!(err.path(), None);
+    }
+
+    #[test]
+    fn test_error_context() {
+        let options = crate::ParserOptions::default().with_error_context(4);
+        let err = de::from_bytes_with_options::<crate::BencodeValue>(b"d3:keyi1x2ee", options)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: expect digit bug get x near 65 79 69 31 [78] 32 65 65 |eyi1[x]2ee| \
+             at offset 8 at path key"
+        );
+
+        let options = crate::ParserOptions::default().with_error_context(2);
+        let err = de::from_bytes_with_options::<Vec<u8>>(b"l\x00\xffe", options).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains(" near 6c [00] ff 65 |l[.].e| at offset 1"));
+    }
+
+    #[test]
+    fn test_binary_keys() {
+        let data = b"d2:\xff\x00i1e2:abi2ee";
+        let map: HashMap<Vec<u8>, i64> = de::from_bytes(data).unwrap();
+        assert_eq!(map[&vec![0xff, 0x00]], 1);
+        assert_eq!(map[&b"ab".to_vec()], 2);
+        let map: BTreeMap<[u8; 2], i64> = de::from_bytes(data).unwrap();
+        assert_eq!(map.keys().collect::<Vec<_>>(), [b"ab", b"\xff\x00"]);
+        let map: HashMap<&[u8], i64> = de::from_bytes(data).unwrap();
+        assert_eq!(map[b"\xff\x00".as_slice()], 1);
+        assert!(de::from_bytes::<HashMap<String, i64>>(data)
+            .unwrap_err()
+            .to_string()
+            .contains("UTF-8 error"));
+
+        // Unknown binary keys of a struct are skipped
+        #[derive(Deserialize, Debug)]
+        struct Wanted {
+            ab: i64,
+        }
+        assert_eq!(de::from_bytes::<Wanted>(data).unwrap().ab, 2);
+
+        let map: HashMap<crate::RawValue, i64> = de::from_bytes(data).unwrap();
+        let mut keys: Vec<_> = map.keys().map(|key| key.get()).collect();
+        keys.sort();
+        assert_eq!(keys, [b"2:ab", b"2:\xff\x00"]);
+    }
+
+    #[test]
+    fn test_flatten_borrowed() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Kind {
+            Query,
+            Response,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Header<'a> {
+            kind: Kind,
+            id: &'a str,
+            #[serde(borrow)]
+            token: &'a [u8],
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Message<'a> {
+            #[serde(borrow, flatten)]
+            header: Header<'a>,
+            #[serde(borrow)]
+            payload: &'a [u8],
+        }
+
+        let data = b"d2:id2:ab4:kind5:query7:payload3:\xff\x00\x015:token2:\x80\x81e";
+        let message: Message = de::from_bytes(data).unwrap();
+        assert_eq!(
+            message,
+            Message {
+                header: Header {
+                    kind: Kind::Query,
+                    id: "ab",
+                    token: b"\x80\x81",
+                },
+                payload: b"\xff\x00\x01",
+            }
+        );
+        // Borrowed from the data, not copied
+        let range = data.as_ptr_range();
+        assert!(range.contains(&message.header.token.as_ptr()));
+        assert!(range.contains(&message.header.id.as_ptr()));
+    }
+
+    #[test]
+    fn test_byte_array() {
+        #[derive(Deserialize, Debug)]
+        struct Peer {
+            id: [u8; 4],
+            addr: [u8; 6],
+        }
+
+        let data = b"d4:addr6:\x7f\x00\x00\x01\x1a\xe12:id4:abcde";
+        let peer: Peer = de::from_bytes(data).unwrap();
+        assert_eq!(&peer.id, b"abcd");
+        assert_eq!(peer.addr, [127, 0, 0, 1, 0x1a, 0xe1]);
+
+        let err = de::from_bytes::<[u8; 20]>(b"3:abc").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Serde custom error: expect 20 bytes for tuple but get 3 at 0"
+        );
+        // A list of integers is still accepted
+        assert_eq!(de::from_bytes::<[u8; 2]>(b"li1ei2ee").unwrap(), [1, 2]);
+    }
+
+    #[test]
+    fn test_float_strings() {
+        #[derive(Deserialize, Debug)]
+        struct Stats {
+            ratio: f64,
+            speed: f32,
+        }
+
+        let data = b"d5:ratio4:0.255:speedi2ee";
+        assert!(de::from_bytes::<Stats>(data).is_err());
+        let options = crate::ParserOptions::default().with_float_strings(true);
+        let stats: Stats = de::from_bytes_with_options(data, options.clone()).unwrap();
+        assert_eq!(stats.ratio, 0.25);
+        assert_eq!(stats.speed, 2.0);
+        assert!(de::from_bytes_with_options::<f64>(b"3:abc", options).is_err());
+    }
+
+    #[test]
+    fn test_strict_bools() {
+        assert!(de::from_bytes::<bool>(b"i2e").unwrap());
+        assert!(!de::from_bytes::<bool>(b"i-1e").unwrap());
+
+        let options = crate::ParserOptions::default().with_strict_bools(true);
+        assert!(de::from_bytes_with_options::<bool>(b"i1e", options.clone()).unwrap());
+        assert!(!de::from_bytes_with_options::<bool>(b"i0e", options.clone()).unwrap());
+        let err = de::from_bytes_with_options::<crate::Info>(
+            b"d6:lengthi1e12:piece lengthi1e6:pieces0:7:privatei2ee",
+            options,
+        )
+        .unwrap_err();
+        assert_eq!(err.path(), Some("private"));
+        assert!(err.to_string().contains("expect 0 or 1 for bool but get 2"));
+    }
+
+    #[test]
+    fn test_int_overflow() {
+        let data = b"li300ei-1ei99999999999999999999ee";
+        assert!(de::from_bytes::<Vec<u8>>(data).is_err());
+        assert!(de::from_bytes::<(u8, i8, i64)>(data).is_err());
+
+        let options =
+            crate::ParserOptions::default().with_int_overflow(crate::IntOverflowPolicy::Saturate);
+        let saturated: (u8, u8, i64) = de::from_bytes_with_options(data, options.clone()).unwrap();
+        assert_eq!(saturated, (u8::MAX, 0, i64::MAX));
+        let saturated: (i8, i8, f64) = de::from_bytes_with_options(data, options).unwrap();
+        assert_eq!(saturated, (i8::MAX, -1, 1e20));
+    }
+
+    #[test]
+    fn test_size_hints() {
+        /// The size hint of a list or dict
+        struct Hint(Option<usize>);
+
+        impl<'de> Deserialize<'de> for Hint {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct HintVisitor;
+
+                impl<'de> serde::de::Visitor<'de> for HintVisitor {
+                    type Value = Hint;
+
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("list or dict")
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Hint, A::Error>
+                    where
+                        A: serde::de::SeqAccess<'de>,
+                    {
+                        let hint = seq.size_hint();
+                        while seq.next_element::<serde::de::IgnoredAny>()?.is_some() {}
+                        Ok(Hint(hint))
+                    }
+
+                    fn visit_map<A>(self, mut map: A) -> Result<Hint, A::Error>
+                    where
+                        A: serde::de::MapAccess<'de>,
+                    {
+                        let hint = map.size_hint();
+                        while map
+                            .next_entry::<serde::de::IgnoredAny, serde::de::IgnoredAny>()?
+                            .is_some()
+                        {}
+                        Ok(Hint(hint))
+                    }
+                }
+
+                deserializer.deserialize_any(HintVisitor)
+            }
+        }
+
+        let data = b"ld1:ai1e1:bli1ei2eeeli1eeleli1ei2ei3eee";
+        let options = crate::ParserOptions::default().with_size_hints(true);
+        let hints: Vec<Hint> = de::from_bytes_with_options(data, options.clone()).unwrap();
+        let hints: Vec<_> = hints.into_iter().map(|hint| hint.0).collect();
+        assert_eq!(hints, vec![Some(2), Some(1), Some(0), Some(3)]);
+        let hints: Vec<Hint> = de::from_bytes(data).unwrap();
+        assert!(hints.iter().all(|hint| hint.0.is_none()));
+
+        let list: Vec<u8> = de::from_bytes_with_options(b"li1ei2ei3ei4ei5ee", options).unwrap();
+        assert_eq!(list.capacity(), 5);
+    }
+
+    #[test]
+    fn test_deserialize_object() {
+        use crate::{BencodeParser, Object};
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Kind {
+            File,
+            Dir { depth: u8 },
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Rest<'a> {
+            c: bool,
+            #[serde(borrow)]
+            d: &'a str,
+            e: Kind,
+        }
+
+        let data = b"d1:ai-1e1:bi2e1:ci1e1:d1:x1:ed3:Dird5:depthi3eeee";
+        let mut parser = BencodeParser::new(data);
+        let Some(Object::Dict(mut dict)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        let a = dict.find_key(b"a").unwrap().unwrap();
+        assert_eq!(i8::deserialize(a).unwrap(), -1);
+        // The value of "b" is pending, it's skipped
+        assert_eq!(dict.next_pair().unwrap().unwrap().0, b"b");
+        let rest = Object::Dict(dict);
+        assert_eq!(
+            Rest::deserialize(rest).unwrap(),
+            Rest {
+                c: true,
+                d: "x",
+                e: Kind::Dir { depth: 3 },
+            }
+        );
+        assert_eq!(parser.offset(), data.len());
+
+        let mut parser = BencodeParser::new(b"l4:Filei300ee");
+        let Some(Object::List(mut list)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        let file = list.next_object().unwrap().unwrap();
+        assert_eq!(Kind::deserialize(file).unwrap(), Kind::File);
+        let overflow = list.next_object().unwrap().unwrap();
+        assert!(u8::deserialize(overflow).is_err());
+        assert!(list.next_object().unwrap().is_none());
+        assert!(Vec::<u8>::deserialize(Object::List(list)).is_err());
+    }
+
+    #[test]
+    fn test_int_keys() {
+        let data = b"d1:0i0e2:-1i1e2:10i2ee";
+        let map: BTreeMap<i64, u8> = de::from_bytes(data).unwrap();
+        assert_eq!(map, BTreeMap::from([(-1, 1), (0, 0), (10, 2)]));
+        assert!(de::from_bytes::<HashMap<u8, u8>>(data).is_err());
+
+        let data = b"d2:07i0e2:+8i1ee";
+        assert!(de::from_bytes::<HashMap<u32, u8>>(data).is_err());
+        let options = crate::ParserOptions::default().with_lenient_int_keys(true);
+        let map: HashMap<u32, u8> = de::from_bytes_with_options(data, options.clone()).unwrap();
+        assert_eq!(map, HashMap::from([(7, 0), (8, 1)]));
+        assert!(de::from_bytes_with_options::<HashMap<u32, u8>>(b"d1:xi0ee", options).is_err());
+    }
+
+    #[test]
+    fn test_from_bytes_at() {
+        let data = b"d1:ali1ei2eld1:bi3eeee1:ci4ee";
+        assert_eq!(
+            de::from_bytes_at::<i64>(data, "a[2][0].b").unwrap(),
+            Some(3)
+        );
+        assert_eq!(de::from_bytes_at::<i64>(data, "c").unwrap(), Some(4));
+        assert_eq!(de::from_bytes_at::<i64>(data, "a[0][0]").unwrap(), None);
+        assert_eq!(de::from_bytes_at::<i64>(data, "a[3]").unwrap(), None);
+        assert_eq!(de::from_bytes_at::<i64>(data, "b").unwrap(), None);
+        assert_eq!(de::from_bytes_at::<i64>(data, "c.d").unwrap(), None);
+        let whole: BTreeMap<String, serde::de::IgnoredAny> =
+            de::from_bytes_at(data, "").unwrap().unwrap();
+        assert_eq!(whole.len(), 2);
+
+        let error = de::from_bytes_at::<String>(data, "a[2][0].b").unwrap_err();
+        assert!(
+            matches!(error, crate::Error::Path { path, offset: 16, .. } if path == "a[2][0].b")
+        );
+        for path in ["a.", ".a", "a[", "a[x]", "a[1]b"] {
+            assert!(de::from_bytes_at::<i64>(data, path).is_err(), "{}", path);
+        }
+
+        use de::PathSegment::{Index, Key};
+        let data = b"d3:a.bl0:d2:[0i5eee1:ai0ee";
+        let path = [Key(b"a.b"), Index(1), Key(b"[0")];
+        assert_eq!(de::from_bytes_at_path::<i64>(data, &path).unwrap(), Some(5));
+        assert_eq!(de::from_bytes_at::<i64>(data, "a.b[1]").unwrap(), None);
+        let error = de::from_bytes_at_path::<String>(data, &path).unwrap_err();
+        assert!(matches!(error, crate::Error::Path { path, .. } if path == "a.b[1].[0"));
+    }
 }
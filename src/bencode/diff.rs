@@ -0,0 +1,186 @@
+//! Compare two bencode documents.
+//!
+//! Example:
+//! ```
+//! use ytorrent::diff::{bencode_diff, BencodeDiffItem};
+//! use ytorrent::BencodeValue;
+//!
+//! let old = b"d7:comment3:old4:infod6:lengthi1eee";
+//! let new = b"d7:comment3:new4:infod6:lengthi1eee";
+//! let diff = bencode_diff(old, new).unwrap();
+//! assert_eq!(
+//!     diff,
+//!     vec![BencodeDiffItem::Changed {
+//!         path: vec![b"comment".to_vec()],
+//!         old: BencodeValue::Bytes(b"old".to_vec()),
+//!         new: BencodeValue::Bytes(b"new".to_vec()),
+//!     }]
+//! );
+//! ```
+use super::*;
+
+/// One difference between two bencode documents.
+///
+/// `path` is the list of dict keys leading to the value, empty for the root value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BencodeDiffItem {
+    /// The key only exists in the new document
+    Added {
+        path: Vec<Vec<u8>>,
+        value: BencodeValue,
+    },
+    /// The key only exists in the old document
+    Removed {
+        path: Vec<Vec<u8>>,
+        old_value: BencodeValue,
+    },
+    /// The value differs, lists and values of different types are reported as a whole
+    Changed {
+        path: Vec<Vec<u8>>,
+        old: BencodeValue,
+        new: BencodeValue,
+    },
+}
+
+/// Recursively compare the dicts of bencode documents `a` (old) and `b` (new).
+///
+/// Items are ordered by path.
+pub fn bencode_diff(a: &[u8], b: &[u8]) -> Result<Vec<BencodeDiffItem>> {
+    let old = BencodeValue::from_bytes(a)?;
+    let new = BencodeValue::from_bytes(b)?;
+    let mut items = vec![];
+    diff_value(&mut vec![], old, new, &mut items);
+    items.sort_by(|a, b| a.path().cmp(b.path()));
+    Ok(items)
+}
+
+fn diff_value(
+    path: &mut Vec<Vec<u8>>,
+    old: BencodeValue,
+    new: BencodeValue,
+    items: &mut Vec<BencodeDiffItem>,
+) {
+    match (old, new) {
+        (BencodeValue::Dict(mut old_dict), BencodeValue::Dict(new_dict)) => {
+            for (key, new_value) in new_dict {
+                path.push(key);
+                match old_dict.remove(path.last().unwrap()) {
+                    Some(old_value) => diff_value(path, old_value, new_value, items),
+                    None => items.push(BencodeDiffItem::Added {
+                        path: path.clone(),
+                        value: new_value,
+                    }),
+                }
+                path.pop();
+            }
+            for (key, old_value) in old_dict {
+                let mut removed_path = path.clone();
+                removed_path.push(key);
+                items.push(BencodeDiffItem::Removed {
+                    path: removed_path,
+                    old_value,
+                });
+            }
+        }
+        (old, new) => {
+            if old != new {
+                items.push(BencodeDiffItem::Changed {
+                    path: path.clone(),
+                    old,
+                    new,
+                });
+            }
+        }
+    }
+}
+
+impl BencodeDiffItem {
+    pub fn path(&self) -> &[Vec<u8>] {
+        match self {
+            BencodeDiffItem::Added { path, .. } => path,
+            BencodeDiffItem::Removed { path, .. } => path,
+            BencodeDiffItem::Changed { path, .. } => path,
+        }
+    }
+
+    /// Whether the difference is within the value at `prefix`, e.g. `&[b"info"]` to check
+    /// an edit of a torrent left its info dict, and so its info hash, alone
+    pub fn is_under(&self, prefix: &[&[u8]]) -> bool {
+        let path = self.path();
+        path.len() >= prefix.len() && path.iter().zip(prefix).all(|(key, other)| key == other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(str: &str) -> BencodeValue {
+        BencodeValue::Bytes(str.as_bytes().to_vec())
+    }
+
+    fn path(keys: &[&str]) -> Vec<Vec<u8>> {
+        keys.iter().map(|key| key.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_identical() {
+        let data = b"d8:announce3:url4:infod6:lengthi1e4:name1:aee";
+        assert!(bencode_diff(data, data).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_nested_diff() {
+        let old = b"d8:announce4:url17:comment2:hi4:infod6:lengthi1e4:name1:aee";
+        let new = b"d8:announce4:url210:created by2:me4:infod6:lengthi2e4:name1:aee";
+        assert_eq!(
+            bencode_diff(old, new).unwrap(),
+            vec![
+                BencodeDiffItem::Changed {
+                    path: path(&["announce"]),
+                    old: bytes("url1"),
+                    new: bytes("url2"),
+                },
+                BencodeDiffItem::Removed {
+                    path: path(&["comment"]),
+                    old_value: bytes("hi"),
+                },
+                BencodeDiffItem::Added {
+                    path: path(&["created by"]),
+                    value: bytes("me"),
+                },
+                BencodeDiffItem::Changed {
+                    path: path(&["info", "length"]),
+                    old: BencodeValue::Int(1),
+                    new: BencodeValue::Int(2),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_change() {
+        let diff = bencode_diff(b"d1:ai1ee", b"d1:al1:bee").unwrap();
+        assert_eq!(
+            diff,
+            vec![BencodeDiffItem::Changed {
+                path: path(&["a"]),
+                old: BencodeValue::Int(1),
+                new: BencodeValue::List(vec![bytes("b")]),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_is_under() {
+        let old = b"d8:announce4:url14:infod6:lengthi1e4:named1:ai1eeee";
+        let new = b"d8:announce4:url24:infod6:lengthi1e4:named1:ai2eeee";
+        let diff = bencode_diff(old, new).unwrap();
+        assert_eq!(diff.len(), 2);
+        assert!(!diff[0].is_under(&[b"info"]));
+        assert!(diff[1].is_under(&[b"info"]));
+        assert!(diff[1].is_under(&[b"info", b"name"]));
+        assert!(!diff[1].is_under(&[b"info", b"length"]));
+        assert!(diff[1].is_under(&[]));
+    }
+}
@@ -0,0 +1,197 @@
+//! Pull-based reading of bencode as a stream of events, borrowing from the data.
+//!
+//! A lower level than [Object], for processors that need neither serde nor owned values.
+//!
+//! Example:
+//! ```
+//! use ytorrent::{EventReader, ParseEvent};
+//!
+//! let events: Vec<_> = EventReader::new(b"d1:ai42ee")
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(
+//!     events,
+//!     vec![
+//!         (ParseEvent::DictStart, 0..1),
+//!         (ParseEvent::Key(b"a"), 1..4),
+//!         (ParseEvent::Int("42"), 4..8),
+//!         (ParseEvent::End, 8..9),
+//!     ]
+//! );
+//! ```
+use core::ops::Range;
+
+use super::*;
+use super::Error::*;
+
+/// A piece of bencode read by [EventReader]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseEvent<'de> {
+    DictStart,
+    ListStart,
+    /// A dict key, the event after it starts the value
+    Key(&'de [u8]),
+    /// The digits of an integer, as they are in the data
+    Int(&'de str),
+    StringValue(&'de [u8]),
+    /// End of the innermost list or dict
+    End,
+}
+
+/// Iterator over the events of the first bencode value of some data, with the byte range
+/// each one covers.
+///
+/// Dict keys are checked to be strings followed by a value, besides the checks of the
+/// [ParserOptions]. Iteration stops after the value is complete or at the first error.
+pub struct EventReader<'de> {
+    parser: BencodeParser<'de>,
+    /// Open containers, `Some(expect_key)` for a dict
+    containers: Vec<Option<bool>>,
+    finished: bool,
+}
+
+impl<'de> EventReader<'de> {
+    pub fn new(data: &'de [u8]) -> Self {
+        Self::with_options(data, ParserOptions::default())
+    }
+
+    pub fn with_options(data: &'de [u8], options: ParserOptions) -> Self {
+        EventReader {
+            parser: BencodeParser::with_options(data, options),
+            containers: vec![],
+            finished: false,
+        }
+    }
+
+    /// Number of lists and dicts open at this point
+    pub fn depth(&self) -> usize {
+        self.containers.len()
+    }
+
+    /// Number of bytes of the data read so far
+    pub fn bytes_consumed(&self) -> usize {
+        self.parser.bytes_consumed()
+    }
+
+    /// Continue inside the containers left open by an earlier reader, whose data ended before
+    /// the value did. See [Self::into_containers]
+    pub(super) fn resume(
+        data: &'de [u8],
+        options: ParserOptions,
+        containers: Vec<Option<bool>>,
+    ) -> Result<Self> {
+        let mut reader = Self::with_options(data, options);
+        for _ in &containers {
+            reader.parser.enter_nested()?;
+        }
+        reader.containers = containers;
+        Ok(reader)
+    }
+
+    pub(super) fn into_containers(self) -> Vec<Option<bool>> {
+        self.containers
+    }
+
+    fn next_event(&mut self) -> Result<(ParseEvent<'de>, Range<usize>)> {
+        let position = self.parser.offset;
+        let token = self.parser.read_token()?;
+        let mut is_key = false;
+        if let Some(Some(expect_key)) = self.containers.last_mut() {
+            match token {
+                Token::String(_) if *expect_key => {
+                    *expect_key = false;
+                    is_key = true;
+                }
+                Token::End if *expect_key => {}
+                Token::End => {
+                    return Err(BencodeDecode {
+                        message: "expect dict value but get End".to_string(),
+                        offset: position,
+                    })
+                }
+                _ if *expect_key => {
+                    return Err(BencodeDecode {
+                        message: format!("expect dict key but get {}", token),
+                        offset: position,
+                    })
+                }
+                _ => *expect_key = true,
+            }
+        }
+        let event = match token {
+            Token::Num(str) => ParseEvent::Int(str),
+            Token::String(bytes) if is_key => ParseEvent::Key(bytes),
+            Token::String(bytes) => ParseEvent::StringValue(bytes),
+            Token::List => {
+                self.parser.enter_nested()?;
+                self.containers.push(None);
+                ParseEvent::ListStart
+            }
+            Token::Dict => {
+                self.parser.enter_nested()?;
+                self.containers.push(Some(true));
+                ParseEvent::DictStart
+            }
+            Token::End => {
+                if self.containers.pop().is_none() {
+                    return Err(BencodeDecode {
+                        message: "expect value but get End".to_string(),
+                        offset: position,
+                    });
+                }
+                self.parser.leave_nested();
+                ParseEvent::End
+            }
+        };
+        Ok((event, position..self.parser.offset))
+    }
+}
+
+impl<'de> Iterator for EventReader<'de> {
+    type Item = Result<(ParseEvent<'de>, Range<usize>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let ret = self.next_event();
+        self.finished = ret.is_err() || self.containers.is_empty();
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let data = b"l4:spamd1:xi-1eeei1e";
+        let mut reader = EventReader::new(data);
+        let mut events = vec![];
+        while let Some(event) = reader.next() {
+            events.push((event.unwrap().0, reader.depth()));
+        }
+        assert_eq!(
+            events,
+            vec![
+                (ParseEvent::ListStart, 1),
+                (ParseEvent::StringValue(b"spam"), 1),
+                (ParseEvent::DictStart, 2),
+                (ParseEvent::Key(b"x"), 2),
+                (ParseEvent::Int("-1"), 2),
+                (ParseEvent::End, 1),
+                (ParseEvent::End, 0),
+            ]
+        );
+        assert_eq!(reader.bytes_consumed(), 17);
+    }
+
+    #[test]
+    fn test_events_stop_at_error() {
+        let mut reader = EventReader::new(b"di1ei2ee");
+        assert_eq!(reader.next().unwrap().unwrap().0, ParseEvent::DictStart);
+        assert_eq!(reader.next().unwrap().unwrap_err().decode_offset(), Some(1));
+        assert!(reader.next().is_none());
+    }
+}
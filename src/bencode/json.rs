@@ -0,0 +1,228 @@
+//! Conversion of bencode to JSON.
+//!
+//! Example:
+//! ```
+//! use ytorrent::transcode_to_json;
+//!
+//! let mut json = vec![];
+//! transcode_to_json(&b"d4:infod6:lengthi1ee4:listl1:ai-2eee"[..], &mut json).unwrap();
+//! assert_eq!(json, br#"{"info":{"length":1},"list":["a",-2]}"#);
+//! ```
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+
+use super::*;
+use super::Error::*;
+
+/// Convert the next bencode value of `reader` to JSON, written to `writer`.
+///
+/// The value is streamed from its bencode bytes, no tree of it is built. UTF-8 strings become
+/// JSON strings and other byte strings arrays of numbers, like
+/// [serde_json](https://docs.rs/serde_json) writes bytes. Dict keys which aren't UTF-8 are
+/// converted lossily.
+pub fn transcode_to_json<R: BufRead, W: Write>(reader: R, mut writer: W) -> Result<()> {
+    let data = de::read_value(reader, &ParserOptions::default())?;
+    parse_with(&data, |object| write_json(&mut writer, object))
+}
+
+/// How byte strings which aren't UTF-8 are written in JSON, see [BencodeValue::to_json]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryEncoding {
+    /// Lowercase hex
+    Hex,
+    /// Standard base64 with padding
+    Base64,
+    /// UTF-8 with invalid sequences replaced by `U+FFFD`
+    LossyUtf8,
+}
+
+impl BinaryEncoding {
+    fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            BinaryEncoding::Hex => bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            BinaryEncoding::Base64 => BASE64.encode(bytes),
+            BinaryEncoding::LossyUtf8 => String::from_utf8_lossy(bytes).into_owned(),
+        }
+    }
+}
+
+impl BencodeValue {
+    /// Convert to a JSON value.
+    ///
+    /// UTF-8 byte strings become JSON strings, other byte strings and dict keys are encoded
+    /// with `binary`.
+    pub fn to_json(&self, binary: BinaryEncoding) -> serde_json::Value {
+        let string = |bytes: &[u8]| match std::str::from_utf8(bytes) {
+            Ok(str) => str.to_string(),
+            Err(_) => binary.encode(bytes),
+        };
+        match self {
+            BencodeValue::Int(int) => serde_json::Value::from(*int),
+            BencodeValue::Bytes(bytes) => serde_json::Value::String(string(bytes)),
+            BencodeValue::List(list) => {
+                serde_json::Value::Array(list.iter().map(|item| item.to_json(binary)).collect())
+            }
+            BencodeValue::Dict(dict) => serde_json::Value::Object(
+                dict.iter()
+                    .map(|(key, value)| (string(key), value.to_json(binary)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Convert from a JSON value.
+    ///
+    /// Strings are taken as their UTF-8 bytes, strings encoded by [Self::to_json] aren't
+    /// decoded back. Booleans become `0` or `1`. `null` and numbers which aren't integers
+    /// have no bencode counterpart.
+    pub fn from_json(value: &serde_json::Value) -> Result<BencodeValue> {
+        Ok(match value {
+            serde_json::Value::Bool(bool) => BencodeValue::Int(*bool as i64),
+            serde_json::Value::Number(number) => match number.as_i64() {
+                Some(int) => BencodeValue::Int(int),
+                None => {
+                    return Err(InvalidArgument(format!(
+                        "{} is not a bencode integer",
+                        number
+                    )))
+                }
+            },
+            serde_json::Value::String(str) => BencodeValue::Bytes(str.as_bytes().to_vec()),
+            serde_json::Value::Array(array) => BencodeValue::List(
+                array
+                    .iter()
+                    .map(BencodeValue::from_json)
+                    .collect::<Result<_>>()?,
+            ),
+            serde_json::Value::Object(object) => BencodeValue::Dict(
+                object
+                    .iter()
+                    .map(|(key, value)| {
+                        Ok((key.as_bytes().to_vec(), BencodeValue::from_json(value)?))
+                    })
+                    .collect::<Result<BTreeMap<_, _>>>()?,
+            ),
+            serde_json::Value::Null => {
+                return Err(InvalidArgument(
+                    "null has no bencode counterpart".to_string(),
+                ))
+            }
+        })
+    }
+}
+
+fn write_json<W: Write>(writer: &mut W, object: Object) -> Result<()> {
+    match object {
+        Object::Int(str) => write!(writer, "{}", str)?,
+        Object::Bytes(bytes) => match std::str::from_utf8(bytes) {
+            Ok(str) => write_json_string(writer, str)?,
+            Err(_) => serde_json::to_writer(&mut *writer, bytes).map_err(json_error)?,
+        },
+        Object::List(mut decoder) => {
+            write!(writer, "[")?;
+            let mut first = true;
+            while let Some(item) = decoder.next_object()? {
+                if !first {
+                    write!(writer, ",")?;
+                }
+                first = false;
+                write_json(writer, item)?;
+            }
+            write!(writer, "]")?;
+        }
+        Object::Dict(mut decoder) => {
+            write!(writer, "{{")?;
+            let mut first = true;
+            while let Some((key, value)) = decoder.next_pair()? {
+                if !first {
+                    write!(writer, ",")?;
+                }
+                first = false;
+                write_json_string(writer, &String::from_utf8_lossy(key))?;
+                write!(writer, ":")?;
+                write_json(writer, value)?;
+            }
+            write!(writer, "}}")?;
+        }
+    }
+    Ok(())
+}
+
+fn write_json_string<W: Write>(writer: &mut W, str: &str) -> Result<()> {
+    serde_json::to_writer(writer, str).map_err(json_error)
+}
+
+fn json_error(err: serde_json::Error) -> Error {
+    match err.io_error_kind() {
+        Some(_) => Io(err.into()),
+        None => SerdeCustom(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_json<R: BufRead>(reader: R) -> Result<String> {
+        let mut json = vec![];
+        transcode_to_json(reader, &mut json)?;
+        Ok(String::from_utf8(json).unwrap())
+    }
+
+    #[test]
+    fn test_transcode_to_json() {
+        assert_eq!(to_json(b"i-42e".as_slice()).unwrap(), "-42");
+        assert_eq!(to_json(b"le".as_slice()).unwrap(), "[]");
+        assert_eq!(to_json(b"de".as_slice()).unwrap(), "{}");
+        assert_eq!(
+            to_json(b"d2:\xff\xfe3:\x00\x01\xff4:spaml1:\"lleeee".as_slice()).unwrap(),
+            r#"{"��":[0,1,255],"spam":["\"",[[]]]}"#
+        );
+        assert!(to_json(b"l1:a".as_slice()).is_err());
+        assert!(to_json(b"".as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_transcode_torrent() {
+        let file =
+            std::fs::File::open("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let json = to_json(std::io::BufReader::new(file)).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["announce"],
+            "http://bttracker.debian.org:6969/announce"
+        );
+        assert_eq!(value["info"]["pieces"].as_array().unwrap().len(), 50320);
+    }
+
+    #[test]
+    fn test_json_value() {
+        let value =
+            BencodeValue::from_bytes(b"d4:name1:a4:sizei-3e3:\xff\x00\x01l2:\xfe\x80ee").unwrap();
+        assert_eq!(
+            value.to_json(BinaryEncoding::Hex),
+            serde_json::json!({"name": "a", "size": -3, "ff0001": ["fe80"]})
+        );
+        assert_eq!(
+            value.to_json(BinaryEncoding::Base64),
+            serde_json::json!({"name": "a", "size": -3, "/wAB": ["/oA="]})
+        );
+        assert_eq!(
+            value.to_json(BinaryEncoding::LossyUtf8),
+            serde_json::json!({"name": "a", "size": -3, "\u{fffd}\u{0}\u{1}": ["\u{fffd}\u{fffd}"]})
+        );
+
+        let json = serde_json::json!({"list": [1, "x", true], "empty": {}});
+        let value = BencodeValue::from_json(&json).unwrap();
+        assert_eq!(value.to_bytes(), b"d5:emptyde4:listli1e1:xi1eee");
+        assert_eq!(
+            value.to_json(BinaryEncoding::Hex)["list"],
+            serde_json::json!([1, "x", 1])
+        );
+        assert!(BencodeValue::from_json(&serde_json::json!([1.5])).is_err());
+        assert!(BencodeValue::from_json(&serde_json::json!({"a": null})).is_err());
+    }
+}
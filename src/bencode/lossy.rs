@@ -0,0 +1,229 @@
+//! Best effort parsing of damaged bencode, e.g. truncated or corrupt torrent files.
+//!
+//! Example:
+//! ```
+//! use ytorrent::{parse_lossy, BencodeValue};
+//!
+//! // Cut in the middle of the pieces
+//! let (value, errors) = parse_lossy(b"d8:announce3:url4:infod4:name4:test6:pieces20:abc");
+//! let value = value.unwrap();
+//! assert_eq!(value.pointer("/announce"), Some(&BencodeValue::Bytes(b"url".to_vec())));
+//! assert_eq!(value.pointer("/info/name"), Some(&BencodeValue::Bytes(b"test".to_vec())));
+//! assert!(!errors.is_empty());
+//! ```
+use alloc::collections::BTreeMap;
+
+use super::*;
+use super::Error::*;
+
+/// A list or dict being read
+enum Frame {
+    List(Vec<BencodeValue>),
+    Dict {
+        dict: BTreeMap<Vec<u8>, BencodeValue>,
+        /// The key read last, waiting for its value
+        key: Option<Vec<u8>>,
+    },
+}
+
+/// Parse the first bencode value of `data`, getting past errors instead of failing on them.
+///
+/// Bytes that don't start a valid token are skipped until one does, values that can't be
+/// placed are dropped, and whatever is open at the end of the data is closed. Each problem is
+/// returned as an error, a run of skipped bytes counts as one. The value is `None` only when
+/// nothing could be read.
+pub fn parse_lossy(data: &[u8]) -> (Option<BencodeValue>, Vec<Error>) {
+    let mut parser = BencodeParser::new(data);
+    let mut errors = vec![];
+    let mut stack: Vec<Frame> = vec![];
+    // Whether the previous token failed, to report a run of bad bytes once
+    let mut skipping = false;
+    loop {
+        let position = parser.offset;
+        if position >= data.len() {
+            if !stack.is_empty() {
+                errors.push(BencodeDecode {
+                    message: format!("unexpected EOF with {} lists or dicts open", stack.len()),
+                    offset: position,
+                });
+            }
+            // Close everything open, the outermost one is the value
+            let mut value = None;
+            while let Some(frame) = stack.pop() {
+                let closed = close(frame, &mut errors, position, false);
+                value = emit(&mut stack, closed, &mut errors, position);
+            }
+            return (value, errors);
+        }
+
+        let token = match parser.read_token() {
+            Ok(token) => token,
+            Err(err) => {
+                if !skipping {
+                    errors.push(err);
+                    skipping = true;
+                    drop_pending_key(&mut stack);
+                }
+                parser.offset = resync_offset(data, position);
+                continue;
+            }
+        };
+        skipping = false;
+        let value = match token {
+            Token::Num(str) => match str.parse() {
+                Ok(int) => BencodeValue::Int(int),
+                Err(e) => {
+                    errors.push(BencodeDecode {
+                        message: format!("invalid integer {}, {:?}", str, e),
+                        offset: position,
+                    });
+                    drop_pending_key(&mut stack);
+                    continue;
+                }
+            },
+            Token::String(bytes) => BencodeValue::Bytes(bytes.to_vec()),
+            Token::List | Token::Dict if stack.len() >= BencodeParser::DEFAULT_MAX_DEPTH => {
+                errors.push(DepthLimitExceeded {
+                    max_depth: BencodeParser::DEFAULT_MAX_DEPTH,
+                    offset: position,
+                });
+                parser.offset = position + 1;
+                skipping = true;
+                continue;
+            }
+            Token::List => {
+                stack.push(Frame::List(vec![]));
+                continue;
+            }
+            Token::Dict => {
+                stack.push(Frame::Dict {
+                    dict: BTreeMap::new(),
+                    key: None,
+                });
+                continue;
+            }
+            Token::End => match stack.pop() {
+                Some(frame) => close(frame, &mut errors, position, true),
+                None => {
+                    errors.push(BencodeDecode {
+                        message: "unexpected end of list or dict".to_string(),
+                        offset: position,
+                    });
+                    continue;
+                }
+            },
+        };
+        if let Some(value) = emit(&mut stack, value, &mut errors, position) {
+            return (Some(value), errors);
+        }
+    }
+}
+
+/// Where to look for the next token after a bad one at `position`: past the end of a bad
+/// integer, the next byte otherwise
+fn resync_offset(data: &[u8], position: usize) -> usize {
+    match data[position] {
+        b'i' => {
+            memchr::memchr(b'e', &data[position..]).map_or(position + 1, |end| position + end + 1)
+        }
+        _ => position + 1,
+    }
+}
+
+/// A bad value in a dict is most likely the value of the last key, forget the key as well so
+/// the following keys and values stay paired
+fn drop_pending_key(stack: &mut [Frame]) {
+    if let Some(Frame::Dict { key, .. }) = stack.last_mut() {
+        *key = None;
+    }
+}
+
+/// The value of a closed list or dict
+fn close(frame: Frame, errors: &mut Vec<Error>, offset: usize, report: bool) -> BencodeValue {
+    match frame {
+        Frame::List(list) => BencodeValue::List(list),
+        Frame::Dict { dict, key } => {
+            if let (Some(key), true) = (key, report) {
+                errors.push(BencodeDecode {
+                    message: format!("no value for key {}", String::from_utf8_lossy(&key)),
+                    offset,
+                });
+            }
+            BencodeValue::Dict(dict)
+        }
+    }
+}
+
+/// Put a complete value into the innermost list or dict, returning it if it's the root
+fn emit(
+    stack: &mut [Frame],
+    value: BencodeValue,
+    errors: &mut Vec<Error>,
+    offset: usize,
+) -> Option<BencodeValue> {
+    match stack.last_mut() {
+        None => return Some(value),
+        Some(Frame::List(list)) => list.push(value),
+        Some(Frame::Dict { dict, key }) => match (key.take(), value) {
+            (Some(key), value) => {
+                dict.insert(key, value);
+            }
+            (None, BencodeValue::Bytes(bytes)) => *key = Some(bytes),
+            (None, _) => errors.push(BencodeDecode {
+                message: "dict key is not a string, dropped".to_string(),
+                offset,
+            }),
+        },
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offsets(errors: &[Error]) -> Vec<usize> {
+        errors.iter().filter_map(Error::decode_offset).collect()
+    }
+
+    #[test]
+    fn test_valid() {
+        let data = b"d1:ali1ei2ee1:bd1:xi0eee";
+        let (value, errors) = parse_lossy(data);
+        assert_eq!(value.unwrap(), BencodeValue::from_bytes(data).unwrap());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let (value, errors) = parse_lossy(b"d1:ali1ei2");
+        assert_eq!(value.unwrap().to_bytes(), b"d1:ali1eee");
+        assert_eq!(offsets(&errors), vec![10, 10]);
+
+        // A key without its value
+        let (value, errors) = parse_lossy(b"d1:ai1e1:b");
+        assert_eq!(value.unwrap().to_bytes(), b"d1:ai1ee");
+        assert_eq!(errors.len(), 1);
+
+        let (value, errors) = parse_lossy(b"");
+        assert!(value.is_none());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_corrupt() {
+        // Garbage between values is skipped as one error
+        let (value, errors) = parse_lossy(b"d1:ai1e??x1:bi2ee");
+        assert_eq!(value.unwrap().to_bytes(), b"d1:ai1e1:bi2ee");
+        assert_eq!(offsets(&errors), vec![7]);
+
+        // Invalid integers are dropped with their keys, keys that aren't strings are dropped
+        let (value, errors) = parse_lossy(b"d1:ai-0e1:bi1ei2e1:ci3ee");
+        assert_eq!(value.unwrap().to_bytes(), b"d1:bi1e1:ci3ee");
+        assert_eq!(offsets(&errors), vec![6, 14]);
+
+        let (value, errors) = parse_lossy(b"ei1e");
+        assert_eq!(value.unwrap(), BencodeValue::Int(1));
+        assert_eq!(offsets(&errors), vec![0]);
+    }
+}
@@ -0,0 +1,76 @@
+use std::fs::File;
+use std::ops::Deref;
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::*;
+
+/// A file mapped into memory, read by the parser without copying it.
+///
+/// Values deserialized from it with [crate::de::from_bytes] may borrow from it, as long as it
+/// lives. See [crate::de::from_path_mmap] for owned values.
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Map the file at `path` read only.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated, by this or any other process, while the
+    /// [MappedFile] lives. The bytes would change under the parser and any value borrowing
+    /// from them, which is undefined behavior, and reading a truncated part of the mapping
+    /// kills the process with `SIGBUS`. See [memmap2::Mmap::map].
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the caller guarantees the file isn't modified while it's mapped
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(MappedFile { map })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+impl AsRef<[u8]> for MappedFile {
+    fn as_ref(&self) -> &[u8] {
+        &self.map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::de;
+
+    #[test]
+    fn test_mapped_file() {
+        #[derive(Deserialize)]
+        struct Announce<'a> {
+            announce: &'a str,
+        }
+
+        let path = "./resources/debian-12.5.0-amd64-netinst.iso.torrent";
+        // SAFETY: the resources aren't modified by the tests
+        let file = unsafe { MappedFile::open(path) }.unwrap();
+        assert_eq!(&*file, std::fs::read(path).unwrap());
+        let torrent: Announce = de::from_bytes(&file).unwrap();
+        assert_eq!(
+            torrent.announce,
+            "http://bttracker.debian.org:6969/announce"
+        );
+
+        let meta: crate::MetaInfo = unsafe { de::from_path_mmap(path) }.unwrap();
+        assert_eq!(meta.info.piece_length, 262144);
+        assert!(unsafe { MappedFile::open("./resources/missing.torrent") }.is_err());
+    }
+}
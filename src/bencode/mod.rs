@@ -1,10 +1,175 @@
+pub use big_int::*;
+pub use canonical::*;
+pub use events::*;
+#[cfg(feature = "std")]
+pub use json::*;
+pub use lossy::*;
+#[cfg(feature = "mmap")]
+pub use mmap::*;
 pub use object::*;
+#[cfg(feature = "preserve_order")]
+pub use ordered::*;
+pub use owned::*;
 pub use parser::*;
+pub use pretty::*;
+pub use push::*;
+pub use raw::*;
+pub use schema::*;
+pub use ser::{to_bytes, to_writer};
+#[cfg(feature = "testing")]
+pub use testing::*;
+pub use validate::*;
+pub use value::*;
+#[cfg(not(feature = "std"))]
+pub use write::*;
 use token::*;
 
+#[cfg(feature = "std")]
+use std::io::Write;
+
 use super::common::*;
 
+/// Log through `tracing` with the "tracing" feature, through `log` otherwise
+macro_rules! trace {
+    ($($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!($($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::trace!($($arg)+);
+    }};
+}
+
+mod big_int;
+mod canonical;
 pub mod de;
+pub mod diff;
+mod events;
+#[cfg(feature = "std")]
+mod json;
+mod lossy;
+#[cfg(feature = "mmap")]
+mod mmap;
 mod object;
+#[cfg(feature = "preserve_order")]
+mod ordered;
+mod owned;
 mod parser;
+mod pretty;
+mod push;
+mod raw;
+mod schema;
+pub mod ser;
+#[cfg(feature = "std")]
+mod stream;
+#[cfg(feature = "testing")]
+mod testing;
 mod token;
+mod validate;
+mod value;
+#[cfg(not(feature = "std"))]
+mod write;
+
+#[cfg(test)]
+mod tests {
+    /// Examples from [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html#bencoding)
+    mod bep0003_compliance {
+        use std::collections::BTreeMap;
+
+        use crate::{de, BencodeValue, ParserOptions};
+
+        fn bytes(str: &str) -> BencodeValue {
+            BencodeValue::Bytes(str.as_bytes().to_vec())
+        }
+
+        #[test]
+        fn test_integer() {
+            assert_eq!(
+                BencodeValue::from_bytes(b"i42e").unwrap(),
+                BencodeValue::Int(42)
+            );
+            assert_eq!(
+                BencodeValue::from_bytes(b"i-3e").unwrap(),
+                BencodeValue::Int(-3)
+            );
+            assert_eq!(
+                BencodeValue::from_bytes(b"i0e").unwrap(),
+                BencodeValue::Int(0)
+            );
+            // "i-0e" is invalid, as are integers with leading zeros
+            assert!(BencodeValue::from_bytes(b"i-0e").is_err());
+            assert!(BencodeValue::from_bytes(b"i03e").is_err());
+        }
+
+        #[test]
+        fn test_string() {
+            assert_eq!(BencodeValue::from_bytes(b"4:spam").unwrap(), bytes("spam"));
+            assert_eq!(BencodeValue::from_bytes(b"0:").unwrap(), bytes(""));
+        }
+
+        #[test]
+        fn test_list() {
+            assert_eq!(
+                BencodeValue::from_bytes(b"l4:spam4:eggse").unwrap(),
+                BencodeValue::List(vec![bytes("spam"), bytes("eggs")])
+            );
+        }
+
+        #[test]
+        fn test_dict() {
+            assert_eq!(
+                BencodeValue::from_bytes(b"d3:cow3:moo4:spam4:eggse").unwrap(),
+                BencodeValue::Dict(BTreeMap::from([
+                    (b"cow".to_vec(), bytes("moo")),
+                    (b"spam".to_vec(), bytes("eggs")),
+                ]))
+            );
+            assert_eq!(
+                BencodeValue::from_bytes(b"d4:spaml1:a1:bee").unwrap(),
+                BencodeValue::Dict(BTreeMap::from([(
+                    b"spam".to_vec(),
+                    BencodeValue::List(vec![bytes("a"), bytes("b")])
+                )]))
+            );
+        }
+
+        #[test]
+        fn test_encode_examples() {
+            for example in [
+                b"i42e".as_slice(),
+                b"4:spam",
+                b"l4:spam4:eggse",
+                b"d3:cow3:moo4:spam4:eggse",
+            ] {
+                assert_eq!(
+                    BencodeValue::from_bytes(example).unwrap().to_bytes(),
+                    example
+                );
+            }
+        }
+
+        #[test]
+        fn test_strict_dict_keys() {
+            // Keys must appear in sorted order, and so only once
+            let strict = ParserOptions::default().with_strict(true);
+            let decode =
+                |data: &[u8]| de::from_bytes_with_options::<BencodeValue>(data, strict.clone());
+            assert!(decode(b"d3:cow3:moo4:spam4:eggse").is_ok());
+
+            let err = decode(b"d4:spam4:eggs3:cow3:mooe").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Decode error: unsorted dict key cow at offset 13"
+            );
+            let err = decode(b"d3:cow3:moo3:cow4:eggse").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "Decode error: duplicate dict key cow at offset 11"
+            );
+            // Keys are compared as raw bytes, not as text
+            assert!(decode(b"d1:Z0:1:a0:e").is_ok());
+            assert!(decode(b"d1:a0:1:Z0:e").is_err());
+            // Nested dicts are checked as well
+            assert!(decode(b"d1:ad1:b0:1:a0:ee").is_err());
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use std::fmt::{Display, Formatter};
+use core::cmp::Ordering;
+use alloc::collections::BTreeMap;
+use core::fmt::{Display, Formatter};
+use core::ops::Range;
 
 use super::*;
 
@@ -10,7 +13,7 @@ pub enum Object<'obj, 'de: 'obj> {
 }
 
 impl<'obj, 'de: 'obj> Display for Object<'obj, 'de> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             Object::Int(str) => write!(f, "Integer {str}"),
             Object::Bytes(bytes) => write!(f, "Bytes({})", bytes.len()),
@@ -27,6 +30,55 @@ impl<'obj, 'de: 'obj> Object<'obj, 'de> {
             _ => None,
         }
     }
+
+    /// Convert to an owned [BencodeValue], reading the rest of a dict or list.
+    ///
+    /// An integer out of the range of i64 fails at its offset in the data, except for a lone
+    /// [Object::Int] which doesn't know where it is: that is an [Error::SerdeCustom].
+    pub fn into_owned(self) -> Result<BencodeValue> {
+        self.into_owned_in(None)
+    }
+
+    /// Same as [Self::into_owned], `data` is what the object was parsed from
+    pub(super) fn into_owned_in(self, data: Option<&'de [u8]>) -> Result<BencodeValue> {
+        match self {
+            Object::Int(str) => parse_int(str, data).map(BencodeValue::Int),
+            Object::Bytes(bytes) => Ok(BencodeValue::Bytes(bytes.to_vec())),
+            Object::List(mut decoder) => {
+                let data = decoder.data();
+                decoder
+                    .try_fold(vec![], |mut list, item| {
+                        list.push(item.into_owned_in(Some(data))?);
+                        Ok(list)
+                    })
+                    .map(BencodeValue::List)
+            }
+            Object::Dict(mut decoder) => {
+                let data = decoder.data();
+                decoder
+                    .try_fold(BTreeMap::new(), |mut dict, key, value| {
+                        dict.insert(key.to_vec(), value.into_owned_in(Some(data))?);
+                        Ok(dict)
+                    })
+                    .map(BencodeValue::Dict)
+            }
+        }
+    }
+}
+
+/// Parse the digits of an integer token borrowed from `data`, which locates an error
+pub(super) fn parse_int(digits: &str, data: Option<&[u8]>) -> Result<i64> {
+    digits.parse().map_err(|e| {
+        let message = format!("invalid integer {}, {:?}", digits, e);
+        match data {
+            // The digits follow the `i` of the token
+            Some(data) => Error::BencodeDecode {
+                message,
+                offset: digits.as_ptr() as usize - data.as_ptr() as usize - 1,
+            },
+            None => Error::SerdeCustom(message),
+        }
+    })
 }
 
 /// Decode list struct of bencoded data
@@ -60,6 +112,73 @@ impl<'obj, 'de: 'obj> ListDecoder<'obj, 'de> {
         Ok(item)
     }
 
+    /// Same as [Self::next_object], with where the item is in the data, see
+    /// [BencodeParser::parse_spanned]
+    pub fn next_object_spanned<'item>(
+        &'item mut self,
+    ) -> Result<Option<(Object<'item, 'de>, Range<usize>)>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        let item = self.parser.parse_spanned()?;
+
+        if item.is_none() {
+            self.finished = true;
+        }
+
+        Ok(item)
+    }
+
+    /// The data the list is parsed from
+    pub(super) fn data(&self) -> &'de [u8] {
+        self.parser.data
+    }
+
+    /// Where the list is in the data. Its items are read to find the end.
+    pub fn into_span(mut self) -> Result<Range<usize>> {
+        self.consume_all()?;
+        Ok(self.start_point..self.parser.offset)
+    }
+
+    /// Call `f` with each remaining item, stopping at the first error.
+    ///
+    /// Items borrow the decoder, so it can't be an [Iterator]; this spares the
+    /// `while let Some(..) = next_object()?` loop.
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(Object<'_, 'de>) -> Result<()>,
+    {
+        self.try_fold((), |(), item| f(item))
+    }
+
+    /// Fold the remaining items into an accumulator, stopping at the first error
+    pub fn try_fold<B, F>(&mut self, init: B, mut f: F) -> Result<B>
+    where
+        F: FnMut(B, Object<'_, 'de>) -> Result<B>,
+    {
+        let mut acc = init;
+        while let Some(item) = self.next_object()? {
+            acc = f(acc, item)?;
+        }
+        Ok(acc)
+    }
+
+    /// Run `f` on the parser at the items not read yet, `f` reads up to the end of the list
+    pub(super) fn read_rest<T>(
+        &mut self,
+        f: impl FnOnce(&mut BencodeParser<'de>) -> Result<T>,
+    ) -> Result<T> {
+        if self.finished {
+            return Err(Error::SerdeCustom(format!(
+                "list at {} has been read to the end",
+                self.start_point
+            )));
+        }
+        self.finished = true;
+        f(self.parser)
+    }
+
     fn consume_all(&mut self) -> Result<()> {
         while self.next_object()?.is_some() {
             // just drop the items
@@ -81,12 +200,18 @@ impl<'obj, 'de: 'obj> Drop for ListDecoder<'obj, 'de> {
     fn drop(&mut self) {
         // we don't care about errors in drop; they'll be reported again in the parent
         self.consume_all().ok();
+        self.parser.leave_nested();
     }
 }
 
+/// A key, its value, and where the value is in the data
+pub type SpannedPair<'item, 'de> = (&'de [u8], Object<'item, 'de>, Range<usize>);
+
 pub struct DictDecoder<'obj, 'de: 'obj> {
     parser: &'obj mut BencodeParser<'de>,
     finished: bool,
+    /// The value of the last key read is not consumed yet
+    value_pending: bool,
     start_point: usize,
 }
 
@@ -96,30 +221,131 @@ impl<'obj, 'de: 'obj> DictDecoder<'obj, 'de> {
         DictDecoder {
             parser,
             finished: false,
+            value_pending: false,
             start_point,
         }
     }
 
     pub fn next_pair<'item>(&'item mut self) -> Result<Option<(&'de [u8], Object<'item, 'de>)>> {
+        match self.next_key()? {
+            Some(key) => Ok(Some((key, self.next_value()?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Find the value of `target`, skipping the values of other keys without decoding them.
+    ///
+    /// Since keys are sorted, the search stops at the first key greater than `target`, the
+    /// rest of the dict isn't read.
+    pub fn find_key<'item>(&'item mut self, target: &[u8]) -> Result<Option<Object<'item, 'de>>> {
+        while let Some(key) = self.next_key()? {
+            match key.cmp(target) {
+                Ordering::Less => self.parser.skip_value()?,
+                Ordering::Equal => return self.next_value().map(Some),
+                Ordering::Greater => {
+                    self.value_pending = true;
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Same as [Self::next_pair], with where the value is in the data, see
+    /// [BencodeParser::parse_spanned]
+    pub fn next_pair_spanned<'item>(&'item mut self) -> Result<Option<SpannedPair<'item, 'de>>> {
+        let Some(key) = self.next_key()? else {
+            return Ok(None);
+        };
+        let span = self.parser.value_span()?;
+        Ok(Some((key, self.next_value()?, span)))
+    }
+
+    /// Where the dict is in the data. Its pairs are read to find the end.
+    pub fn into_span(mut self) -> Result<Range<usize>> {
+        self.consume_all()?;
+        Ok(self.start_point..self.parser.offset)
+    }
+
+    /// The data the dict is parsed from
+    pub(super) fn data(&self) -> &'de [u8] {
+        self.parser.data
+    }
+
+    /// Read the remaining pairs into a map of owned values, with keys borrowed from the data
+    pub fn into_map(mut self) -> Result<BTreeMap<&'de [u8], BencodeValue>> {
+        let data = self.data();
+        self.try_fold(BTreeMap::new(), |mut map, key, value| {
+            map.insert(key, value.into_owned_in(Some(data))?);
+            Ok(map)
+        })
+    }
+
+    /// Call `f` with each remaining pair, stopping at the first error.
+    ///
+    /// See [ListDecoder::for_each]
+    pub fn for_each<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&'de [u8], Object<'_, 'de>) -> Result<()>,
+    {
+        self.try_fold((), |(), key, value| f(key, value))
+    }
+
+    /// Fold the remaining pairs into an accumulator, stopping at the first error
+    pub fn try_fold<B, F>(&mut self, init: B, mut f: F) -> Result<B>
+    where
+        F: FnMut(B, &'de [u8], Object<'_, 'de>) -> Result<B>,
+    {
+        let mut acc = init;
+        while let Some((key, value)) = self.next_pair()? {
+            acc = f(acc, key, value)?;
+        }
+        Ok(acc)
+    }
+
+    fn next_key(&mut self) -> Result<Option<&'de [u8]>> {
         if self.finished {
             return Ok(None);
         }
+        if self.value_pending {
+            self.value_pending = false;
+            self.parser.skip_value()?;
+        }
 
         let key = self.parser.parse()?.and_then(Object::unwrap_bytes);
-
-        if let Some(k) = key {
-            let position = self.parser.offset;
-            let v = self.parser.parse()?.ok_or(Error::BencodeDecode(format!(
-                "unexpected end of list at {}",
-                position
-            )))?;
-            Ok(Some((k, v)))
-        } else {
+        if key.is_none() {
             // We can't have gotten anything but a string, as anything else would be
             // a state error
             self.finished = true;
-            Ok(None)
         }
+        Ok(key)
+    }
+
+    fn next_value(&mut self) -> Result<Object<'_, 'de>> {
+        let position = self.parser.offset;
+        self.parser.parse()?.ok_or_else(|| Error::BencodeDecode {
+            message: "unexpected end of list".to_string(),
+            offset: position,
+        })
+    }
+
+    /// Run `f` on the parser at the pairs not read yet, `f` reads up to the end of the dict
+    pub(super) fn read_rest<T>(
+        &mut self,
+        f: impl FnOnce(&mut BencodeParser<'de>) -> Result<T>,
+    ) -> Result<T> {
+        if self.finished {
+            return Err(Error::SerdeCustom(format!(
+                "dict at {} has been read to the end",
+                self.start_point
+            )));
+        }
+        if self.value_pending {
+            self.value_pending = false;
+            self.parser.skip_value()?;
+        }
+        self.finished = true;
+        f(self.parser)
     }
 
     fn consume_all(&mut self) -> Result<()> {
@@ -143,5 +369,145 @@ impl<'obj, 'de: 'obj> Drop for DictDecoder<'obj, 'de> {
     fn drop(&mut self) {
         // we don't care about errors in drop; they'll be reported again in the parent
         self.consume_all().ok();
+        self.parser.leave_nested();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_int(data: &[u8], target: &[u8]) -> Result<Option<i64>> {
+        let mut parser = BencodeParser::new(data);
+        let Some(Object::Dict(mut decoder)) = parser.parse()? else {
+            unreachable!()
+        };
+        let ret = match decoder.find_key(target)? {
+            Some(Object::Int(str)) => Ok(Some(str.parse().unwrap())),
+            Some(other) => panic!("unexpected {}", other),
+            None => Ok(None),
+        };
+        ret
+    }
+
+    #[test]
+    fn test_find_key() {
+        let data = b"d1:ali1ee1:bi2e1:cd1:xi0ee1:di4ee";
+        assert_eq!(find_int(data, b"b").unwrap(), Some(2));
+        assert_eq!(find_int(data, b"d").unwrap(), Some(4));
+        assert_eq!(find_int(data, b"0").unwrap(), None);
+        assert_eq!(find_int(data, b"bb").unwrap(), None);
+        assert_eq!(find_int(data, b"e").unwrap(), None);
+    }
+
+    #[test]
+    fn test_find_key_stops_early() {
+        // The value of "z" is invalid, it's never read since "z" > "m"
+        let data = b"d1:ai1e1:zi--1ee";
+        assert_eq!(find_int(data, b"m").unwrap(), None);
+        assert!(find_int(data, b"z").is_err());
+    }
+
+    #[test]
+    fn test_for_each() {
+        let mut parser = BencodeParser::new(b"d1:ali1ei2ee1:bi3e1:cd1:xi4eee");
+        let Some(Object::Dict(mut decoder)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        let mut ints = vec![];
+        decoder
+            .for_each(|key, value| {
+                match value {
+                    Object::List(mut list) => list.for_each(|item| {
+                        ints.push((key, item.to_string()));
+                        Ok(())
+                    })?,
+                    other => ints.push((key, other.to_string())),
+                }
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            ints,
+            vec![
+                (b"a".as_slice(), "Integer 1".to_string()),
+                (b"a", "Integer 2".to_string()),
+                (b"b", "Integer 3".to_string()),
+                (b"c", "Dict".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_into_map() {
+        let mut parser = BencodeParser::new(b"d1:ai1e1:bl1:xe1:ci3ee");
+        let Some(Object::Dict(mut decoder)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        assert!(decoder.find_key(b"b").unwrap().is_some());
+        assert_eq!(
+            decoder.into_map().unwrap(),
+            BTreeMap::from([(b"c".as_slice(), BencodeValue::Int(3))])
+        );
+    }
+
+    #[test]
+    fn test_try_fold_stops_at_error() {
+        let mut parser = BencodeParser::new(b"li1ei2ei3ee");
+        let Some(Object::List(mut decoder)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        let mut count = 0;
+        let ret = decoder.try_fold(0, |sum, item| {
+            count += 1;
+            match item {
+                Object::Int("2") => Err(Error::InvalidArgument("2".to_string())),
+                Object::Int(str) => Ok(sum + str.parse::<i64>().unwrap()),
+                _ => unreachable!(),
+            }
+        });
+        assert!(ret.is_err());
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn test_spans() {
+        let data = b"d1:ai-1e1:bl2:xye1:cd1:di1eee";
+        let mut parser = BencodeParser::new(data);
+        let Some((Object::Dict(mut decoder), span)) = parser.parse_spanned().unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(span, 0..data.len());
+
+        let (key, _, span) = decoder.next_pair_spanned().unwrap().unwrap();
+        assert_eq!((key, &data[span]), (b"a".as_slice(), b"i-1e".as_slice()));
+        if let (_, Object::List(mut list), span) = decoder.next_pair_spanned().unwrap().unwrap() {
+            assert_eq!(&data[span], b"l2:xye");
+            let (_, span) = list.next_object_spanned().unwrap().unwrap();
+            assert_eq!(&data[span], b"2:xy");
+            assert!(list.next_object_spanned().unwrap().is_none());
+        } else {
+            unreachable!()
+        }
+
+        if let (_, Object::Dict(inner), span) = decoder.next_pair_spanned().unwrap().unwrap() {
+            assert_eq!(inner.into_span().unwrap(), span);
+        } else {
+            unreachable!()
+        }
+        assert!(decoder.next_pair_spanned().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_find_key_then_next_pair() {
+        let data = b"d1:ai1e1:cl1:xe1:di4ee";
+        let mut parser = BencodeParser::new(data);
+        let Some(Object::Dict(mut decoder)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        assert!(decoder.find_key(b"b").unwrap().is_none());
+        let (key, value) = decoder.next_pair().unwrap().unwrap();
+        assert_eq!(key, b"d");
+        assert_eq!(value.to_string(), "Integer 4");
     }
 }
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::ser::KeyOrder;
+use super::*;
+
+/// Owned bencode value keeping dict keys in the order of the data.
+///
+/// Unlike [BencodeValue], re-encoding it gives back the data it was decoded from, even if the
+/// keys weren't sorted, as long as its integers and strings were canonical:
+///
+/// ```
+/// use ytorrent::OrderedBencodeValue;
+///
+/// let data = b"d4:name1:a6:lengthi1ee";
+/// let value = OrderedBencodeValue::from_bytes(data).unwrap();
+/// assert_eq!(value.to_bytes().unwrap(), data);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderedBencodeValue {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<OrderedBencodeValue>),
+    Dict(IndexMap<Vec<u8>, OrderedBencodeValue>),
+}
+
+impl OrderedBencodeValue {
+    /// Decode exactly one bencode value from `data`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        de::from_bytes_exact(data)
+    }
+
+    /// Encode the value to bencode, dict keys are written in their order.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        ser::to_bytes_with_key_order(self, KeyOrder::Preserve)
+    }
+}
+
+/// Dict keys are sorted
+impl From<OrderedBencodeValue> for BencodeValue {
+    fn from(value: OrderedBencodeValue) -> Self {
+        match value {
+            OrderedBencodeValue::Int(int) => BencodeValue::Int(int),
+            OrderedBencodeValue::Bytes(bytes) => BencodeValue::Bytes(bytes),
+            OrderedBencodeValue::List(list) => {
+                BencodeValue::List(list.into_iter().map(Into::into).collect())
+            }
+            OrderedBencodeValue::Dict(dict) => BencodeValue::Dict(
+                dict.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<BencodeValue> for OrderedBencodeValue {
+    fn from(value: BencodeValue) -> Self {
+        match value {
+            BencodeValue::Int(int) => OrderedBencodeValue::Int(int),
+            BencodeValue::Bytes(bytes) => OrderedBencodeValue::Bytes(bytes),
+            BencodeValue::List(list) => {
+                OrderedBencodeValue::List(list.into_iter().map(Into::into).collect())
+            }
+            BencodeValue::Dict(dict) => OrderedBencodeValue::Dict(
+                dict.into_iter()
+                    .map(|(key, value)| (key, value.into()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl Serialize for OrderedBencodeValue {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            OrderedBencodeValue::Int(int) => serializer.serialize_i64(*int),
+            OrderedBencodeValue::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            OrderedBencodeValue::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for item in list {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            OrderedBencodeValue::Dict(dict) => {
+                let mut map = serializer.serialize_map(Some(dict.len()))?;
+                for (key, value) in dict {
+                    map.serialize_entry(&RawBytes(key), value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderedBencodeValue {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = OrderedBencodeValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any bencode value")
+            }
+
+            fn visit_bool<E>(self, v: bool) -> std::result::Result<Self::Value, E> {
+                Ok(OrderedBencodeValue::Int(v as i64))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> std::result::Result<Self::Value, E> {
+                Ok(OrderedBencodeValue::Int(v))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> std::result::Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                i64::try_from(v)
+                    .map(OrderedBencodeValue::Int)
+                    .map_err(|_| E::custom(format!("integer {} out of range", v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> std::result::Result<Self::Value, E> {
+                Ok(OrderedBencodeValue::Bytes(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Self::Value, E> {
+                Ok(OrderedBencodeValue::Bytes(v.to_vec()))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut list = vec![];
+                while let Some(item) = seq.next_element()? {
+                    list.push(item);
+                }
+                Ok(OrderedBencodeValue::List(list))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut dict = IndexMap::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some((key, value)) = map.next_entry()? {
+                    match key {
+                        OrderedBencodeValue::Bytes(key) => dict.insert(key, value),
+                        _ => return Err(serde::de::Error::custom("dict key is not bytes")),
+                    };
+                }
+                Ok(OrderedBencodeValue::Dict(dict))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = b"d1:bli2ei1ee1:ad1:zi0e1:y0:ee";
+        let value = OrderedBencodeValue::from_bytes(data).unwrap();
+        let OrderedBencodeValue::Dict(dict) = &value else {
+            unreachable!()
+        };
+        assert_eq!(dict.keys().collect::<Vec<_>>(), [b"b", b"a"]);
+        assert_eq!(value.to_bytes().unwrap(), data);
+
+        let sorted = BencodeValue::from(value);
+        assert_eq!(sorted.to_bytes(), b"d1:ad1:y0:1:zi0ee1:bli2ei1eee");
+        assert_eq!(
+            ser::to_bytes(&OrderedBencodeValue::from(sorted)).unwrap(),
+            b"d1:ad1:y0:1:zi0ee1:bli2ei1eee"
+        );
+    }
+}
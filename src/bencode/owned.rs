@@ -0,0 +1,212 @@
+use core::fmt::{Display, Formatter};
+
+use super::*;
+
+/// Owned bencode value which keeps the order of dict keys as they are in the data.
+///
+/// Unlike [BencodeValue], unsorted or duplicate keys are preserved, so a value can be written
+/// back byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OwnedObject {
+    Int(i64),
+    Bytes(Vec<u8>),
+    Dict(Vec<(Vec<u8>, OwnedObject)>),
+    List(Vec<OwnedObject>),
+}
+
+/// Decode the first bencode value of `data` to an [OwnedObject]
+pub fn parse_to_owned(data: &[u8]) -> Result<OwnedObject> {
+    parse_with(data, |object| OwnedObject::from_object(object, Some(data)))
+}
+
+impl OwnedObject {
+    /// Encode the object to bencode, dict keys are written in their current order
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = vec![];
+        self.write_to(&mut buffer);
+        buffer
+    }
+
+    fn write_to(&self, buffer: &mut Vec<u8>) {
+        match self {
+            OwnedObject::Int(int) => {
+                buffer.push(b'i');
+                buffer.extend_from_slice(int.to_string().as_bytes());
+                buffer.push(b'e');
+            }
+            OwnedObject::Bytes(bytes) => write_bytes(bytes, buffer),
+            OwnedObject::List(list) => {
+                buffer.push(b'l');
+                for item in list {
+                    item.write_to(buffer);
+                }
+                buffer.push(b'e');
+            }
+            OwnedObject::Dict(dict) => {
+                buffer.push(b'd');
+                for (key, value) in dict {
+                    write_bytes(key, buffer);
+                    value.write_to(buffer);
+                }
+                buffer.push(b'e');
+            }
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            OwnedObject::Int(_) => "Int",
+            OwnedObject::Bytes(_) => "Bytes",
+            OwnedObject::Dict(_) => "Dict",
+            OwnedObject::List(_) => "List",
+        }
+    }
+}
+
+impl<'obj, 'de: 'obj> TryFrom<Object<'obj, 'de>> for OwnedObject {
+    type Error = Error;
+
+    /// Reads the rest of a dict or list, errors are located as by [Object::into_owned]
+    fn try_from(object: Object<'obj, 'de>) -> Result<Self> {
+        Self::from_object(object, None)
+    }
+}
+
+impl OwnedObject {
+    /// `data` is what the object was parsed from
+    fn from_object<'de>(object: Object<'_, 'de>, data: Option<&'de [u8]>) -> Result<Self> {
+        match object {
+            Object::Int(str) => parse_int(str, data).map(OwnedObject::Int),
+            Object::Bytes(bytes) => Ok(OwnedObject::Bytes(bytes.to_vec())),
+            Object::List(mut decoder) => {
+                let data = decoder.data();
+                let mut list = vec![];
+                while let Some(item) = decoder.next_object()? {
+                    list.push(Self::from_object(item, Some(data))?);
+                }
+                Ok(OwnedObject::List(list))
+            }
+            Object::Dict(mut decoder) => {
+                let data = decoder.data();
+                let mut dict = vec![];
+                while let Some((key, value)) = decoder.next_pair()? {
+                    dict.push((key.to_vec(), Self::from_object(value, Some(data))?));
+                }
+                Ok(OwnedObject::Dict(dict))
+            }
+        }
+    }
+}
+
+impl TryFrom<OwnedObject> for i64 {
+    type Error = Error;
+
+    fn try_from(object: OwnedObject) -> Result<Self> {
+        match object {
+            OwnedObject::Int(int) => Ok(int),
+            _ => Err(Error::InvalidArgument(format!(
+                "expect Int but get {}",
+                object.kind()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<OwnedObject> for Vec<u8> {
+    type Error = Error;
+
+    fn try_from(object: OwnedObject) -> Result<Self> {
+        match object {
+            OwnedObject::Bytes(bytes) => Ok(bytes),
+            _ => Err(Error::InvalidArgument(format!(
+                "expect Bytes but get {}",
+                object.kind()
+            ))),
+        }
+    }
+}
+
+/// Rendered by [pretty_print]
+impl Display for OwnedObject {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let text = pretty_print(&self.to_bytes()).map_err(|_| core::fmt::Error)?;
+        f.write_str(&text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_to_owned() {
+        assert_eq!(parse_to_owned(b"i-42e").unwrap(), OwnedObject::Int(-42));
+        assert_eq!(
+            parse_to_owned(b"4:spam").unwrap(),
+            OwnedObject::Bytes(b"spam".to_vec())
+        );
+        assert_eq!(
+            parse_to_owned(b"li1el1:aee").unwrap(),
+            OwnedObject::List(vec![
+                OwnedObject::Int(1),
+                OwnedObject::List(vec![OwnedObject::Bytes(b"a".to_vec())])
+            ])
+        );
+        assert!(parse_to_owned(b"").is_err());
+        assert!(parse_to_owned(b"l1:a").is_err());
+        let err = parse_to_owned(b"d1:ai99999999999999999999ee").unwrap_err();
+        assert_eq!(err.decode_offset(), Some(4));
+    }
+
+    #[test]
+    fn test_owned_dict_order() {
+        // Unsorted keys are kept as they are
+        let data = b"d4:spaml1:ae3:cowi1ee";
+        let object = parse_to_owned(data).unwrap();
+        assert_eq!(
+            object,
+            OwnedObject::Dict(vec![
+                (
+                    b"spam".to_vec(),
+                    OwnedObject::List(vec![OwnedObject::Bytes(b"a".to_vec())])
+                ),
+                (b"cow".to_vec(), OwnedObject::Int(1)),
+            ])
+        );
+        assert_eq!(object.to_bytes(), data);
+        assert_eq!(object.clone(), object);
+    }
+
+    #[test]
+    fn test_owned_round_trip() {
+        for data in [
+            b"i0e".as_slice(),
+            b"0:",
+            b"le",
+            b"de",
+            b"d4:infod6:lengthi1ee4:listl1:a1:bee",
+        ] {
+            assert_eq!(parse_to_owned(data).unwrap().to_bytes(), data);
+        }
+    }
+
+    #[test]
+    fn test_owned_conversion() {
+        assert_eq!(i64::try_from(OwnedObject::Int(7)).unwrap(), 7);
+        assert!(matches!(
+            i64::try_from(OwnedObject::List(vec![])),
+            Err(Error::InvalidArgument(_))
+        ));
+        assert_eq!(
+            Vec::<u8>::try_from(OwnedObject::Bytes(b"a".to_vec())).unwrap(),
+            b"a"
+        );
+        assert!(Vec::<u8>::try_from(OwnedObject::Int(7)).is_err());
+    }
+
+    #[test]
+    fn test_owned_display() {
+        let object = parse_to_owned(b"d1:ali1eee").unwrap();
+        assert_eq!(object.to_string(), "{\n  a: [\n    1\n  ]\n}");
+    }
+}
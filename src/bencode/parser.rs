@@ -30,64 +30,440 @@
 //!     _ => unreachable!()
 //! }
 //! ```
-use std::rc::Rc;
-
-use log::trace;
+use alloc::collections::BTreeSet;
+use core::ops::Range;
 
 use super::*;
 use super::Error::*;
 
+/// Trace a token at `offset`, as fields of an event in the span of the parser with the
+/// "tracing" feature
+macro_rules! trace_token {
+    ($parser:expr, $message:literal, $token:expr, $offset:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::trace!(parent: &$parser.span, offset = $offset, token = %$token, $message);
+        #[cfg(not(feature = "tracing"))]
+        log::trace!(concat!($message, " {} at {}"), $token, $offset);
+    }};
+}
+
+/// Configuration of [BencodeParser]
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    /// Nesting level of lists and dicts allowed, deeper input is rejected instead of
+    /// overflowing the stack
+    pub max_depth: usize,
+    /// Only accept the canonical encoding: dict keys must be sorted and unique.
+    ///
+    /// Integers with leading zeros and `i-0e` are rejected in any mode.
+    pub strict: bool,
+    /// What to do with a key appearing twice in a dict, when not [Self::strict]
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// Longest byte string accepted, checked against the length prefix before the content is
+    /// read
+    pub max_string_length: Option<usize>,
+    /// Most bytes of bencode accepted for a value
+    pub max_total_size: Option<usize>,
+    /// Bytes of the data shown on each side of the failing offset in decode errors, `0` for
+    /// none. See [Error::with_hex_context]
+    pub error_context: usize,
+    /// Also accept strings such as `4:3.14` for `f32` and `f64`, bencode has no floats
+    pub float_strings: bool,
+    /// Only accept `i0e` and `i1e` for `bool`, instead of any positive integer as `true` and
+    /// anything else as `false`
+    pub strict_bools: bool,
+    /// What to do with an integer out of the range of the target type. See [BigIntString] to
+    /// keep such integers as they are.
+    pub int_overflow: IntOverflowPolicy,
+    /// Count the items of each list and dict before deserializing it, so collections are
+    /// allocated once at the right size. The count costs a scan of the container, worth it for
+    /// long lists such as the files of a large torrent.
+    pub size_hints: bool,
+    /// Also accept dict keys such as `007`, `+7` or ` 7` for integer map keys, e.g. of a
+    /// `BTreeMap<u64, V>`, instead of only the canonical digits of a bencode integer
+    pub lenient_int_keys: bool,
+}
+
+/// How the parser handles duplicate dict keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// Reject the input
+    Error,
+    /// Keep the first value, later ones are skipped
+    FirstWins,
+    /// Pass every pair on, maps keep the last value. Derived structs reject duplicate fields
+    /// anyway.
+    #[default]
+    LastWins,
+}
+
+/// How the parser handles an integer too large or too small for the target type
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntOverflowPolicy {
+    /// Reject the input
+    #[default]
+    Error,
+    /// Use the closest value of the type, e.g. `i-1e` is `0` for a `u32`
+    Saturate,
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: BencodeParser::DEFAULT_MAX_DEPTH,
+            strict: false,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            max_string_length: None,
+            max_total_size: None,
+            error_context: 0,
+            float_strings: false,
+            strict_bools: false,
+            int_overflow: IntOverflowPolicy::default(),
+            size_hints: false,
+            lenient_int_keys: false,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn with_max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn with_duplicate_keys(mut self, policy: DuplicateKeyPolicy) -> Self {
+        self.duplicate_keys = policy;
+        self
+    }
+
+    pub fn with_max_string_length(mut self, length: usize) -> Self {
+        self.max_string_length = Some(length);
+        self
+    }
+
+    pub fn with_max_total_size(mut self, size: usize) -> Self {
+        self.max_total_size = Some(size);
+        self
+    }
+
+    pub fn with_error_context(mut self, window: usize) -> Self {
+        self.error_context = window;
+        self
+    }
+
+    pub fn with_float_strings(mut self, float_strings: bool) -> Self {
+        self.float_strings = float_strings;
+        self
+    }
+
+    pub fn with_strict_bools(mut self, strict_bools: bool) -> Self {
+        self.strict_bools = strict_bools;
+        self
+    }
+
+    pub fn with_int_overflow(mut self, policy: IntOverflowPolicy) -> Self {
+        self.int_overflow = policy;
+        self
+    }
+
+    pub fn with_size_hints(mut self, size_hints: bool) -> Self {
+        self.size_hints = size_hints;
+        self
+    }
+
+    pub fn with_lenient_int_keys(mut self, lenient_int_keys: bool) -> Self {
+        self.lenient_int_keys = lenient_int_keys;
+        self
+    }
+
+    /// Check the length prefix of a string starting at `offset`
+    pub(super) fn check_string_length(&self, length: u64, offset: usize) -> Result<()> {
+        match self.max_string_length {
+            Some(max) if length > max as u64 => Err(BencodeDecode {
+                message: format!("string of {} bytes exceeds the limit of {}", length, max),
+                offset,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the data read so far, up to `end`, for the token starting at `offset`
+    pub(super) fn check_total_size(&self, end: u64, offset: usize) -> Result<()> {
+        match self.max_total_size {
+            Some(max) if end > max as u64 => Err(BencodeDecode {
+                message: format!("data exceeds the limit of {} bytes", max),
+                offset,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A list or dict being read, when dict keys are checked
+enum Container<'de> {
+    List,
+    Dict {
+        /// Used in strict mode
+        last_key: Option<&'de [u8]>,
+        /// Used to find duplicates in unsorted dicts
+        seen: BTreeSet<&'de [u8]>,
+        /// The next token is a key or the end of the dict
+        expect_key: bool,
+    },
+}
+
+/// Kinds of the containers open while a value is skipped, `true` for a dict. The innermost
+/// 128 levels are kept in bits, only deeper nesting allocates.
+#[derive(Default)]
+struct SkippedContainers {
+    len: usize,
+    bits: u128,
+    deeper: Vec<bool>,
+}
+
+impl SkippedContainers {
+    fn push(&mut self, dict: bool) {
+        if self.len < 128 {
+            self.bits = self.bits & !(1 << self.len) | (dict as u128) << self.len;
+        } else {
+            self.deeper.push(dict);
+        }
+        self.len += 1;
+    }
+
+    fn pop(&mut self) {
+        self.len -= 1;
+        if self.len >= 128 {
+            self.deeper.pop();
+        }
+    }
+
+    /// Whether the innermost container is a dict, `None` once all are closed
+    fn last(&self) -> Option<bool> {
+        match self.len {
+            0 => None,
+            len if len <= 128 => Some(self.bits >> (len - 1) & 1 == 1),
+            _ => self.deeper.last().copied(),
+        }
+    }
+}
+
+/// A dict value read through serde, see [BencodeParser::on_dict_value]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DictValueSpan<'de> {
+    pub key: &'de [u8],
+    /// Nesting level of the dict, 1 for the outermost one
+    pub depth: usize,
+    /// Where the value is in the data
+    pub span: Range<usize>,
+}
+
 pub struct BencodeParser<'de> {
     pub(super) data: &'de [u8],
     pub(super) offset: usize,
-    peeked_token: Option<Rc<Token<'de>>>,
+    peeked_token: Option<Token<'de>>,
+    /// Where the peeked token starts
+    peeked_offset: usize,
+    options: ParserOptions,
+    current_depth: usize,
+    /// Open containers, only tracked when dict keys are checked
+    containers: Vec<Container<'de>>,
+    dict_value_observer: Option<Box<dyn FnMut(DictValueSpan<'de>) + 'de>>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
 }
 
 impl<'de> BencodeParser<'de> {
+    pub const DEFAULT_MAX_DEPTH: usize = 64;
+
     pub fn new(data: &'de [u8]) -> Self {
+        Self::with_options(data, ParserOptions::default())
+    }
+
+    pub fn with_max_depth(data: &'de [u8], depth: usize) -> Self {
+        Self::with_options(data, ParserOptions::default().with_max_depth(depth))
+    }
+
+    pub fn with_options(data: &'de [u8], options: ParserOptions) -> Self {
         BencodeParser {
             data,
             offset: 0,
             peeked_token: None,
+            peeked_offset: 0,
+            options,
+            current_depth: 0,
+            containers: vec![],
+            dict_value_observer: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!("bencode_parser", size = data.len()),
+        }
+    }
+
+    pub fn options(&self) -> &ParserOptions {
+        &self.options
+    }
+
+    /// Number of bytes of the data parsed so far
+    pub fn bytes_consumed(&self) -> usize {
+        self.offset
+    }
+
+    /// Where the next value starts in the data.
+    ///
+    /// Record it before and after [Self::skip_value] to get the span of a value.
+    pub fn offset(&self) -> usize {
+        self.next_token_offset()
+    }
+
+    /// The data from [Self::offset] on
+    pub fn remaining(&self) -> &'de [u8] {
+        &self.data[self.offset()..]
+    }
+
+    /// A new parser over `range` of the data, with the same options.
+    ///
+    /// Offsets in its errors are relative to the start of `range`. Returns `None` if `range`
+    /// is out of the data.
+    pub fn subparser(&self, range: Range<usize>) -> Option<BencodeParser<'de>> {
+        self.data
+            .get(range)
+            .map(|data| Self::with_options(data, self.options.clone()))
+    }
+
+    /// Call `observer` with the span of each dict value read through serde, once the value is
+    /// deserialized.
+    ///
+    /// The raw bytes of a value, e.g. the `info` dict to hash, are then available without
+    /// parsing the data again:
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use ytorrent::BencodeParser;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Meta {
+    ///     info: Info,
+    /// }
+    /// #[derive(Deserialize)]
+    /// struct Info {
+    ///     length: u64,
+    /// }
+    ///
+    /// let data = b"d4:infod6:lengthi1eee";
+    /// let mut info = None;
+    /// let mut parser = BencodeParser::new(data);
+    /// parser.on_dict_value(|value| {
+    ///     if value.depth == 1 && value.key == b"info" {
+    ///         info = Some(value.span);
+    ///     }
+    /// });
+    /// let meta = Meta::deserialize(&mut parser).unwrap();
+    /// drop(parser);
+    /// assert_eq!(meta.info.length, 1);
+    /// assert_eq!(&data[info.unwrap()], b"d6:lengthi1ee");
+    /// ```
+    pub fn on_dict_value(&mut self, observer: impl FnMut(DictValueSpan<'de>) + 'de) {
+        self.dict_value_observer = Some(Box::new(observer));
+    }
+
+    /// Report the value of `key` starting at `start` and just read
+    pub(super) fn observe_dict_value(&mut self, key: &'de [u8], start: usize) {
+        if let Some(observer) = &mut self.dict_value_observer {
+            observer(DictValueSpan {
+                key,
+                depth: self.current_depth,
+                span: start..self.offset,
+            });
+        }
+    }
+
+    /// Enter a list or dict, paired with [Self::leave_nested]
+    pub(super) fn enter_nested(&mut self) -> Result<()> {
+        if self.current_depth >= self.options.max_depth {
+            return Err(DepthLimitExceeded {
+                max_depth: self.options.max_depth,
+                offset: self.offset,
+            });
         }
+        self.current_depth += 1;
+        Ok(())
+    }
+
+    pub(super) fn leave_nested(&mut self) {
+        self.current_depth -= 1;
+    }
+
+    /// Run `f` inside a list or dict, the depth is restored even if `f` fails
+    pub(super) fn nested<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.enter_nested()?;
+        let ret = f(self);
+        self.leave_nested();
+        ret
     }
 
     /// Peek the next token, but not consume it.
     ///
     /// See [Self::next_token]
-    pub(super) fn peek_token(&mut self) -> Result<Rc<Token<'de>>> {
+    pub(super) fn peek_token(&mut self) -> Result<Token<'de>> {
         // Consume the cached token first
-        if let Some(token) = &self.peeked_token {
-            trace!("peek reused token: {}", token);
-            return Ok(token.clone());
-        }
-        self.next_raw_token().map(|token| {
-            trace!("peek token: {}", token);
-            let token = Rc::new(token);
-            self.peeked_token = Some(token.clone());
-            token
-        })
+        if let Some(token) = self.peeked_token {
+            trace_token!(self, "peek reused token", token, self.peeked_offset);
+            return Ok(token);
+        }
+        self.peeked_offset = self.offset;
+        let token = self.read_token()?;
+        trace_token!(self, "peek token", token, self.peeked_offset);
+        self.peeked_token = Some(token);
+        Ok(token)
     }
 
     /// Consume next token.
     ///
     /// See [`Self::peek_token`]
-    pub(super) fn next_token(&mut self) -> Result<Rc<Token<'de>>> {
+    pub(super) fn next_token(&mut self) -> Result<Token<'de>> {
         // Consume the cached token first
         if let Some(token) = self.peeked_token.take() {
-            trace!("reused token: {}", token);
+            trace_token!(self, "reused token", token, self.peeked_offset);
             return Ok(token);
         }
-        self.next_raw_token().map(Rc::new)
+        self.next_raw_token()
     }
 
     /// Try to parse next token
     fn next_raw_token(&mut self) -> Result<Token<'de>> {
         let position = self.offset;
-        match self.take_byte().ok_or(BencodeDecode(format!(
-            "unexpected EOF at {} when parse token",
-            position
-        )))? as char
+        let token = self.read_token()?;
+        trace_token!(self, "parsed token", token, position);
+        Ok(token)
+    }
+
+    /// Same as [Self::next_raw_token], but without logging
+    pub(super) fn read_token(&mut self) -> Result<Token<'de>> {
+        let check_keys =
+            self.options.strict || self.options.duplicate_keys != DuplicateKeyPolicy::LastWins;
+        loop {
+            let position = self.offset;
+            let token = self.scan_token()?;
+            self.options
+                .check_total_size(self.offset as u64, position)?;
+            if !check_keys || self.check_token(&token, position)? {
+                return Ok(token);
+            }
+            // A duplicate key to ignore, drop its value as well
+            self.skip_value()?;
+        }
+    }
+
+    fn scan_token(&mut self) -> Result<Token<'de>> {
+        match self.take_byte().ok_or_else(|| BencodeDecode {
+            message: "unexpected EOF when parse token".to_string(),
+            offset: self.offset,
+        })? as char
         {
             'e' => Ok(Token::End),
             'l' => Ok(Token::List),
@@ -97,21 +473,89 @@ impl<'de> BencodeParser<'de> {
                 self.offset -= 1;
                 Ok(Token::String(self.take_bytes()?))
             }
-            tok => Err(BencodeDecode(format!(
-                "invalid token {} at {}",
-                tok, self.offset
-            ))),
+            tok => Err(BencodeDecode {
+                message: format!("invalid token {}", tok),
+                offset: self.offset - 1,
+            }),
         }
-        .map(|token| {
-            trace!("parsed token: {}", token);
-            token
-        })
+    }
+
+    /// Check the dict keys according to [ParserOptions].
+    ///
+    /// It's done per token, so values skipped without decoding are checked as well. Returns
+    /// `false` for a duplicate key that should be ignored.
+    fn check_token(&mut self, token: &Token<'de>, position: usize) -> Result<bool> {
+        let strict = self.options.strict;
+        let policy = self.options.duplicate_keys;
+        if let Some(Container::Dict {
+            last_key,
+            seen,
+            expect_key,
+        }) = self.containers.last_mut()
+        {
+            if *expect_key {
+                match token {
+                    Token::String(key) => {
+                        *expect_key = false;
+                        let reason = if strict {
+                            let reason = match last_key {
+                                Some(last_key) if key == last_key => Some("duplicate"),
+                                Some(last_key) if key < last_key => Some("unsorted"),
+                                _ => None,
+                            };
+                            *last_key = Some(key);
+                            reason
+                        } else if seen.insert(key) {
+                            None
+                        } else if policy == DuplicateKeyPolicy::FirstWins {
+                            return Ok(false);
+                        } else {
+                            Some("duplicate")
+                        };
+                        return match reason {
+                            Some(reason) => Err(BencodeDecode {
+                                message: format!(
+                                    "{} dict key {}",
+                                    reason,
+                                    String::from_utf8_lossy(key)
+                                ),
+                                offset: position,
+                            }),
+                            None => Ok(true),
+                        };
+                    }
+                    Token::End => {}
+                    other => {
+                        return Err(BencodeDecode {
+                            message: format!("expect dict key but get {}", other),
+                            offset: position,
+                        })
+                    }
+                }
+            } else {
+                // The token starts the value, a key follows it
+                *expect_key = true;
+            }
+        }
+        match token {
+            Token::List => self.containers.push(Container::List),
+            Token::Dict => self.containers.push(Container::Dict {
+                last_key: None,
+                seen: BTreeSet::new(),
+                expect_key: true,
+            }),
+            Token::End => {
+                self.containers.pop();
+            }
+            Token::Num(_) | Token::String(_) => {}
+        }
+        Ok(true)
     }
 
     /// Except next token is "d"
     pub(super) fn expect_dict_begin(&mut self, log: &str) -> Result<()> {
         let position = self.offset;
-        match &*self.next_token()? {
+        match self.next_token()? {
             Token::Dict => Ok(()),
             other => Err(SerdeCustom(format!(
                 "expect dict for {} but get {} at {}",
@@ -123,7 +567,7 @@ impl<'de> BencodeParser<'de> {
     /// Except next token is "l"
     pub(super) fn expect_list_begin(&mut self, log: &str) -> Result<()> {
         let position = self.offset;
-        match &*self.next_token()? {
+        match self.next_token()? {
             Token::List => Ok(()),
             other => Err(SerdeCustom(format!(
                 "expect list for {} but get {} at {}",
@@ -135,7 +579,7 @@ impl<'de> BencodeParser<'de> {
     /// Except next token is "e"
     pub(super) fn expect_end(&mut self, log: &str) -> Result<()> {
         let position = self.offset;
-        match &*self.next_token()? {
+        match self.next_token()? {
             Token::End => Ok(()),
             other => Err(SerdeCustom(format!(
                 "expect end for {} but get {} at {}",
@@ -151,6 +595,102 @@ impl<'de> BencodeParser<'de> {
         Ok(())
     }
 
+    /// Consume the next complete value, without building any [Object].
+    ///
+    /// Nothing is allocated, however large the value is. The value is still checked as it is
+    /// read, dict keys must be strings, and it's checked against the [ParserOptions] as well.
+    pub fn skip_value(&mut self) -> Result<()> {
+        let position = self.offset;
+        let mut containers = SkippedContainers::default();
+        // Only the first token may be a peeked one, read the rest directly. Skipped tokens
+        // aren't logged, formatting the log allocates.
+        match self.next_token()? {
+            Token::List => containers.push(false),
+            Token::Dict => containers.push(true),
+            Token::Num(_) | Token::String(_) => return Ok(()),
+            Token::End => {
+                return Err(BencodeDecode {
+                    message: "expect value but get End".to_string(),
+                    offset: position,
+                })
+            }
+        }
+        // The innermost dict expects a key or its end next
+        let mut expect_key = true;
+        while let Some(in_dict) = containers.last() {
+            let position = self.offset;
+            let token = self.read_token()?;
+            if in_dict {
+                match (&token, expect_key) {
+                    (Token::String(_), true) => {
+                        expect_key = false;
+                        continue;
+                    }
+                    (Token::End, true) => {}
+                    (Token::End, false) => {
+                        return Err(BencodeDecode {
+                            message: "expect dict value but get End".to_string(),
+                            offset: position,
+                        })
+                    }
+                    (other, true) => {
+                        return Err(BencodeDecode {
+                            message: format!("expect dict key but get {}", other),
+                            offset: position,
+                        })
+                    }
+                    // A value, a container sets it back when it ends
+                    (_, false) => expect_key = true,
+                }
+            }
+            match token {
+                Token::List => containers.push(false),
+                Token::Dict => {
+                    containers.push(true);
+                    expect_key = true;
+                }
+                Token::End => {
+                    containers.pop();
+                    expect_key = true;
+                }
+                Token::Num(_) | Token::String(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of values in the list or dict just entered, `None` if [ParserOptions::size_hints]
+    /// is off or the container is invalid. A dict has two per pair.
+    pub(super) fn count_items(&self) -> Option<usize> {
+        if !self.options.size_hints {
+            return None;
+        }
+        let data = &self.data[self.next_token_offset()..];
+        let mut scanner = BencodeParser::new(data);
+        let mut count = 0;
+        // Only an end starts with `e`
+        while *data.get(scanner.offset)? != b'e' {
+            scanner.skip_value().ok()?;
+            count += 1;
+        }
+        Some(count)
+    }
+
+    /// Consume the next complete value, returning its bytes as they are in the data
+    pub(super) fn take_raw_value(&mut self) -> Result<&'de [u8]> {
+        let start = self.next_token_offset();
+        self.skip_value()?;
+        Ok(&self.data[start..self.offset])
+    }
+
+    /// Where the next token starts, the peeked one if any
+    pub(super) fn next_token_offset(&self) -> usize {
+        match self.peeked_token {
+            Some(_) => self.peeked_offset,
+            None => self.offset,
+        }
+    }
+
     /// Move forward for one byte
     fn take_byte(&mut self) -> Option<u8> {
         if self.offset < self.data.len() {
@@ -175,7 +715,35 @@ impl<'de> BencodeParser<'de> {
     }
 
     /// Move forward to next `expected_terminator`
-    fn take_int(&mut self, expected_terminator: char) -> Result<&'de str> {
+    pub(super) fn take_int(&mut self, expected_terminator: char) -> Result<&'de str> {
+        // Find the terminator with memchr and check the digits in one pass. Anything unusual,
+        // including every invalid integer, goes through the state machine which reports where
+        // the integer goes wrong.
+        let rest = &self.data[self.offset..];
+        let Some(end) = memchr::memchr(expected_terminator as u8, rest) else {
+            return self.take_int_slow(expected_terminator);
+        };
+        let digits = match &rest[..end] {
+            [b'-', digits @ ..] => digits,
+            digits => digits,
+        };
+        let canonical = match digits {
+            [] => false,
+            [b'0'] => digits.len() == end,
+            [b'0', ..] => false,
+            _ => digits.iter().all(u8::is_ascii_digit),
+        };
+        if !canonical {
+            return self.take_int_slow(expected_terminator);
+        }
+        // Only ASCII digits and a sign
+        let str = unsafe { core::str::from_utf8_unchecked(&rest[..end]) };
+        self.offset += end + 1;
+        Ok(str)
+    }
+
+    /// Same as [Self::take_int], one byte at a time
+    fn take_int_slow(&mut self, expected_terminator: char) -> Result<&'de str> {
         enum State {
             Start,
             Sign,
@@ -198,10 +766,10 @@ impl<'de> BencodeParser<'de> {
                     } else if ('1'..='9').contains(&c) {
                         state = State::Digits;
                     } else {
-                        return Err(BencodeDecode(format!(
-                            "expect '-' or digit but get {} , at {}",
-                            c, cur_position
-                        )));
+                        return Err(BencodeDecode {
+                            message: format!("expect '-' or digit but get {}", c),
+                            offset: cur_position,
+                        });
                     }
                 }
                 State::Zero => {
@@ -209,20 +777,20 @@ impl<'de> BencodeParser<'de> {
                         success = true;
                         break;
                     } else {
-                        return Err(BencodeDecode(format!(
-                            "expect {} but get {}, at {}",
-                            expected_terminator, c, cur_position
-                        )));
+                        return Err(BencodeDecode {
+                            message: format!("expect {} but get {}", expected_terminator, c),
+                            offset: cur_position,
+                        });
                     }
                 }
                 State::Sign => {
                     if ('1'..='9').contains(&c) {
                         state = State::Digits;
                     } else {
-                        return Err(BencodeDecode(format!(
-                            "except sign but get {}, at {}",
-                            c, cur_position
-                        )));
+                        return Err(BencodeDecode {
+                            message: format!("except sign but get {}", c),
+                            offset: cur_position,
+                        });
                     }
                 }
                 State::Digits => {
@@ -232,10 +800,10 @@ impl<'de> BencodeParser<'de> {
                         success = true;
                         break;
                     } else {
-                        return Err(BencodeDecode(format!(
-                            "expect digit bug get {}, at {}",
-                            c, cur_position
-                        )));
+                        return Err(BencodeDecode {
+                            message: format!("expect digit bug get {}", c),
+                            offset: cur_position,
+                        });
                     }
                 }
             }
@@ -243,12 +811,15 @@ impl<'de> BencodeParser<'de> {
         }
 
         if !success {
-            return Err(BencodeDecode(format!("unexpected EOF at {}", cur_position)));
+            return Err(BencodeDecode {
+                message: "unexpected EOF".to_string(),
+                offset: cur_position,
+            });
         }
 
         let slice = &self.data[self.offset..cur_position];
         self.offset = cur_position + 1;
-        let str = unsafe { std::str::from_utf8_unchecked(slice) };
+        let str = unsafe { core::str::from_utf8_unchecked(slice) };
         Ok(str)
     }
 
@@ -270,23 +841,178 @@ impl<'de> BencodeParser<'de> {
     fn take_bytes(&mut self) -> Result<&'de [u8]> {
         let cur_position = self.offset;
         let int_str = self.take_int(':')?;
-        let len = int_str
-            .parse::<usize>()
-            .map_err(|_| BencodeDecode(format!("invalid integer at {}", cur_position)))?;
-        self.take_chunk(len).ok_or(BencodeDecode(format!(
-            "unexpected EOF at {} when read bytes",
-            self.offset
-        )))
+        let len = int_str.parse::<usize>().map_err(|_| BencodeDecode {
+            message: "invalid integer".to_string(),
+            offset: cur_position,
+        })?;
+        self.options.check_string_length(len as u64, cur_position)?;
+        self.options
+            .check_total_size(self.offset as u64 + len as u64, cur_position)?;
+        self.take_chunk(len).ok_or_else(|| BencodeDecode {
+            message: "unexpected EOF when read bytes".to_string(),
+            offset: self.offset,
+        })
+    }
+
+    /// Same as [Self::parse], with where the value is in the data.
+    ///
+    /// The end of a list or dict is found by scanning it first, so reading nested values with
+    /// spans scans them once per level.
+    pub fn parse_spanned<'obj>(
+        &'obj mut self,
+    ) -> Result<Option<(Object<'obj, 'de>, Range<usize>)>> {
+        // Only valid at a value, an end is left for `parse` to report
+        let span = self.value_span();
+        match self.parse()? {
+            Some(object) => Ok(Some((object, span?))),
+            None => Ok(None),
+        }
+    }
+
+    /// Where the next value is, without consuming it
+    pub(super) fn value_span(&self) -> Result<Range<usize>> {
+        let start = self.next_token_offset();
+        let mut scanner = BencodeParser::with_options(self.data, self.options.clone());
+        scanner.offset = start;
+        scanner.skip_value()?;
+        Ok(start..scanner.offset)
     }
 
     /// Parse raw bencode bytes to [Object].
     pub fn parse<'obj>(&'obj mut self) -> Result<Option<Object<'obj, 'de>>> {
-        match *self.next_token()? {
-            Token::List => Ok(Some(Object::List(ListDecoder::new(self)))),
-            Token::Dict => Ok(Some(Object::Dict(DictDecoder::new(self)))),
+        match self.next_token()? {
+            // The decoders leave the nesting level when dropped
+            Token::List => {
+                self.enter_nested()?;
+                Ok(Some(Object::List(ListDecoder::new(self))))
+            }
+            Token::Dict => {
+                self.enter_nested()?;
+                Ok(Some(Object::Dict(DictDecoder::new(self))))
+            }
             Token::Num(str) => Ok(Some(Object::Int(str))),
             Token::String(bytes) => Ok(Some(Object::Bytes(bytes))),
             Token::End => Ok(None),
         }
     }
 }
+
+/// Parse the first value of `data` and hand it to `f`, failing if `data` starts with an end.
+///
+/// The decoders of a list or dict borrow the parser and read the rest of it when dropped, so
+/// they must go before the parser does. A temporary in the tail expression of a block outlives
+/// the locals, so the result of `f` is bound to a variable before it's returned.
+pub(super) fn parse_with<'de, T, F>(data: &'de [u8], f: F) -> Result<T>
+where
+    F: FnOnce(Object<'_, 'de>) -> Result<T>,
+{
+    let mut parser = BencodeParser::new(data);
+    let offset = parser.offset();
+    let ret = match parser.parse()? {
+        Some(object) => f(object),
+        None => Err(BencodeDecode {
+            message: "expect value but get End".to_string(),
+            offset,
+        }),
+    };
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subparser() {
+        let data = b"d4:infod6:lengthi1ee4:name1:xe";
+        let mut parser = BencodeParser::new(data);
+        parser.expect_dict_begin("test").unwrap();
+        parser.skip_value().unwrap();
+        let start = parser.offset();
+        parser.skip_value().unwrap();
+        let info = start..parser.offset();
+        assert_eq!(parser.remaining(), b"4:name1:xe");
+
+        let mut subparser = parser.subparser(info).unwrap();
+        assert_eq!(subparser.remaining(), b"d6:lengthi1ee");
+        let Some(Object::Dict(mut decoder)) = subparser.parse().unwrap() else {
+            unreachable!()
+        };
+        assert!(matches!(
+            decoder.find_key(b"length").unwrap(),
+            Some(Object::Int("1"))
+        ));
+        assert!(parser.subparser(0..data.len() + 1).is_none());
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut parser = BencodeParser::new(b"i1eld1:xl1:yeeei2e4:spame");
+        parser.skip_value().unwrap();
+        parser.skip_value().unwrap();
+        assert_eq!(parser.bytes_consumed(), 15);
+        assert!(matches!(parser.parse().unwrap(), Some(Object::Int("2"))));
+        parser.skip_value().unwrap();
+        assert!(parser.skip_value().is_err());
+
+        let mut parser = BencodeParser::new(b"ld1:xi-0eee");
+        assert_eq!(parser.skip_value().unwrap_err().decode_offset(), Some(7));
+
+        // Dict keys are strings, even when keys aren't otherwise checked
+        let err = BencodeParser::new(b"di1ei2ee").skip_value().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: expect dict key but get Num(\"1\") at offset 1"
+        );
+        let mut parser = BencodeParser::new(b"ld1:ad1:bi1ee1:cli1eei2eleee");
+        assert_eq!(parser.skip_value().unwrap_err().decode_offset(), Some(21));
+        let mut parser = BencodeParser::new(b"d1:ad1:bee");
+        assert_eq!(parser.skip_value().unwrap_err().decode_offset(), Some(8));
+        // Deeper than the bits of the skipped containers
+        let nested = [b"d1:a".repeat(200), b"1:b".to_vec(), b"e".repeat(200)].concat();
+        let mut parser = BencodeParser::new(&nested);
+        parser.skip_value().unwrap();
+        assert_eq!(parser.bytes_consumed(), nested.len());
+        let nested = [b"d1:a".repeat(200), b"1:bi1ei2e".to_vec(), b"e".repeat(200)].concat();
+        assert!(BencodeParser::new(&nested).skip_value().is_err());
+    }
+
+    #[test]
+    fn test_take_int_matches_slow_path() {
+        for data in [
+            "0e",
+            "-0e",
+            "00e",
+            "01e",
+            "-01e",
+            "42e",
+            "-42e",
+            "e",
+            "-e",
+            "4-2e",
+            "42",
+            "4x2e",
+            "-",
+            "",
+            "0",
+            "123456789012345678901234567890e",
+            "42:",
+            "0:",
+        ] {
+            for terminator in ['e', ':'] {
+                let mut fast = BencodeParser::new(data.as_bytes());
+                let mut slow = BencodeParser::new(data.as_bytes());
+                match (fast.take_int(terminator), slow.take_int_slow(terminator)) {
+                    (Ok(fast_int), Ok(slow_int)) => {
+                        assert_eq!(fast_int, slow_int);
+                        assert_eq!(fast.offset, slow.offset);
+                    }
+                    (Err(fast_err), Err(slow_err)) => {
+                        assert_eq!(fast_err.to_string(), slow_err.to_string(), "{}", data)
+                    }
+                    (fast_ret, slow_ret) => panic!("{:?} {:?} for {}", fast_ret, slow_ret, data),
+                }
+            }
+        }
+    }
+}
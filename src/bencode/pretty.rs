@@ -0,0 +1,192 @@
+//! Human readable rendering of bencode, for debugging.
+//!
+//! Example:
+//! ```
+//! use ytorrent::pretty_print;
+//!
+//! let text = pretty_print(b"d4:infod6:lengthi1ee4:listl1:a1:bee").unwrap();
+//! assert_eq!(
+//!     text,
+//!     "{\n  info: {\n    length: 1\n  }\n  list: [\n    \"a\",\n    \"b\"\n  ]\n}"
+//! );
+//! ```
+use super::*;
+
+/// Render bencode `data`, indented with 2 spaces per level
+pub fn pretty_print(data: &[u8]) -> Result<String> {
+    let mut buffer = vec![];
+    pretty_print_writer(data, &mut buffer, 2)?;
+    // Only UTF-8 strings are written as they are, everything else is escaped or hex
+    Ok(String::from_utf8(buffer).unwrap())
+}
+
+/// Same as [pretty_print], but never fails: invalid `data` is rendered as the error and a hex
+/// preview, handy for logs
+pub fn dump(data: &[u8]) -> String {
+    pretty_print(data).unwrap_or_else(|err| {
+        let printer = PrettyPrinter::default();
+        let mut preview = vec![];
+        printer
+            .write_bytes(&mut preview, data)
+            .expect("write to a Vec");
+        format!(
+            "<invalid bencode: {}> {}",
+            err,
+            String::from_utf8_lossy(&preview)
+        )
+    })
+}
+
+/// Render bencode `data` to `writer`, indented with `indent` spaces per level
+pub fn pretty_print_writer<W: Write>(data: &[u8], writer: W, indent: usize) -> Result<()> {
+    PrettyPrinter {
+        indent,
+        ..PrettyPrinter::default()
+    }
+    .print(data, writer)
+}
+
+/// Options of the bencode pretty-printer, see [pretty_print]
+#[derive(Debug, Clone)]
+pub struct PrettyPrinter {
+    /// Spaces per nesting level
+    pub indent: usize,
+    /// Non UTF-8 byte strings longer than this are truncated
+    pub max_bytes: usize,
+}
+
+impl Default for PrettyPrinter {
+    fn default() -> Self {
+        Self {
+            indent: 2,
+            max_bytes: 32,
+        }
+    }
+}
+
+impl PrettyPrinter {
+    pub fn print<W: Write>(&self, data: &[u8], mut writer: W) -> Result<()> {
+        parse_with(data, |object| self.write_object(&mut writer, object, 0))
+    }
+
+    fn write_object<W: Write>(&self, writer: &mut W, object: Object, depth: usize) -> Result<()> {
+        match object {
+            Object::Int(str) => write!(writer, "{}", str)?,
+            Object::Bytes(bytes) => self.write_bytes(writer, bytes)?,
+            Object::List(mut decoder) => {
+                write!(writer, "[")?;
+                let mut empty = true;
+                while let Some(item) = decoder.next_object()? {
+                    if !empty {
+                        write!(writer, ",")?;
+                    }
+                    empty = false;
+                    self.write_line_start(writer, depth + 1)?;
+                    self.write_object(writer, item, depth + 1)?;
+                }
+                if !empty {
+                    self.write_line_start(writer, depth)?;
+                }
+                write!(writer, "]")?;
+            }
+            Object::Dict(mut decoder) => {
+                write!(writer, "{{")?;
+                let mut empty = true;
+                while let Some((key, value)) = decoder.next_pair()? {
+                    empty = false;
+                    self.write_line_start(writer, depth + 1)?;
+                    match core::str::from_utf8(key) {
+                        Ok(key) => write!(writer, "{}: ", key)?,
+                        Err(_) => write!(writer, "{}: ", hex(key))?,
+                    }
+                    self.write_object(writer, value, depth + 1)?;
+                }
+                if !empty {
+                    self.write_line_start(writer, depth)?;
+                }
+                write!(writer, "}}")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_line_start<W: Write>(&self, writer: &mut W, depth: usize) -> Result<()> {
+        write!(writer, "\n{:width$}", "", width = depth * self.indent)?;
+        Ok(())
+    }
+
+    fn write_bytes<W: Write>(&self, writer: &mut W, bytes: &[u8]) -> Result<()> {
+        match core::str::from_utf8(bytes) {
+            Ok(str) => write!(writer, "{:?}", str)?,
+            Err(_) if bytes.len() > self.max_bytes => write!(
+                writer,
+                "<{} bytes: {}...>",
+                bytes.len(),
+                hex(&bytes[..self.max_bytes])
+            )?,
+            Err(_) => write!(writer, "<{} bytes: {}>", bytes.len(), hex(bytes))?,
+        }
+        Ok(())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02X}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pretty_print() {
+        assert_eq!(pretty_print(b"i-42e").unwrap(), "-42");
+        assert_eq!(pretty_print(b"le").unwrap(), "[]");
+        assert_eq!(pretty_print(b"de").unwrap(), "{}");
+        assert_eq!(
+            pretty_print(b"d2:\xff\xfe3:\x00\x01\xff4:spaml1:\"lleeee").unwrap(),
+            "{\n  FFFE: <3 bytes: 0001FF>\n  spam: [\n    \"\\\"\",\n    [\n      []\n    ]\n  ]\n}"
+        );
+        assert!(pretty_print(b"l1:a").is_err());
+        assert!(pretty_print(b"").is_err());
+    }
+
+    #[test]
+    fn test_dump() {
+        assert_eq!(dump(b"d1:ai1ee"), "{\n  a: 1\n}");
+        assert_eq!(
+            dump(b"d1:ai1e"),
+            "<invalid bencode: Decode error: unexpected EOF when parse token at offset 7> \
+             \"d1:ai1e\""
+        );
+        assert_eq!(
+            dump(b"\xff"),
+            "<invalid bencode: Decode error: invalid token \u{ff} at offset 0> <1 bytes: FF>"
+        );
+    }
+
+    #[test]
+    fn test_pretty_print_writer() {
+        let mut buffer = vec![];
+        pretty_print_writer(b"d1:ali1eee", &mut buffer, 4).unwrap();
+        assert_eq!(buffer, b"{\n    a: [\n        1\n    ]\n}");
+
+        let printer = PrettyPrinter {
+            max_bytes: 2,
+            ..PrettyPrinter::default()
+        };
+        let mut buffer = vec![];
+        printer.print(b"3:\x00\x01\xff", &mut buffer).unwrap();
+        assert_eq!(buffer, b"<3 bytes: 0001...>");
+    }
+
+    #[test]
+    fn test_pretty_print_torrent() {
+        let data = std::fs::read("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let text = pretty_print(&data).unwrap();
+        assert!(text.starts_with("{\n  announce: \"http://bttracker.debian.org:6969/announce\"\n"));
+        assert!(text.contains("\n  info: {\n    length: "));
+        assert!(text.contains("\n    pieces: <50320 bytes: "));
+        assert!(text.ends_with("\n}"));
+    }
+}
@@ -0,0 +1,271 @@
+//! Incremental parsing of bencode arriving in chunks, e.g. messages read off a socket.
+//!
+//! Example:
+//! ```
+//! use ytorrent::{ParseEvent, PushParser};
+//!
+//! let mut parser = PushParser::new();
+//! let mut ints = vec![];
+//! parser
+//!     .feed(b"d1:ai4", |event| assert_ne!(event, ParseEvent::End))
+//!     .unwrap();
+//! assert!(parser.needs_more_data());
+//! parser
+//!     .feed(b"2ee", |event| {
+//!         if let ParseEvent::Int(digits) = event {
+//!             ints.push(digits.to_string());
+//!         }
+//!     })
+//!     .unwrap();
+//! assert_eq!(ints, ["42"]);
+//! assert!(!parser.needs_more_data());
+//! ```
+use super::*;
+use super::Error::*;
+
+/// A bencode parser driven by chunks of data, which never fails on a truncated input.
+///
+/// Complete tokens are read with an [EventReader] and reported as [ParseEvent]s, the start of
+/// a token cut by the end of a chunk is kept until the rest arrives. Values fed back to back
+/// are reported one after another. After an error the state of the parser is undefined, it
+/// should be dropped.
+#[derive(Debug)]
+pub struct PushParser {
+    options: ParserOptions,
+    /// Data fed but not read yet, the start of an incomplete token
+    pending: Vec<u8>,
+    /// Open containers, as tracked by [EventReader]
+    containers: Vec<Option<bool>>,
+    /// Bytes read before `pending`
+    offset: usize,
+}
+
+impl Default for PushParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PushParser {
+    /// Limit of [PushParser::new] on the length of a string, which is buffered until complete
+    pub const DEFAULT_MAX_STRING_LENGTH: usize = 1 << 24;
+
+    /// Long enough for the digits of any i64 or string length, with the sign and the terminator
+    const MAX_INT_TOKEN: usize = 22;
+
+    pub fn new() -> Self {
+        Self::with_options(
+            ParserOptions::default().with_max_string_length(Self::DEFAULT_MAX_STRING_LENGTH),
+        )
+    }
+
+    /// Only [ParserOptions::max_depth] and [ParserOptions::max_string_length] apply, the order
+    /// and uniqueness of dict keys aren't checked across chunks.
+    pub fn with_options(options: ParserOptions) -> Self {
+        PushParser {
+            options: ParserOptions {
+                max_depth: options.max_depth,
+                max_string_length: options.max_string_length,
+                ..ParserOptions::default()
+            },
+            pending: vec![],
+            containers: vec![],
+            offset: 0,
+        }
+    }
+
+    /// Whether the data fed so far ends in the middle of a value
+    pub fn needs_more_data(&self) -> bool {
+        !self.pending.is_empty() || !self.containers.is_empty()
+    }
+
+    /// Parse the next chunk, passing every event complete in it to `on_event`.
+    ///
+    /// Offsets of errors count from the start of the first chunk.
+    pub fn feed<F>(&mut self, data: &[u8], mut on_event: F) -> Result<()>
+    where
+        F: FnMut(ParseEvent<'_>),
+    {
+        self.pending.extend_from_slice(data);
+        let complete = self.complete_len()?;
+        let mut consumed = 0;
+        while consumed < complete {
+            let data = &self.pending[consumed..complete];
+            let containers = core::mem::take(&mut self.containers);
+            let mut reader = EventReader::resume(data, self.options.clone(), containers)?;
+            // A reader stops after each value at the top level, the next one starts another
+            while reader.bytes_consumed() < data.len() {
+                match reader.next() {
+                    Some(Ok((event, _))) => on_event(event),
+                    Some(Err(err)) => return Err(shift_offset(err, self.offset + consumed)),
+                    None => break,
+                }
+            }
+            consumed += reader.bytes_consumed();
+            self.containers = reader.into_containers();
+        }
+        self.pending.drain(..consumed);
+        self.offset += consumed;
+        Ok(())
+    }
+
+    /// Length of the complete tokens at the start of `pending`
+    fn complete_len(&self) -> Result<usize> {
+        let mut end = 0;
+        while let Some(len) = self.token_len(end)? {
+            end += len;
+        }
+        Ok(end)
+    }
+
+    /// Length of the token at `start` in `pending`, `None` if it isn't complete yet.
+    ///
+    /// Only the framing is checked here, the content of the token is left to [EventReader].
+    fn token_len(&self, start: usize) -> Result<Option<usize>> {
+        let rest = &self.pending[start..];
+        let offset = self.offset + start;
+        let terminator = match rest.first() {
+            None => return Ok(None),
+            Some(b'i') => b'e',
+            Some(b'0'..=b'9') => b':',
+            // One byte, the reader reports it if it's invalid
+            Some(_) => return Ok(Some(1)),
+        };
+        let window = &rest[..rest.len().min(Self::MAX_INT_TOKEN)];
+        let Some(position) = memchr::memchr(terminator, window) else {
+            if rest.len() >= Self::MAX_INT_TOKEN {
+                return Err(BencodeDecode {
+                    message: "too many digits".to_string(),
+                    offset: offset + Self::MAX_INT_TOKEN,
+                });
+            }
+            return Ok(None);
+        };
+        if terminator == b'e' {
+            return Ok(Some(position + 1));
+        }
+
+        // An invalid length is reported by the reader, from the token up to the colon
+        let Some(len) = core::str::from_utf8(&rest[..position])
+            .ok()
+            .and_then(|digits| digits.parse::<u64>().ok())
+        else {
+            return Ok(Some(position + 1));
+        };
+        self.options.check_string_length(len, offset)?;
+        let token_len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_add(position + 1))
+            .ok_or_else(|| BencodeDecode {
+                message: format!("string of {} bytes is too long", len),
+                offset,
+            })?;
+        Ok((token_len <= rest.len()).then_some(token_len))
+    }
+}
+
+/// Count the offset of an error of a reader from `base`, where its data starts
+pub(super) fn shift_offset(err: Error, base: usize) -> Error {
+    match err {
+        BencodeDecode { message, offset } => BencodeDecode {
+            message,
+            offset: base + offset,
+        },
+        DepthLimitExceeded { max_depth, offset } => DepthLimitExceeded {
+            max_depth,
+            offset: base + offset,
+        },
+        err => err,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut PushParser, data: &[u8]) -> Result<Vec<String>> {
+        let mut events = vec![];
+        parser.feed(data, |event| events.push(format!("{:?}", event)))?;
+        Ok(events)
+    }
+
+    #[test]
+    fn test_feed_chunks() {
+        let data = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qei-7e";
+        let (dict, int) = data.split_at(data.len() - 4);
+        let expected: Vec<_> = [dict, int]
+            .into_iter()
+            .flat_map(EventReader::new)
+            .map(|event| format!("{:?}", event.unwrap().0))
+            .collect();
+        assert_eq!(expected.len(), 14);
+        for chunk_size in 1..=data.len() {
+            let mut parser = PushParser::new();
+            let mut events = vec![];
+            for chunk in data.chunks(chunk_size) {
+                events.extend(feed(&mut parser, chunk).unwrap());
+            }
+            assert_eq!(events, expected, "chunk size {}", chunk_size);
+            assert!(!parser.needs_more_data());
+        }
+    }
+
+    #[test]
+    fn test_need_more_data() {
+        let mut parser = PushParser::new();
+        assert!(feed(&mut parser, b"").unwrap().is_empty());
+        assert!(!parser.needs_more_data());
+        assert!(feed(&mut parser, b"4:sp").unwrap().is_empty());
+        assert!(parser.needs_more_data());
+        assert_eq!(
+            feed(&mut parser, b"am0:l").unwrap(),
+            [
+                "StringValue([115, 112, 97, 109])",
+                "StringValue([])",
+                "ListStart"
+            ]
+        );
+        assert!(parser.needs_more_data());
+        assert_eq!(feed(&mut parser, b"e").unwrap(), ["End"]);
+        assert!(!parser.needs_more_data());
+    }
+
+    #[test]
+    fn test_feed_invalid() {
+        for (data, offset) in [
+            (b"e".as_slice(), 0),
+            (b"x", 0),
+            (b"li1ei-0e", 6),
+            (b"i03e", 2),
+            (b"1a:", 1),
+            (b"l03:abc", 2),
+            (b"i1234567890123456789012345e", 22),
+            (b"18446744073709551615:", 0),
+            (b"99999999999999999999:", 0),
+            (b"li1e999999999999:", 4),
+            (b"di1ei2ee", 1),
+            (b"d1:ae", 4),
+            (b"d1:ai1e1:be", 10),
+        ] {
+            let err = feed(&mut PushParser::new(), data).unwrap_err();
+            assert_eq!(err.decode_offset(), Some(offset), "{:?}", err);
+        }
+
+        let mut parser =
+            PushParser::with_options(ParserOptions::default().with_max_string_length(3));
+        assert!(feed(&mut parser, b"l3:ab").is_ok());
+        assert_eq!(
+            feed(&mut parser, b"c4:").unwrap_err().decode_offset(),
+            Some(6)
+        );
+
+        let mut parser = PushParser::with_options(ParserOptions::default().with_max_depth(2));
+        assert!(feed(&mut parser, b"ll").is_ok());
+        let err = feed(&mut parser, b"l").unwrap_err();
+        assert!(
+            matches!(err, DepthLimitExceeded { offset: 3, .. }),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::de::Visitor;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Name of the newtype struct which [crate::de] and [crate::ser] treat as a [RawValue]
+pub(super) const RAW_VALUE_TOKEN: &str = "$ytorrent::RawValue";
+
+/// The bytes of any bencode value exactly as they are in the input, without decoding them.
+///
+/// Only works with [crate::de::from_bytes] and [crate::ser::to_bytes] of this crate, other formats see a
+/// newtype struct of bytes.
+///
+/// Example:
+/// ```
+/// use serde::Deserialize;
+/// use ytorrent::{de, RawValue};
+///
+/// #[derive(Deserialize)]
+/// struct Meta<'a> {
+///     #[serde(borrow)]
+///     info: RawValue<'a>,
+/// }
+/// let meta: Meta = de::from_bytes(b"d4:infod6:lengthi1eee").unwrap();
+/// assert_eq!(meta.info.get(), b"d6:lengthi1ee");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawValue<'de>(pub(super) &'de [u8]);
+
+impl<'de> RawValue<'de> {
+    /// The raw bencode
+    pub fn get(&self) -> &'de [u8] {
+        self.0
+    }
+}
+
+impl Serialize for RawValue<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(
+            RAW_VALUE_TOKEN,
+            &serde_with::ser::SerializeAsWrap::<_, serde_with::Bytes>::new(&self.0),
+        )
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for RawValue<'a> {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RawValueVisitor<'a>(PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for RawValueVisitor<'a> {
+            type Value = RawValue<'a>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("raw bencode")
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> core::result::Result<Self::Value, E> {
+                Ok(RawValue(v))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(RAW_VALUE_TOKEN, RawValueVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::{de, ser};
+
+    #[derive(Deserialize, Serialize)]
+    struct Message<'a> {
+        #[serde(borrow)]
+        args: Option<RawValue<'a>>,
+        #[serde(borrow)]
+        list: Vec<RawValue<'a>>,
+        y: String,
+    }
+
+    #[test]
+    fn test_raw_value() {
+        let data = b"d4:argsd2:id2:ab6:targetl1:xi-1eee4:listli1e0:dee1:y1:qe";
+        let message: Message = de::from_bytes(data).unwrap();
+        assert_eq!(message.args.unwrap().get(), b"d2:id2:ab6:targetl1:xi-1eee");
+        let list: Vec<_> = message.list.iter().map(RawValue::get).collect();
+        assert_eq!(list, vec![b"i1e".as_slice(), b"0:", b"de"]);
+        assert_eq!(message.y, "q");
+        assert_eq!(ser::to_bytes(&message).unwrap(), data);
+
+        let raw: RawValue = de::from_bytes(b"l1:ae").unwrap();
+        assert_eq!(raw.get(), b"l1:ae");
+        assert!(de::from_bytes::<RawValue>(b"l1:a").is_err());
+        let map: BTreeMap<&str, RawValue> = de::from_bytes(b"d1:ai1e1:blee").unwrap();
+        assert_eq!(map["a"].get(), b"i1e");
+        assert_eq!(map["b"].get(), b"le");
+    }
+}
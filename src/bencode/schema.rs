@@ -0,0 +1,335 @@
+//! Check the shape of a bencode document: required keys, types and lengths.
+//!
+//! Example:
+//! ```
+//! use ytorrent::Schema;
+//!
+//! let schema = Schema::dict()
+//!     .required("announce", Schema::bytes())
+//!     .required(
+//!         "info",
+//!         Schema::dict()
+//!             .required("name", Schema::bytes().with_len(1, 255))
+//!             .required("piece length", Schema::int().with_range(16384, 1 << 24))
+//!             .optional("files", Schema::list(Schema::dict()).with_len(1, usize::MAX)),
+//!     );
+//!
+//! let violations = schema
+//!     .validate(b"d4:infod4:name0:12:piece lengthi1eee")
+//!     .unwrap();
+//! let paths: Vec<_> = violations.iter().map(|v| v.path.as_str()).collect();
+//! assert_eq!(paths, ["", "/info/name", "/info/piece length"]);
+//! ```
+use core::fmt::{Display, Formatter};
+
+use super::*;
+
+/// Expected shape of a bencode value, checked by [Schema::validate]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Schema {
+    /// Any value
+    Any,
+    Int {
+        min: i64,
+        max: i64,
+    },
+    /// A string, `min_len` and `max_len` are in bytes
+    Bytes {
+        min_len: usize,
+        max_len: usize,
+    },
+    List {
+        items: Box<Schema>,
+        min_len: usize,
+        max_len: usize,
+    },
+    Dict {
+        fields: Vec<SchemaField>,
+        /// Whether keys not in `fields` are violations
+        deny_unknown_keys: bool,
+    },
+}
+
+/// A key of a [Schema::Dict]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaField {
+    pub key: Vec<u8>,
+    pub schema: Schema,
+    pub required: bool,
+}
+
+/// A value that doesn't match its [Schema]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaViolation {
+    /// Where the value is, in the format of [BencodeValue::pointer]
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for SchemaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let path = if self.path.is_empty() {
+            "/"
+        } else {
+            &self.path
+        };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+impl Schema {
+    /// Any integer, see [Self::with_range]
+    pub fn int() -> Self {
+        Schema::Int {
+            min: i64::MIN,
+            max: i64::MAX,
+        }
+    }
+
+    /// Any string, see [Self::with_len]
+    pub fn bytes() -> Self {
+        Schema::Bytes {
+            min_len: 0,
+            max_len: usize::MAX,
+        }
+    }
+
+    /// A list whose items all match `items`, see [Self::with_len]
+    pub fn list(items: Schema) -> Self {
+        Schema::List {
+            items: Box::new(items),
+            min_len: 0,
+            max_len: usize::MAX,
+        }
+    }
+
+    /// A dict allowing any key, see [Self::required] and [Self::optional]
+    pub fn dict() -> Self {
+        Schema::Dict {
+            fields: vec![],
+            deny_unknown_keys: false,
+        }
+    }
+
+    /// Limit an integer to `min..=max`, no-op for other schemas
+    pub fn with_range(mut self, min: i64, max: i64) -> Self {
+        if let Schema::Int {
+            min: old_min,
+            max: old_max,
+        } = &mut self
+        {
+            (*old_min, *old_max) = (min, max);
+        }
+        self
+    }
+
+    /// Limit the length of a string or list to `min..=max`, no-op for other schemas
+    pub fn with_len(mut self, min: usize, max: usize) -> Self {
+        if let Schema::Bytes { min_len, max_len }
+        | Schema::List {
+            min_len, max_len, ..
+        } = &mut self
+        {
+            (*min_len, *max_len) = (min, max);
+        }
+        self
+    }
+
+    /// Add a key the dict must have, no-op for other schemas
+    pub fn required(self, key: &str, schema: Schema) -> Self {
+        self.with_field(key, schema, true)
+    }
+
+    /// Add a key the dict may have, checked when present, no-op for other schemas
+    pub fn optional(self, key: &str, schema: Schema) -> Self {
+        self.with_field(key, schema, false)
+    }
+
+    /// Report dict keys not added by [Self::required] or [Self::optional]
+    pub fn deny_unknown_keys(mut self) -> Self {
+        if let Schema::Dict {
+            deny_unknown_keys, ..
+        } = &mut self
+        {
+            *deny_unknown_keys = true;
+        }
+        self
+    }
+
+    fn with_field(mut self, key: &str, schema: Schema, required: bool) -> Self {
+        if let Schema::Dict { fields, .. } = &mut self {
+            fields.push(SchemaField {
+                key: key.as_bytes().to_vec(),
+                schema,
+                required,
+            });
+        }
+        self
+    }
+
+    /// Check `data`, which must be exactly one valid bencode value, against the schema.
+    ///
+    /// Every violation is reported, not only the first one. A value's own violation comes before
+    /// those of its items, list items are checked in order and dict keys in sorted order, which
+    /// may differ from the order of `data`. An empty list means the document matches.
+    pub fn validate(&self, data: &[u8]) -> Result<Vec<SchemaViolation>> {
+        let value = de::from_bytes_exact::<BencodeValue>(data)?;
+        Ok(self.validate_value(&value))
+    }
+
+    /// Same as [Self::validate], for a decoded value
+    pub fn validate_value(&self, value: &BencodeValue) -> Vec<SchemaViolation> {
+        let mut violations = vec![];
+        self.check(value, &mut String::new(), &mut violations);
+        violations
+    }
+
+    fn check(&self, value: &BencodeValue, path: &mut String, out: &mut Vec<SchemaViolation>) {
+        let mut violation = |message: String| {
+            out.push(SchemaViolation {
+                path: path.clone(),
+                message,
+            })
+        };
+        match (self, value) {
+            (Schema::Any, _) => {}
+            (Schema::Int { min, max }, BencodeValue::Int(int)) => {
+                if int < min || int > max {
+                    violation(format!("{} is not in {}..={}", int, min, max));
+                }
+            }
+            (Schema::Bytes { min_len, max_len }, BencodeValue::Bytes(bytes)) => {
+                check_len(bytes.len(), *min_len, *max_len, violation);
+            }
+            (
+                Schema::List {
+                    items,
+                    min_len,
+                    max_len,
+                },
+                BencodeValue::List(list),
+            ) => {
+                check_len(list.len(), *min_len, *max_len, violation);
+                for (index, item) in list.iter().enumerate() {
+                    let len = path.len();
+                    path.push_str(&format!("/{}", index));
+                    items.check(item, path, out);
+                    path.truncate(len);
+                }
+            }
+            (
+                Schema::Dict {
+                    fields,
+                    deny_unknown_keys,
+                },
+                BencodeValue::Dict(dict),
+            ) => {
+                let missing: Vec<_> = fields
+                    .iter()
+                    .filter(|field| field.required && !dict.contains_key(&field.key))
+                    .map(|field| String::from_utf8_lossy(&field.key))
+                    .collect();
+                if !missing.is_empty() {
+                    violation(format!("missing required keys: {}", missing.join(", ")));
+                }
+                for (key, item) in dict {
+                    let len = path.len();
+                    push_segment(path, key);
+                    match fields.iter().find(|field| &field.key == key) {
+                        Some(field) => field.schema.check(item, path, out),
+                        None if *deny_unknown_keys => out.push(SchemaViolation {
+                            path: path.clone(),
+                            message: "unknown key".to_string(),
+                        }),
+                        None => {}
+                    }
+                    path.truncate(len);
+                }
+            }
+            (expected, found) => violation(format!(
+                "expected {}, found {}",
+                expected.type_name(),
+                type_name(found)
+            )),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Schema::Any => "any value",
+            Schema::Int { .. } => "an integer",
+            Schema::Bytes { .. } => "a string",
+            Schema::List { .. } => "a list",
+            Schema::Dict { .. } => "a dict",
+        }
+    }
+}
+
+fn type_name(value: &BencodeValue) -> &'static str {
+    match value {
+        BencodeValue::Int(_) => "an integer",
+        BencodeValue::Bytes(_) => "a string",
+        BencodeValue::List(_) => "a list",
+        BencodeValue::Dict(_) => "a dict",
+    }
+}
+
+fn check_len(len: usize, min: usize, max: usize, violation: impl FnOnce(String)) {
+    if len < min || len > max {
+        violation(format!("length {} is not in {}..={}", len, min, max));
+    }
+}
+
+/// Append `/key` to `path`, escaped as [BencodeValue::pointer] expects
+fn push_segment(path: &mut String, key: &[u8]) {
+    path.push('/');
+    path.push_str(
+        &String::from_utf8_lossy(key)
+            .replace('~', "~0")
+            .replace('/', "~1"),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(schema: &Schema, data: &[u8]) -> Vec<String> {
+        let violations = schema.validate(data).unwrap();
+        violations.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema = Schema::dict()
+            .required("a/b", Schema::int().with_range(0, 10))
+            .optional(
+                "list",
+                Schema::list(Schema::bytes().with_len(1, 2)).with_len(0, 2),
+            )
+            .deny_unknown_keys();
+
+        assert!(messages(&schema, b"d3:a/bi10e4:listl1:a2:abee").is_empty());
+        assert_eq!(
+            messages(&schema, b"d3:a/bi11e1:xi0e4:listl0:3:abc1:ai0eee"),
+            [
+                "/a~1b: 11 is not in 0..=10",
+                "/list: length 4 is not in 0..=2",
+                "/list/0: length 0 is not in 1..=2",
+                "/list/1: length 3 is not in 1..=2",
+                "/list/3: expected a string, found an integer",
+                "/x: unknown key",
+            ]
+        );
+        assert_eq!(
+            messages(&schema, b"d4:listlee"),
+            ["/: missing required keys: a/b"]
+        );
+        assert_eq!(
+            messages(&schema, b"le"),
+            ["/: expected a dict, found a list"]
+        );
+        assert!(messages(&Schema::Any, b"i1e").is_empty());
+        assert!(schema.validate(b"d3:a/bi1e").is_err());
+    }
+}
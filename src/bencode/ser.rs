@@ -0,0 +1,881 @@
+//! Bencode serializer.
+//!
+//! The output is canonical bencode: dict keys are sorted, so re-encoding a parsed `info` dict
+//! gives the same info hash. With [KeyOrder::Preserve], keys are written in the order they are
+//! serialized instead, see [to_bytes_with_key_order].
+//!
+//! Example:
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use serde::Serialize;
+//! use ytorrent::ser;
+//!
+//! #[derive(Serialize)]
+//! struct Foo {
+//!     str: String,
+//!     int: i32,
+//!     map: HashMap<String, String>,
+//! }
+//! let foo = Foo {
+//!     str: "demo".into(),
+//!     int: 1,
+//!     map: HashMap::from([("key1".into(), "value1".into())]),
+//! };
+//! let bytes = ser::to_bytes(&foo).unwrap();
+//! assert_eq!(bytes, b"d3:inti1e3:mapd4:key16:value1e3:str4:demoe");
+//! ```
+use alloc::collections::BTreeSet;
+
+use serde::ser::{
+    Impossible, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant,
+};
+use serde::{Serialize, Serializer};
+
+use super::*;
+use super::Error::*;
+
+/// Order of the keys of serialized dicts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyOrder {
+    /// Sorted, as canonical bencode and info hashes require
+    #[default]
+    Sorted,
+    /// In the order they are serialized: struct fields as declared, maps as they iterate.
+    ///
+    /// Along with an `OrderedBencodeValue`, it writes back a document byte for byte even
+    /// if its keys aren't sorted. Duplicate keys are still refused.
+    Preserve,
+}
+
+pub struct BencodeSerializer<W> {
+    writer: W,
+    bytes_written: usize,
+    key_order: KeyOrder,
+}
+
+impl<W: Write> BencodeSerializer<W> {
+    pub fn new(writer: W) -> Self {
+        BencodeSerializer {
+            writer,
+            bytes_written: 0,
+            key_order: KeyOrder::default(),
+        }
+    }
+
+    pub fn with_key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.writer.write_all(bytes)?;
+        self.bytes_written += bytes.len();
+        Ok(())
+    }
+
+    fn write_int<I: core::fmt::Display>(&mut self, int: I) -> Result<()> {
+        self.write(format!("i{}e", int).as_bytes())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.write(format!("{}:", bytes.len()).as_bytes())?;
+        self.write(bytes)
+    }
+
+    /// Serialize a value which can't be skipped, `None` fails instead of writing nothing
+    fn write_value<T>(&mut self, value: &T, position: &str) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let start = self.bytes_written;
+        value.serialize(&mut *self)?;
+        if self.bytes_written == start {
+            return Err(SerdeCustom(format!(
+                "can't serialize None as {} at {}",
+                position, start
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl<'a, W: Write> Serializer for &'a mut BencodeSerializer<W> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = SeqSerializer<'a, W>;
+    type SerializeTupleStruct = SeqSerializer<'a, W>;
+    type SerializeTupleVariant = SeqSerializer<'a, W>;
+    type SerializeMap = MapSerializer<'a, W>;
+    type SerializeStruct = MapSerializer<'a, W>;
+    type SerializeStructVariant = MapSerializer<'a, W>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.write_int(v as u8)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.write_int(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        Err(SerdeCustom(format!("bencode doesn't support float {}", v)))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        Err(SerdeCustom(format!("bencode doesn't support float {}", v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.write_bytes(v.encode_utf8(&mut [0; 4]).as_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_bytes(v)
+    }
+
+    /// Writes nothing, the parent dict skips the entry. Anywhere else, such as in a list or as
+    /// the value of a newtype variant, it's an error.
+    fn serialize_none(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    /// Written as an empty list, see `deserialize_unit`
+    fn serialize_unit(self) -> Result<()> {
+        self.write(b"le")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if name == RAW_VALUE_TOKEN {
+            // The bytes of a RawValue are written as they are
+            let raw = value.serialize(MapKeySerializer)?;
+            return self.write(&raw);
+        }
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write(b"d")?;
+        self.write_bytes(variant.as_bytes())?;
+        self.write_value(value, "newtype variant value")?;
+        self.write(b"e")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        trace!("serialize_seq");
+        self.write(b"l")?;
+        Ok(SeqSerializer {
+            ser: self,
+            in_variant: false,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.write(b"d")?;
+        self.write_bytes(variant.as_bytes())?;
+        self.write(b"l")?;
+        Ok(SeqSerializer {
+            ser: self,
+            in_variant: true,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        trace!("serialize_map");
+        Ok(MapSerializer {
+            ser: self,
+            entries: Vec::with_capacity(len.unwrap_or(0)),
+            pending_key: None,
+            in_variant: false,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        self.write(b"d")?;
+        self.write_bytes(variant.as_bytes())?;
+        Ok(MapSerializer {
+            ser: self,
+            entries: Vec::with_capacity(len),
+            pending_key: None,
+            in_variant: true,
+        })
+    }
+}
+
+/// Serialize list items, and the list of a tuple variant
+pub struct SeqSerializer<'a, W> {
+    ser: &'a mut BencodeSerializer<W>,
+    in_variant: bool,
+}
+
+impl<W: Write> SeqSerializer<'_, W> {
+    fn element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.ser.write_value(value, "list item")
+    }
+
+    fn finish(self) -> Result<()> {
+        self.ser.write(b"e")?;
+        if self.in_variant {
+            self.ser.write(b"e")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeSeq for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTuple for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTupleStruct for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeTupleVariant for SeqSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Serialize dict entries.
+///
+/// Entries are buffered and written in key order when the dict ends, since bencode requires
+/// sorted keys, unless the [KeyOrder] preserves their order. Entries whose value is `None` are
+/// skipped.
+pub struct MapSerializer<'a, W> {
+    ser: &'a mut BencodeSerializer<W>,
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    pending_key: Option<Vec<u8>>,
+    in_variant: bool,
+}
+
+impl<W: Write> MapSerializer<'_, W> {
+    fn entry<T>(&mut self, key: Vec<u8>, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut value_ser = BencodeSerializer::new(vec![]).with_key_order(self.ser.key_order);
+        value.serialize(&mut value_ser)?;
+        let value = value_ser.into_inner();
+        if !value.is_empty() {
+            self.entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let duplicate = match self.ser.key_order {
+            KeyOrder::Sorted => {
+                self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                self.entries
+                    .windows(2)
+                    .find(|pair| pair[0].0 == pair[1].0)
+                    .map(|pair| &pair[0].0)
+            }
+            KeyOrder::Preserve => {
+                let mut seen = BTreeSet::new();
+                self.entries
+                    .iter()
+                    .map(|(key, _)| key)
+                    .find(|key| !seen.insert(*key))
+            }
+        };
+        if let Some(key) = duplicate {
+            return Err(SerdeCustom(format!(
+                "duplicate dict key {}",
+                String::from_utf8_lossy(key)
+            )));
+        }
+        self.ser.write(b"d")?;
+        for (key, value) in &self.entries {
+            self.ser.write_bytes(key)?;
+            self.ser.write(value)?;
+        }
+        self.ser.write(b"e")?;
+        if self.in_variant {
+            self.ser.write(b"e")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> SerializeMap for MapSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.pending_key = Some(key.serialize(MapKeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .pending_key
+            .take()
+            .ok_or_else(|| SerdeCustom("serialize_value called before serialize_key".into()))?;
+        self.entry(key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeStruct for MapSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entry(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+impl<W: Write> SerializeStructVariant for MapSerializer<'_, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entry(key.as_bytes().to_vec(), value)
+    }
+
+    fn end(self) -> Result<()> {
+        self.finish()
+    }
+}
+
+/// Dict keys are byte strings, so only string-like keys are supported
+struct MapKeySerializer;
+
+fn key_error(key_type: &str) -> Error {
+    SerdeCustom(format!("dict key must be a string, but get {}", key_type))
+}
+
+impl Serializer for MapKeySerializer {
+    type Ok = Vec<u8>;
+    type Error = Error;
+    type SerializeSeq = Impossible<Vec<u8>, Error>;
+    type SerializeTuple = Impossible<Vec<u8>, Error>;
+    type SerializeTupleStruct = Impossible<Vec<u8>, Error>;
+    type SerializeTupleVariant = Impossible<Vec<u8>, Error>;
+    type SerializeMap = Impossible<Vec<u8>, Error>;
+    type SerializeStruct = Impossible<Vec<u8>, Error>;
+    type SerializeStructVariant = Impossible<Vec<u8>, Error>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Vec<u8>> {
+        Err(key_error("bool"))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Vec<u8>> {
+        Err(key_error("i8"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Vec<u8>> {
+        Err(key_error("i16"))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Vec<u8>> {
+        Err(key_error("i32"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Vec<u8>> {
+        Err(key_error("i64"))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Vec<u8>> {
+        Err(key_error("u8"))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Vec<u8>> {
+        Err(key_error("u16"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Vec<u8>> {
+        Err(key_error("u32"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Vec<u8>> {
+        Err(key_error("u64"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<u8>> {
+        Err(key_error("f32"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<u8>> {
+        Err(key_error("f64"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Vec<u8>> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Vec<u8>> {
+        Ok(v.as_bytes().to_vec())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>> {
+        Ok(v.to_vec())
+    }
+
+    fn serialize_none(self) -> Result<Vec<u8>> {
+        Err(key_error("none"))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<u8>> {
+        Err(key_error("unit"))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<u8>> {
+        Err(key_error("unit struct"))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Vec<u8>> {
+        Ok(variant.as_bytes().to_vec())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(key_error("newtype variant"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(key_error("seq"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(key_error("tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        Err(key_error("tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        Err(key_error("tuple variant"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        Err(key_error("map"))
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Err(key_error("struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        Err(key_error("struct variant"))
+    }
+}
+
+/// Serialize `value` to canonical bencode and write it to `writer`.
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    to_writer_with_key_order(writer, value, KeyOrder::Sorted)
+}
+
+/// Serialize `value` to bencode with dict keys in `key_order` and write it to `writer`.
+pub fn to_writer_with_key_order<W, T>(writer: W, value: &T, key_order: KeyOrder) -> Result<()>
+where
+    W: Write,
+    T: ?Sized + Serialize,
+{
+    let mut ser = BencodeSerializer::new(writer).with_key_order(key_order);
+    value.serialize(&mut ser)?;
+    if ser.bytes_written == 0 {
+        return Err(SerdeCustom("can't serialize None as bencode value".into()));
+    }
+    Ok(())
+}
+
+/// Serialize `value` to canonical bencode.
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    to_bytes_with_key_order(value, KeyOrder::Sorted)
+}
+
+/// Serialize `value` to bencode with dict keys in `key_order`.
+///
+/// ```
+/// use serde::Serialize;
+/// use ytorrent::ser::{self, KeyOrder};
+///
+/// #[derive(Serialize)]
+/// struct Peer {
+///     port: u16,
+///     ip: String,
+/// }
+/// let peer = Peer { port: 6881, ip: "::1".into() };
+/// assert_eq!(ser::to_bytes(&peer).unwrap(), b"d2:ip3:::14:porti6881ee");
+/// assert_eq!(
+///     ser::to_bytes_with_key_order(&peer, KeyOrder::Preserve).unwrap(),
+///     b"d4:porti6881e2:ip3:::1e"
+/// );
+/// ```
+pub fn to_bytes_with_key_order<T>(value: &T, key_order: KeyOrder) -> Result<Vec<u8>>
+where
+    T: ?Sized + Serialize,
+{
+    let mut bytes = vec![];
+    to_writer_with_key_order(&mut bytes, value, key_order)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::Deserialize;
+    use serde_with::{Bytes, serde_as};
+
+    use crate::{de, ser, Sha1Digest};
+
+    #[test]
+    fn test_se_primitive() {
+        assert_eq!(ser::to_bytes(&true).unwrap(), b"i1e");
+        assert_eq!(ser::to_bytes(&false).unwrap(), b"i0e");
+        assert_eq!(ser::to_bytes(&0u8).unwrap(), b"i0e");
+        assert_eq!(ser::to_bytes(&i8::MIN).unwrap(), b"i-128e");
+        assert_eq!(ser::to_bytes(&u16::MAX).unwrap(), b"i65535e");
+        assert_eq!(ser::to_bytes(&i32::MIN).unwrap(), b"i-2147483648e");
+        assert_eq!(ser::to_bytes(&u32::MAX).unwrap(), b"i4294967295e");
+        assert_eq!(ser::to_bytes(&i64::MIN).unwrap(), b"i-9223372036854775808e");
+        assert_eq!(ser::to_bytes(&u64::MAX).unwrap(), b"i18446744073709551615e");
+        assert_eq!(ser::to_bytes(&'x').unwrap(), b"1:x");
+        assert_eq!(ser::to_bytes("spam").unwrap(), b"4:spam");
+        assert_eq!(ser::to_bytes("").unwrap(), b"0:");
+        assert_eq!(ser::to_bytes(&()).unwrap(), b"le");
+        assert!(ser::to_bytes(&1.5f64).is_err());
+        assert!(ser::to_bytes(&None::<i32>).is_err());
+        assert_eq!(ser::to_bytes(&Some(1)).unwrap(), b"i1e");
+    }
+
+    #[test]
+    fn test_se_bytes() {
+        #[serde_as]
+        #[derive(serde::Serialize)]
+        struct Wrapper(#[serde_as(as = "Bytes")] Vec<u8>);
+
+        let bytes = ser::to_bytes(&Wrapper(vec![0, 1, 2])).unwrap();
+        assert_eq!(bytes, b"3:\x00\x01\x02");
+        let digest = Sha1Digest([7; 20]);
+        let bytes = ser::to_bytes(&digest).unwrap();
+        assert_eq!(&bytes[..3], b"20:");
+        assert_eq!(de::from_bytes::<Sha1Digest>(&bytes).unwrap(), digest);
+    }
+
+    #[test]
+    fn test_se_nested() {
+        let value = BTreeMap::from([("spam", vec![vec![1, 2], vec![]]), ("cow", vec![vec![3]])]);
+        let bytes = ser::to_bytes(&value).unwrap();
+        assert_eq!(bytes, b"d3:cowlli3eee4:spamlli1ei2eeleee");
+        let ret: BTreeMap<String, Vec<Vec<i32>>> = de::from_bytes(&bytes).unwrap();
+        assert_eq!(ret.len(), 2);
+        assert_eq!(ret["spam"], vec![vec![1, 2], vec![]]);
+
+        assert!(ser::to_bytes(&vec![Some(1), None]).is_err());
+        assert!(ser::to_bytes(&HashMap::from([(1, 2)])).is_err());
+    }
+
+    #[test]
+    fn test_se_none_positions() {
+        #[derive(serde::Serialize)]
+        struct Pair(Option<i32>, i32);
+
+        #[derive(serde::Serialize)]
+        enum Enum {
+            Newtype(Option<i32>),
+            Tuple(Option<i32>, i32),
+            Struct { a: Option<i32>, b: i32 },
+        }
+
+        // Dropping the None would shift the items after it
+        let err = ser::to_bytes(&(1, None::<i32>, 2)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Serde custom error: can't serialize None as list item at 4"
+        );
+        assert!(ser::to_bytes(&Pair(None, 1)).is_err());
+        assert!(ser::to_bytes(&Enum::Tuple(None, 1)).is_err());
+        let err = ser::to_bytes(&Enum::Newtype(None)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Serde custom error: can't serialize None as newtype variant value at 10"
+        );
+        assert_eq!(
+            ser::to_bytes(&Enum::Newtype(Some(1))).unwrap(),
+            b"d7:Newtypei1ee"
+        );
+
+        // Dict values are skipped instead
+        let value = Enum::Struct { a: None, b: 1 };
+        assert_eq!(ser::to_bytes(&value).unwrap(), b"d6:Structd1:bi1eee");
+        let map = BTreeMap::from([("a", None), ("b", Some(1))]);
+        assert_eq!(ser::to_bytes(&map).unwrap(), b"d1:bi1ee");
+    }
+
+    #[test]
+    fn test_se_sorted_keys() {
+        #[derive(serde::Serialize)]
+        struct Unsorted {
+            zebra: i32,
+            #[serde(rename = "piece length")]
+            piece_length: i32,
+            apple: Option<i32>,
+            missing: Option<i32>,
+        }
+
+        let value = Unsorted {
+            zebra: 1,
+            piece_length: 2,
+            apple: Some(3),
+            missing: None,
+        };
+        assert_eq!(
+            ser::to_bytes(&value).unwrap(),
+            b"d5:applei3e12:piece lengthi2e5:zebrai1ee"
+        );
+    }
+
+    #[test]
+    fn test_se_enum() {
+        #[derive(serde::Serialize, Deserialize, PartialEq, Debug)]
+        enum Enum {
+            Unit,
+            Newtype(i32),
+            Tuple(i32, String),
+            Struct { b: i32, a: i32 },
+        }
+
+        for (value, expected) in [
+            (Enum::Unit, b"4:Unit".as_slice()),
+            (Enum::Newtype(1), b"d7:Newtypei1ee"),
+            (Enum::Tuple(1, "x".into()), b"d5:Tupleli1e1:xee"),
+            (Enum::Struct { b: 1, a: 2 }, b"d6:Structd1:ai2e1:bi1eee"),
+        ] {
+            let bytes = ser::to_bytes(&value).unwrap();
+            assert_eq!(bytes, expected);
+            assert_eq!(de::from_bytes::<Enum>(&bytes).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let mut writer = std::io::Cursor::new(vec![]);
+        ser::to_writer(&mut writer, &vec!["a", "b"]).unwrap();
+        assert_eq!(writer.into_inner(), b"l1:a1:be");
+    }
+}
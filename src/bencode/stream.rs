@@ -0,0 +1,1012 @@
+//! Deserializer reading bencode straight from a [BufRead], used by [de::from_reader].
+//!
+//! Only the token being read is kept in memory: the digits of an integer, or the content of a
+//! string, which is skipped without being stored when the target ignores it.
+use core::fmt::{Display, Formatter};
+use core::mem;
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Read};
+
+use serde::de::value::SeqDeserializer;
+use serde::de::{
+    DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::{forward_to_deserialize_any, Deserializer};
+
+use super::de::{parse_key_digits, FromDigits};
+use super::push::shift_offset;
+use super::*;
+use super::Error::*;
+
+/// Longest integer or string length accepted, with the sign, any i64 or u64 fits
+const MAX_DIGITS: usize = 21;
+
+/// The start of a value, or the end of a list or dict
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Header {
+    /// The digits are in the scratch buffer
+    Int,
+    /// A string of this length, its content isn't read yet
+    Bytes(u64),
+    /// A dict key, its content is in the scratch buffer
+    Key,
+    List,
+    Dict,
+    End,
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Header::Int => write!(f, "Num"),
+            Header::Bytes(len) => write!(f, "String({})", len),
+            Header::Key => write!(f, "Key"),
+            Header::List => write!(f, "List"),
+            Header::Dict => write!(f, "Dict"),
+            Header::End => write!(f, "End"),
+        }
+    }
+}
+
+/// A dict being read, for the checks of its keys
+#[derive(Default)]
+struct DictState {
+    /// The next token is a key or the end of the dict
+    expect_key: bool,
+    /// Used in strict mode
+    last_key: Option<Vec<u8>>,
+    /// Used to find duplicates in unsorted dicts, unless the last one wins
+    seen: BTreeSet<Vec<u8>>,
+}
+
+pub(super) struct StreamDeserializer<R> {
+    reader: R,
+    options: ParserOptions,
+    /// Bytes consumed from the reader
+    offset: usize,
+    /// Open containers, `Some` for a dict
+    containers: Vec<Option<DictState>>,
+    /// The header read by [Self::peek], and where it starts
+    peeked: Option<(Header, usize)>,
+    /// Digits of the last integer, or content of the last string or key
+    scratch: Vec<u8>,
+}
+
+impl<R: BufRead> StreamDeserializer<R> {
+    pub(super) fn new(reader: R, options: ParserOptions) -> Self {
+        StreamDeserializer {
+            reader,
+            options,
+            offset: 0,
+            containers: vec![],
+            peeked: None,
+            scratch: vec![],
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = match self.reader.fill_buf()?.first() {
+            Some(byte) => *byte,
+            None => {
+                return Err(BencodeDecode {
+                    message: "unexpected EOF when parse token".to_string(),
+                    offset: self.offset,
+                })
+            }
+        };
+        self.reader.consume(1);
+        self.offset += 1;
+        Ok(byte)
+    }
+
+    /// Read digits up to `terminator` into the scratch buffer, checked as the parser does
+    fn read_digits(&mut self, first: Option<u8>, terminator: u8) -> Result<()> {
+        let start = self.offset - first.iter().count();
+        self.scratch.clear();
+        self.scratch.extend(first);
+        loop {
+            let byte = self.next_byte()?;
+            self.scratch.push(byte);
+            if byte == terminator {
+                break;
+            }
+            if self.scratch.len() > MAX_DIGITS {
+                return Err(BencodeDecode {
+                    message: format!("expect {} but get too many digits", terminator as char),
+                    offset: start,
+                });
+            }
+        }
+        BencodeParser::new(&self.scratch)
+            .take_int(terminator as char)
+            .map_err(|e| shift_offset(e, start))?;
+        self.scratch.pop();
+        Ok(())
+    }
+
+    fn read_header(&mut self) -> Result<Header> {
+        let start = self.offset;
+        let header = match self.next_byte()? {
+            b'i' => {
+                self.read_digits(None, b'e')?;
+                Header::Int
+            }
+            b'l' => Header::List,
+            b'd' => Header::Dict,
+            b'e' => Header::End,
+            digit @ b'0'..=b'9' => {
+                self.read_digits(Some(digit), b':')?;
+                let len = self.digits().parse::<u64>().map_err(|_| BencodeDecode {
+                    message: "invalid integer".to_string(),
+                    offset: start,
+                })?;
+                self.options.check_string_length(len, start)?;
+                self.options
+                    .check_total_size((self.offset as u64).saturating_add(len), start)?;
+                Header::Bytes(len)
+            }
+            byte => {
+                return Err(BencodeDecode {
+                    message: format!("invalid token {}", byte as char),
+                    offset: start,
+                })
+            }
+        };
+        self.options.check_total_size(self.offset as u64, start)?;
+        Ok(header)
+    }
+
+    /// The next header, not consumed. Dict keys are read and checked here, so values skipped
+    /// without decoding are checked as well.
+    fn peek(&mut self) -> Result<Header> {
+        loop {
+            if let Some((header, _)) = self.peeked {
+                return Ok(header);
+            }
+            let start = self.offset;
+            let header = match self.read_header()? {
+                Header::Bytes(len) if self.expects_key() => {
+                    self.read_bytes(len)?;
+                    Header::Key
+                }
+                header => header,
+            };
+            let keep = self.check_header(header, start)?;
+            self.peeked = Some((header, start));
+            if keep {
+                return Ok(header);
+            }
+            // A duplicate key to ignore, drop its value as well
+            self.peeked = None;
+            self.skip_value()?;
+        }
+    }
+
+    /// Consume the next header
+    fn next(&mut self) -> Result<(Header, usize)> {
+        self.peek()?;
+        Ok(self.peeked.take().unwrap())
+    }
+
+    /// Where the next value starts
+    fn next_offset(&self) -> usize {
+        self.peeked.map_or(self.offset, |(_, start)| start)
+    }
+
+    fn digits(&self) -> &str {
+        // Only ASCII digits and a sign, checked by `read_digits`
+        core::str::from_utf8(&self.scratch).unwrap()
+    }
+
+    fn expects_key(&self) -> bool {
+        matches!(self.containers.last(), Some(Some(dict)) if dict.expect_key)
+    }
+
+    /// Check the header against the open containers and the [ParserOptions], as the parser
+    /// does. Returns `false` for a duplicate key that should be ignored.
+    fn check_header(&mut self, header: Header, start: usize) -> Result<bool> {
+        let strict = self.options.strict;
+        let policy = self.options.duplicate_keys;
+        if let Some(Some(dict)) = self.containers.last_mut() {
+            match header {
+                Header::Key => {
+                    dict.expect_key = false;
+                    let key = &self.scratch;
+                    let reason = if strict {
+                        let reason = match &dict.last_key {
+                            Some(last_key) if key == last_key => Some("duplicate"),
+                            Some(last_key) if key < last_key => Some("unsorted"),
+                            _ => None,
+                        };
+                        dict.last_key = Some(key.clone());
+                        reason
+                    } else if policy == DuplicateKeyPolicy::LastWins
+                        || dict.seen.insert(key.clone())
+                    {
+                        None
+                    } else if policy == DuplicateKeyPolicy::FirstWins {
+                        return Ok(false);
+                    } else {
+                        Some("duplicate")
+                    };
+                    if let Some(reason) = reason {
+                        return Err(BencodeDecode {
+                            message: format!(
+                                "{} dict key {}",
+                                reason,
+                                String::from_utf8_lossy(key)
+                            ),
+                            offset: start,
+                        });
+                    }
+                    return Ok(true);
+                }
+                Header::End if dict.expect_key => {}
+                Header::End => {
+                    return Err(BencodeDecode {
+                        message: "expect dict value but get End".to_string(),
+                        offset: start,
+                    })
+                }
+                other if dict.expect_key => {
+                    return Err(BencodeDecode {
+                        message: format!("expect dict key but get {}", other),
+                        offset: start,
+                    })
+                }
+                // The header starts the value, a key follows it
+                _ => dict.expect_key = true,
+            }
+        }
+        match header {
+            Header::List | Header::Dict => {
+                if self.containers.len() >= self.options.max_depth {
+                    return Err(DepthLimitExceeded {
+                        max_depth: self.options.max_depth,
+                        offset: self.offset,
+                    });
+                }
+                self.containers.push(match header {
+                    Header::List => None,
+                    _ => Some(DictState {
+                        expect_key: true,
+                        ..DictState::default()
+                    }),
+                });
+            }
+            Header::End => {
+                if self.containers.pop().is_none() {
+                    return Err(BencodeDecode {
+                        message: "expect value but get End".to_string(),
+                        offset: start,
+                    });
+                }
+            }
+            Header::Int | Header::Bytes(_) | Header::Key => {}
+        }
+        Ok(true)
+    }
+
+    /// Read the `len` bytes of a string into the scratch buffer
+    fn read_bytes(&mut self, len: u64) -> Result<&[u8]> {
+        self.scratch.clear();
+        let read = (&mut self.reader)
+            .take(len)
+            .read_to_end(&mut self.scratch)?;
+        self.offset += read;
+        if (read as u64) < len {
+            return Err(BencodeDecode {
+                message: "unexpected EOF when read bytes".to_string(),
+                offset: self.offset,
+            });
+        }
+        Ok(&self.scratch)
+    }
+
+    /// Consume the next complete value, strings are dropped as they are read
+    fn skip_value(&mut self) -> Result<()> {
+        let depth = match self.next()? {
+            (Header::List, _) | (Header::Dict, _) => self.containers.len() - 1,
+            (Header::Bytes(len), _) => return self.skip_bytes(len),
+            (Header::Int, _) | (Header::Key, _) => return Ok(()),
+            (Header::End, start) => {
+                return Err(BencodeDecode {
+                    message: "expect value but get End".to_string(),
+                    offset: start,
+                })
+            }
+        };
+        while self.containers.len() > depth {
+            if let (Header::Bytes(len), _) = self.next()? {
+                self.skip_bytes(len)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn skip_bytes(&mut self, len: u64) -> Result<()> {
+        let read = io::copy(&mut (&mut self.reader).take(len), &mut io::sink())?;
+        self.offset += read as usize;
+        if read < len {
+            return Err(BencodeDecode {
+                message: "unexpected EOF when read bytes".to_string(),
+                offset: self.offset,
+            });
+        }
+        Ok(())
+    }
+
+    /// The content of the next string or key
+    fn next_bytes(&mut self, target: &str) -> Result<&[u8]> {
+        match self.next()? {
+            (Header::Bytes(len), _) => self.read_bytes(len),
+            (Header::Key, _) => Ok(&self.scratch),
+            (other, start) => Err(SerdeCustom(format!(
+                "expect bytes for {} but get {} at {}",
+                target, other, start
+            ))),
+        }
+    }
+
+    fn next_str(&mut self, target: &str) -> Result<&str> {
+        let start = self.next_offset();
+        let bytes = self.next_bytes(target)?;
+        core::str::from_utf8(bytes).map_err(|e| {
+            SerdeCustom(format!(
+                "UTF-8 error: {} when parse {} at {}",
+                e, target, start
+            ))
+        })
+    }
+
+    fn parse<T: FromDigits>(&mut self) -> Result<T> {
+        self.next_int(core::any::type_name::<T>())
+    }
+
+    fn next_int<T: FromDigits>(&mut self, target: &str) -> Result<T> {
+        let saturate = self.options.int_overflow == IntOverflowPolicy::Saturate;
+        match self.next()? {
+            (Header::Int, start) => T::from_digits(self.digits(), saturate).map_err(|e| {
+                SerdeCustom(format!(
+                    "invalid integer when parse {} at {}, {:?}",
+                    target, start, e
+                ))
+            }),
+            (other, start) => Err(SerdeCustom(format!(
+                "expect integer for {} but get {} at {}",
+                target, other, start
+            ))),
+        }
+    }
+
+    fn next_float<T: FromDigits>(&mut self, target: &str) -> Result<T> {
+        if !matches!(self.peek()?, Header::Bytes(_)) || !self.options.float_strings {
+            return self.next_int(target);
+        }
+        let start = self.next_offset();
+        T::from_digits(self.next_str(target)?, false).map_err(|e| {
+            SerdeCustom(format!(
+                "invalid float when parse {} at {}, {:?}",
+                target, start, e
+            ))
+        })
+    }
+
+    fn expect(&mut self, expected: Header, target: &str) -> Result<()> {
+        match self.next()? {
+            (header, _) if header == expected => Ok(()),
+            (other, start) => Err(SerdeCustom(format!(
+                "expect {} for {} but get {} at {}",
+                expected, target, other, start
+            ))),
+        }
+    }
+}
+
+/// Integers parsed by the `parse` method of a deserializer
+macro_rules! deserialize_digits {
+    ($($method:ident => $visit:ident: $number_type:ty),*) => {
+        $(fn $method<V>(self, visitor: V) -> Result<V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.$visit(self.parse::<$number_type>()?)
+        })*
+    };
+}
+
+impl<'de, R: BufRead> Deserializer<'de> for &mut StreamDeserializer<R> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            Header::Dict => self.deserialize_map(visitor),
+            Header::List => self.deserialize_seq(visitor),
+            Header::Int => self.deserialize_i64(visitor),
+            Header::Bytes(_) | Header::Key => {
+                let bytes = self.next_bytes("any")?;
+                match core::str::from_utf8(bytes) {
+                    Ok(str) => visitor.visit_str(str),
+                    Err(_) => visitor.visit_bytes(bytes),
+                }
+            }
+            Header::End => Err(SerdeCustom(format!(
+                "unexpected End at {} deserialize_any",
+                self.next_offset()
+            ))),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let start = self.next_offset();
+        match self.next_int::<i64>("bool")? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            other if self.options.strict_bools => Err(SerdeCustom(format!(
+                "expect 0 or 1 for bool but get {} at {}",
+                other, start
+            ))),
+            positive => visitor.visit_bool(positive > 0),
+        }
+    }
+
+    deserialize_digits!(
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64
+    );
+
+    fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f32(self.next_float("f32")?)
+    }
+
+    fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_f64(self.next_float("f64")?)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let start = self.next_offset();
+        let str = self.next_str("char")?;
+        if str.len() != 1 {
+            return Err(SerdeCustom(format!(
+                "expect char but get {} at {}",
+                str, start
+            )));
+        }
+        visitor.visit_char(str.chars().next().unwrap())
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(self.next_str("str")?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(self.next_str("string")?)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bytes(self.next_bytes("bytes")?)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bytes(self.next_bytes("byte_buf")?)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect(Header::List, "unit/unit_struct")?;
+        self.expect(Header::End, "unit/unit_struct")?;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if name == RAW_VALUE_TOKEN {
+            return Err(SerdeCustom(
+                "raw values borrow from the data, read them with from_reader_in".to_string(),
+            ));
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect(Header::List, "seq/tuple/tuple_struct")?;
+        let value = visitor.visit_seq(StreamSeq {
+            de: &mut *self,
+            index: 0,
+        })?;
+        self.expect(Header::End, "seq/tuple/tuple_struct")?;
+        Ok(value)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if !matches!(self.peek()?, Header::Bytes(_)) {
+            return self.deserialize_any(visitor);
+        }
+        // A byte array such as `[u8; 20]`, read from a string of that length
+        let start = self.next_offset();
+        let bytes = self.next_bytes("tuple")?;
+        if bytes.len() != len {
+            return Err(SerdeCustom(format!(
+                "expect {} bytes for tuple but get {} at {}",
+                len,
+                bytes.len(),
+                start
+            )));
+        }
+        visitor.visit_seq(SeqDeserializer::new(bytes.iter().copied()))
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect(Header::Dict, "map/struct")?;
+        let value = visitor.visit_map(StreamMap {
+            de: &mut *self,
+            key: None,
+        })?;
+        self.expect(Header::End, "map/struct")?;
+        Ok(value)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            Header::Dict => {
+                self.next()?;
+                visitor.visit_enum(self)
+            }
+            Header::Bytes(_) => {
+                let variant = self.next_str("enum")?.to_string();
+                visitor.visit_enum(variant.into_deserializer())
+            }
+            other => Err(SerdeCustom(format!(
+                "expect dict/bytes for enum but get {} at {}",
+                other,
+                self.next_offset()
+            ))),
+        }
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(self.next_str("identifier")?)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+}
+
+impl<'de, R: BufRead> VariantAccess<'de> for &mut StreamDeserializer<R> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self)?;
+        self.expect(Header::End, "newtype_variant_seed")?;
+        Ok(value)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = Deserializer::deserialize_seq(&mut *self, visitor)?;
+        self.expect(Header::End, "tuple_variant")?;
+        Ok(value)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let value = Deserializer::deserialize_map(&mut *self, visitor)?;
+        self.expect(Header::End, "struct_variant")?;
+        Ok(value)
+    }
+}
+
+impl<'de, R: BufRead> EnumAccess<'de> for &mut StreamDeserializer<R> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        Ok((seed.deserialize(&mut *self)?, self))
+    }
+}
+
+/// Reads the items of a list, counting them to report the path of errors
+struct StreamSeq<'a, R> {
+    de: &'a mut StreamDeserializer<R>,
+    index: usize,
+}
+
+impl<'de, R: BufRead> SeqAccess<'de> for StreamSeq<'_, R> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.de.peek()? == Header::End {
+            return Ok(None);
+        }
+        let offset = self.de.next_offset();
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|e| e.with_path_segment(&format!("[{}]", index), offset))
+    }
+}
+
+/// Reads the pairs of a dict, remembering the key to report the path of errors
+struct StreamMap<'a, R> {
+    de: &'a mut StreamDeserializer<R>,
+    key: Option<Vec<u8>>,
+}
+
+impl<'de, R: BufRead> MapAccess<'de> for StreamMap<'_, R> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        // Anything but a key or the end fails in `peek`
+        if self.de.peek()? == Header::End {
+            return Ok(None);
+        }
+        self.de.next()?;
+        let key = mem::take(&mut self.de.scratch);
+        let value = seed.deserialize(StreamKey {
+            key: &key,
+            lenient_ints: self.de.options.lenient_int_keys,
+        });
+        self.key = Some(key);
+        value.map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let offset = self.de.next_offset();
+        let key = self.key.take();
+        seed.deserialize(&mut *self.de).map_err(|e| match key {
+            Some(key) => e.with_path_segment(&String::from_utf8_lossy(&key), offset),
+            None => e,
+        })
+    }
+}
+
+/// Deserializer of a dict key read from a stream, see the `KeyDeserializer` of [de]
+struct StreamKey<'a> {
+    key: &'a [u8],
+    lenient_ints: bool,
+}
+
+impl StreamKey<'_> {
+    fn as_str(&self) -> Result<&str> {
+        core::str::from_utf8(self.key)
+            .map_err(|e| SerdeCustom(format!("UTF-8 error: {} when parse dict key", e)))
+    }
+
+    fn parse<T: FromDigits>(&self) -> Result<T> {
+        parse_key_digits(self.key, self.lenient_ints)
+    }
+}
+
+impl<'de> Deserializer<'de> for StreamKey<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        match core::str::from_utf8(self.key) {
+            Ok(str) => visitor.visit_str(str),
+            Err(_) => visitor.visit_bytes(self.key),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_str(self.as_str()?)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bytes(self.key)
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_bytes(self.key)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_seq(SeqDeserializer::new(self.key.iter().copied()))
+    }
+
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_enum(self.as_str()?.to_string().into_deserializer())
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_unit()
+    }
+
+    deserialize_digits!(
+        deserialize_i8 => visit_i8: i8,
+        deserialize_i16 => visit_i16: i16,
+        deserialize_i32 => visit_i32: i32,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u8: u8,
+        deserialize_u16 => visit_u16: u16,
+        deserialize_u32 => visit_u32: u32,
+        deserialize_u64 => visit_u64: u64
+    );
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char unit unit_struct tuple_struct map struct
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::io::{BufReader, Read};
+
+    use serde::Deserialize;
+
+    use crate::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Info {
+        name: String,
+        length: u64,
+    }
+
+    #[test]
+    fn test_stream_matches_from_bytes() {
+        let path = "./resources/debian-12.5.0-amd64-netinst.iso.torrent";
+        let bytes = std::fs::read(path).unwrap();
+        let file = BufReader::new(std::fs::File::open(path).unwrap());
+        let value: BencodeValue = de::from_reader(file).unwrap();
+        assert_eq!(value, de::from_bytes::<BencodeValue>(&bytes).unwrap());
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        enum Enum {
+            Unit,
+            Tuple(i8, char),
+            Struct { a: Option<u8>, b: [u8; 2] },
+        }
+        let data = b"l4:Unitd5:Tupleli-1e1:xeed6:Structd1:ai1e1:b2:\x01\x02eee";
+        let expected: Vec<Enum> = de::from_bytes(data).unwrap();
+        assert_eq!(
+            de::from_reader::<_, Vec<Enum>>(&data[..]).unwrap(),
+            expected
+        );
+        let data = b"d1:1i1e2:10i2ee";
+        let expected: BTreeMap<u32, bool> = de::from_bytes(data).unwrap();
+        assert_eq!(
+            de::from_reader::<_, BTreeMap<u32, bool>>(&data[..]).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_stream_skips_ignored_strings() {
+        // The 256 MiB string isn't needed by `Info`, it's dropped as it's read
+        let len = 1u64 << 28;
+        let head = format!("d6:lengthi{}e4:name4:demo6:pieces{}:", len, len);
+        let data = head
+            .as_bytes()
+            .chain(std::io::repeat(b'x').take(len))
+            .chain(b"ei7e".as_slice());
+        let mut reader = BufReader::new(data);
+        let info: Info = de::from_reader(&mut reader).unwrap();
+        assert_eq!(info.name, "demo");
+        // Only the value is consumed, the next one follows
+        assert_eq!(de::from_reader::<_, u8>(&mut reader).unwrap(), 7);
+
+        let truncated = head.as_bytes().chain(std::io::repeat(b'x').take(10));
+        let err = de::from_reader::<_, Info>(BufReader::new(truncated)).unwrap_err();
+        assert_eq!(err.decode_offset(), Some(head.len() + 10));
+    }
+
+    #[test]
+    fn test_stream_checks() {
+        let strict = ParserOptions::default().with_strict(true);
+        let err = de::from_reader_with_options::<_, BencodeValue>(&b"d1:bi1e1:ai2ee"[..], strict)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Decode error: unsorted dict key a at offset 7"
+        );
+        // Keys of skipped values are checked as well
+        let err = de::from_reader::<_, Info>(&b"d4:name1:x6:lengthi1e1:xdi1ei2eee"[..]);
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("expect dict key but get Num"));
+
+        let first_wins =
+            ParserOptions::default().with_duplicate_keys(DuplicateKeyPolicy::FirstWins);
+        let data = b"d6:lengthi1e4:name1:a4:name1:be";
+        let info: Info = de::from_reader_with_options(&data[..], first_wins).unwrap();
+        assert_eq!(info.name, "a");
+        assert!(de::from_reader::<_, Info>(&data[..]).is_err());
+
+        let err = de::from_reader::<_, Info>(&b"d6:lengthi-1e4:name1:ae"[..]).unwrap_err();
+        assert_eq!(err.path(), Some("length"));
+        assert!(de::from_reader::<_, BencodeValue>(&b"i01e"[..]).is_err());
+        assert!(de::from_reader::<_, BencodeValue>(&b"l"[..]).is_err());
+        let nested = [vec![b'l'; 100], vec![b'e'; 100]].concat();
+        let err = de::from_reader::<_, BencodeValue>(&nested[..]).unwrap_err();
+        assert!(matches!(
+            err.root_cause(),
+            Error::DepthLimitExceeded { max_depth: 64, .. }
+        ));
+    }
+}
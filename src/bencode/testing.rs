@@ -0,0 +1,63 @@
+//! [proptest] strategies for bencode, behind the "testing" feature.
+//!
+//! Example:
+//! ```
+//! use proptest::prelude::*;
+//! use ytorrent::{bencode_data, BencodeValue};
+//!
+//! proptest!(|(data in bencode_data())| {
+//!     let value = BencodeValue::from_bytes(&data).unwrap();
+//!     prop_assert_eq!(value.to_bytes(), data);
+//! });
+//! ```
+use std::collections::BTreeMap;
+
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::{btree_map, vec};
+use proptest::prelude::*;
+
+use super::*;
+
+/// Any [BencodeValue], nested up to 4 levels with up to 8 items per list or dict
+pub fn bencode_value() -> impl Strategy<Value = BencodeValue> {
+    let leaf = prop_oneof![
+        any::<i64>().prop_map(BencodeValue::Int),
+        vec(any::<u8>(), 0..32).prop_map(BencodeValue::Bytes),
+    ];
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..8).prop_map(BencodeValue::List),
+            btree_map(vec(any::<u8>(), 0..8), inner, 0..8)
+                .prop_map(|dict: BTreeMap<_, _>| BencodeValue::Dict(dict)),
+        ]
+    })
+}
+
+/// Valid bencode in canonical form, the encoding of [bencode_value]
+pub fn bencode_data() -> impl Strategy<Value = Vec<u8>> {
+    bencode_value().prop_map(|value| value.to_bytes())
+}
+
+impl Arbitrary for BencodeValue {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        bencode_value().boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #[test]
+        fn test_round_trip(value in any::<BencodeValue>()) {
+            let data = value.to_bytes();
+            prop_assert_eq!(BencodeValue::from_bytes(&data).unwrap(), value);
+            prop_assert!(validate(&data).is_ok());
+            prop_assert!(is_canonical(&data));
+        }
+    }
+}
@@ -1,7 +1,7 @@
-use std::fmt::{Display, Formatter};
+use core::fmt::{Display, Formatter};
 
 /// All possible token types for bencode
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 pub(super) enum Token<'a> {
     List,
     Dict,
@@ -11,7 +11,7 @@ pub(super) enum Token<'a> {
 }
 
 impl<'a> Display for Token<'a> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             Token::List => write!(f, "List"),
             Token::Dict => write!(f, "Dict"),
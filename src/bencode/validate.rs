@@ -0,0 +1,108 @@
+//! Syntax check of bencode, without building any value.
+//!
+//! Example:
+//! ```
+//! use ytorrent::validate;
+//!
+//! let report = validate(b"d4:infod6:lengthi1ee4:listl1:a1:bee").unwrap();
+//! assert_eq!((report.dicts, report.lists, report.strings, report.integers), (2, 1, 5, 1));
+//! assert_eq!(report.max_depth, 2);
+//! assert!(validate(b"d4:infoi1e").is_err());
+//! ```
+use super::*;
+use super::Error::*;
+
+/// What [validate] found in valid data
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub integers: usize,
+    pub strings: usize,
+    pub lists: usize,
+    pub dicts: usize,
+    /// Deepest nesting of lists and dicts, 0 for a single integer or string
+    pub max_depth: usize,
+}
+
+/// Check that `data` is exactly one bencode value, see [validate_with_options]
+pub fn validate(data: &[u8]) -> Result<ValidationReport> {
+    validate_with_options(data, ParserOptions::default())
+}
+
+/// Check that `data` is exactly one bencode value, counting its elements.
+///
+/// Only tokens are read, nothing is allocated per value, which makes it a cheap check before
+/// a full parse. The limits and key checks of `options` apply as they do to parsing.
+pub fn validate_with_options(data: &[u8], options: ParserOptions) -> Result<ValidationReport> {
+    let mut reader = EventReader::with_options(data, options);
+    let mut report = ValidationReport::default();
+    while let Some(event) = reader.next() {
+        match event?.0 {
+            ParseEvent::Int(_) => report.integers += 1,
+            ParseEvent::Key(_) | ParseEvent::StringValue(_) => report.strings += 1,
+            ParseEvent::ListStart => report.lists += 1,
+            ParseEvent::DictStart => report.dicts += 1,
+            ParseEvent::End => {}
+        }
+        report.max_depth = report.max_depth.max(reader.depth());
+    }
+    let consumed = reader.bytes_consumed();
+    if consumed < data.len() {
+        return Err(BencodeDecode {
+            message: format!("trailing {} bytes", data.len() - consumed),
+            offset: consumed,
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_offset(data: &[u8], offset: usize) {
+        assert_eq!(validate(data).unwrap_err().decode_offset(), Some(offset));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(
+            validate(b"i42e").unwrap(),
+            ValidationReport {
+                integers: 1,
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            validate(b"ld1:ald1:xi0eeee0:e").unwrap(),
+            ValidationReport {
+                integers: 1,
+                strings: 3,
+                lists: 2,
+                dicts: 2,
+                max_depth: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn test_validate_invalid() {
+        assert_offset(b"", 0);
+        assert_offset(b"e", 0);
+        assert_offset(b"i42ee", 4);
+        assert_offset(b"l1:a", 4);
+        assert_offset(b"di1e1:ae", 1);
+        assert_offset(b"d1:ae", 4);
+        assert_offset(b"d1:ai1e1:ble1:cee", 15);
+        assert_offset(b"i-0e", 2);
+    }
+
+    #[test]
+    fn test_validate_options() {
+        let data = b"d1:bi1e1:ai2ee";
+        assert!(validate(data).is_ok());
+        assert!(validate_with_options(data, ParserOptions::default().with_strict(true)).is_err());
+        assert!(
+            validate_with_options(b"lllleeee", ParserOptions::default().with_max_depth(3)).is_err()
+        );
+    }
+}
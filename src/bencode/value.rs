@@ -0,0 +1,565 @@
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::{SerializeMap, SerializeSeq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::*;
+
+/// Owned bencode value.
+///
+/// Unlike [Object], it doesn't borrow the parser, so it can be stored and moved
+/// freely.
+///
+/// Dicts are kept sorted by key as in canonical bencode, so two values are equal whatever
+/// the key order of the data they come from. Values order by type first, in the order of the
+/// variants, then by content: integers by value, strings and dict keys by their bytes.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BencodeValue {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<BencodeValue>),
+    Dict(BTreeMap<Vec<u8>, BencodeValue>),
+}
+
+impl BencodeValue {
+    /// Decode the first bencode value of `data`.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        parse_with(data, |object| object.into_owned_in(Some(data)))
+    }
+
+    /// Look up a nested value by a JSON-pointer-like path, e.g. `/info/files/0/path`.
+    ///
+    /// Each segment is a dict key, or an index into a list. As in
+    /// [RFC 6901](https://www.rfc-editor.org/rfc/rfc6901), `~1` stands for `/` and `~0` for `~`
+    /// within a segment. The empty path is the value itself.
+    pub fn pointer(&self, path: &str) -> Option<&BencodeValue> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        let path = path.strip_prefix('/')?;
+        path.split('/').try_fold(self, |value, segment| {
+            let segment = unescape_segment(segment);
+            match value {
+                BencodeValue::Dict(dict) => dict.get(segment.as_bytes()),
+                BencodeValue::List(list) => list.get(segment.parse::<usize>().ok()?),
+                _ => None,
+            }
+        })
+    }
+
+    /// Same as [Self::pointer], returning a mutable reference
+    pub fn pointer_mut(&mut self, path: &str) -> Option<&mut BencodeValue> {
+        if path.is_empty() {
+            return Some(self);
+        }
+        let path = path.strip_prefix('/')?;
+        path.split('/').try_fold(self, |value, segment| {
+            let segment = unescape_segment(segment);
+            match value {
+                BencodeValue::Dict(dict) => dict.get_mut(segment.as_bytes()),
+                BencodeValue::List(list) => list.get_mut(segment.parse::<usize>().ok()?),
+                _ => None,
+            }
+        })
+    }
+
+    /// Set the value at `path`, see [Self::pointer], returning the value it replaces.
+    ///
+    /// The parent must exist. In a dict the key is added or replaced, in a list the index
+    /// must be an existing item, or the length of the list to append. Everything else is left
+    /// as it is, so a document can be edited without a struct for it, e.g. to swap trackers:
+    ///
+    /// ```
+    /// use ytorrent::BencodeValue;
+    ///
+    /// let mut torrent = BencodeValue::from_bytes(b"d8:announce3:old4:infod6:lengthi1eee").unwrap();
+    /// torrent.insert("/announce", BencodeValue::Bytes(b"new".to_vec())).unwrap();
+    /// assert_eq!(torrent.to_bytes(), b"d8:announce3:new4:infod6:lengthi1eee");
+    /// ```
+    pub fn insert(&mut self, path: &str, value: BencodeValue) -> Result<Option<BencodeValue>> {
+        let (parent_path, segment) = split_last_segment(path)?;
+        let parent = self.pointer_mut(parent_path).ok_or_else(|| {
+            Error::InvalidArgument(format!("no value at {} to insert into", parent_path))
+        })?;
+        match parent {
+            BencodeValue::Dict(dict) => Ok(dict.insert(segment.into_bytes(), value)),
+            BencodeValue::List(list) => match segment.parse::<usize>() {
+                Ok(index) if index < list.len() => {
+                    Ok(Some(core::mem::replace(&mut list[index], value)))
+                }
+                Ok(index) if index == list.len() => {
+                    list.push(value);
+                    Ok(None)
+                }
+                _ => Err(Error::InvalidArgument(format!(
+                    "invalid index {} for a list of {} items",
+                    segment,
+                    list.len()
+                ))),
+            },
+            _ => Err(Error::InvalidArgument(format!(
+                "{} is not a dict or a list",
+                parent_path
+            ))),
+        }
+    }
+
+    /// Remove the value at `path`, see [Self::pointer]. Later items of a list move down.
+    pub fn remove(&mut self, path: &str) -> Option<BencodeValue> {
+        let (parent_path, segment) = split_last_segment(path).ok()?;
+        match self.pointer_mut(parent_path)? {
+            BencodeValue::Dict(dict) => dict.remove(segment.as_bytes()),
+            BencodeValue::List(list) => {
+                let index = segment.parse::<usize>().ok()?;
+                (index < list.len()).then(|| list.remove(index))
+            }
+            _ => None,
+        }
+    }
+
+    /// Encode the value to bencode, dict keys are written in sorted order.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(size_of_value(self));
+        self.write_to(&mut buffer);
+        buffer
+    }
+
+    fn write_to(&self, buffer: &mut Vec<u8>) {
+        match self {
+            BencodeValue::Int(int) => {
+                buffer.push(b'i');
+                buffer.extend_from_slice(int.to_string().as_bytes());
+                buffer.push(b'e');
+            }
+            BencodeValue::Bytes(bytes) => write_bytes(bytes, buffer),
+            BencodeValue::List(list) => {
+                buffer.push(b'l');
+                for item in list {
+                    item.write_to(buffer);
+                }
+                buffer.push(b'e');
+            }
+            BencodeValue::Dict(dict) => {
+                buffer.push(b'd');
+                for (key, value) in dict {
+                    write_bytes(key, buffer);
+                    value.write_to(buffer);
+                }
+                buffer.push(b'e');
+            }
+        }
+    }
+}
+
+fn unescape_segment(segment: &str) -> String {
+    segment.replace("~1", "/").replace("~0", "~")
+}
+
+/// Split `path` into the path of the parent and the unescaped last segment
+fn split_last_segment(path: &str) -> Result<(&str, String)> {
+    match path.rfind('/') {
+        Some(index) => Ok((&path[..index], unescape_segment(&path[index + 1..]))),
+        None => Err(Error::InvalidArgument(format!(
+            "path {:?} doesn't point into a dict or a list",
+            path
+        ))),
+    }
+}
+
+/// Convert `value` to a [BencodeValue], as [ser::to_bytes] encodes it
+pub fn to_value<T>(value: &T) -> Result<BencodeValue>
+where
+    T: ?Sized + Serialize,
+{
+    // Going through the encoded bytes keeps the rules of the serializer in one place, e.g.
+    // skipping `None` fields
+    BencodeValue::from_bytes(&ser::to_bytes(value)?)
+}
+
+/// Rendered by [pretty_print]
+impl fmt::Display for BencodeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&dump(&self.to_bytes()))
+    }
+}
+
+/// Convert a [BencodeValue] to `T`, as [de::from_bytes] decodes its encoded bytes
+pub fn from_value<T>(value: &BencodeValue) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    de::from_bytes(&value.to_bytes())
+}
+
+impl<'obj, 'de: 'obj> TryFrom<Object<'obj, 'de>> for BencodeValue {
+    type Error = Error;
+
+    /// See [Object::into_owned]
+    fn try_from(object: Object<'obj, 'de>) -> Result<Self> {
+        object.into_owned()
+    }
+}
+
+impl Serialize for BencodeValue {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            BencodeValue::Int(int) => serializer.serialize_i64(*int),
+            BencodeValue::Bytes(bytes) => serializer.serialize_bytes(bytes),
+            BencodeValue::List(list) => {
+                let mut seq = serializer.serialize_seq(Some(list.len()))?;
+                for item in list {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            BencodeValue::Dict(dict) => {
+                let mut map = serializer.serialize_map(Some(dict.len()))?;
+                for (key, value) in dict {
+                    map.serialize_entry(&RawBytes(key), value)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+/// Serialize dict keys as bytes rather than a list of integers
+pub(super) struct RawBytes<'a>(pub(super) &'a [u8]);
+
+impl Serialize for RawBytes<'_> {
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for BencodeValue {
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = BencodeValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any bencode value")
+            }
+
+            fn visit_bool<E>(self, v: bool) -> core::result::Result<Self::Value, E> {
+                Ok(BencodeValue::Int(v as i64))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> core::result::Result<Self::Value, E> {
+                Ok(BencodeValue::Int(v))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> core::result::Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                i64::try_from(v)
+                    .map(BencodeValue::Int)
+                    .map_err(|_| E::custom(format!("integer {} out of range", v)))
+            }
+
+            fn visit_str<E>(self, v: &str) -> core::result::Result<Self::Value, E> {
+                Ok(BencodeValue::Bytes(v.as_bytes().to_vec()))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> core::result::Result<Self::Value, E> {
+                Ok(BencodeValue::Bytes(v.to_vec()))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut list = vec![];
+                while let Some(item) = seq.next_element()? {
+                    list.push(item);
+                }
+                Ok(BencodeValue::List(list))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> core::result::Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut dict = BTreeMap::new();
+                while let Some((key, value)) = map.next_entry()? {
+                    match key {
+                        BencodeValue::Bytes(key) => dict.insert(key, value),
+                        _ => return Err(serde::de::Error::custom("dict key is not bytes")),
+                    };
+                }
+                Ok(BencodeValue::Dict(dict))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+pub(super) fn write_bytes(bytes: &[u8], buffer: &mut Vec<u8>) {
+    buffer.extend_from_slice(bytes.len().to_string().as_bytes());
+    buffer.push(b':');
+    buffer.extend_from_slice(bytes);
+}
+
+/// Number of bytes [BencodeValue::to_bytes] will produce for `value`, without encoding it.
+pub fn size_of_value(value: &BencodeValue) -> usize {
+    match value {
+        // "i" + digits + "e"
+        BencodeValue::Int(int) => 2 + int_len(*int),
+        BencodeValue::Bytes(bytes) => bytes_len(bytes),
+        // "l" + items + "e"
+        BencodeValue::List(list) => 2 + list.iter().map(size_of_value).sum::<usize>(),
+        // "d" + pairs + "e"
+        BencodeValue::Dict(dict) => {
+            2 + dict
+                .iter()
+                .map(|(key, value)| bytes_len(key) + size_of_value(value))
+                .sum::<usize>()
+        }
+    }
+}
+
+/// Length of "<len>:<data>"
+fn bytes_len(bytes: &[u8]) -> usize {
+    digits_len(bytes.len() as u64) + 1 + bytes.len()
+}
+
+/// Length of the decimal representation of `int`, including the sign
+fn int_len(int: i64) -> usize {
+    let sign = if int < 0 { 1 } else { 0 };
+    sign + digits_len(int.unsigned_abs())
+}
+
+fn digits_len(mut num: u64) -> usize {
+    let mut len = 1;
+    while num >= 10 {
+        num /= 10;
+        len += 1;
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_size(value: BencodeValue) {
+        assert_eq!(size_of_value(&value), value.to_bytes().len());
+    }
+
+    #[test]
+    fn test_object_into_owned() {
+        let mut parser = BencodeParser::new(b"d4:listli1e1:ae3:numi-7ee");
+        let Some(Object::Dict(mut decoder)) = parser.parse().unwrap() else {
+            unreachable!()
+        };
+        let (key, list) = decoder.next_pair().unwrap().unwrap();
+        assert_eq!(key, b"list");
+        assert_eq!(
+            list.into_owned().unwrap(),
+            BencodeValue::List(vec![
+                BencodeValue::Int(1),
+                BencodeValue::Bytes(b"a".to_vec())
+            ])
+        );
+        let (key, num) = decoder.next_pair().unwrap().unwrap();
+        assert_eq!(key, b"num");
+        assert_eq!(num.into_owned().unwrap(), BencodeValue::Int(-7));
+        assert!(decoder.next_pair().unwrap().is_none());
+
+        let err = BencodeValue::from_bytes(b"li1ei99999999999999999999ee").unwrap_err();
+        assert_eq!(err.decode_offset(), Some(4));
+        let err = BencodeValue::from_bytes(b"i99999999999999999999e").unwrap_err();
+        assert_eq!(err.decode_offset(), Some(0));
+        let err = BencodeValue::from_bytes(b"e").unwrap_err();
+        assert_eq!(err.decode_offset(), Some(0));
+
+        // A lone integer doesn't know its offset
+        let mut parser = BencodeParser::new(b"i99999999999999999999e");
+        let int = parser.parse().unwrap().unwrap();
+        assert!(matches!(int.into_owned(), Err(Error::SerdeCustom(_))));
+    }
+
+    #[test]
+    fn test_size_of_int() {
+        for int in [0, 1, 9, 10, 42, -1, -10, 1234567890, i64::MAX, i64::MIN] {
+            assert_size(BencodeValue::Int(int));
+        }
+        assert_eq!(BencodeValue::Int(-42).to_bytes(), b"i-42e");
+    }
+
+    #[test]
+    fn test_size_of_bytes() {
+        assert_size(BencodeValue::Bytes(vec![]));
+        assert_size(BencodeValue::Bytes(b"spam".to_vec()));
+        assert_size(BencodeValue::Bytes(vec![0; 1500]));
+        assert_eq!(BencodeValue::Bytes(b"spam".to_vec()).to_bytes(), b"4:spam");
+    }
+
+    #[test]
+    fn test_size_of_list_and_dict() {
+        let list = BencodeValue::List(vec![
+            BencodeValue::Bytes(b"spam".to_vec()),
+            BencodeValue::Int(42),
+            BencodeValue::List(vec![]),
+        ]);
+        assert_size(list.clone());
+        assert_eq!(list.to_bytes(), b"l4:spami42elee");
+
+        let dict = BencodeValue::Dict(BTreeMap::from([
+            (b"spam".to_vec(), BencodeValue::Bytes(b"eggs".to_vec())),
+            (b"cow".to_vec(), BencodeValue::Bytes(b"moo".to_vec())),
+            (b"list".to_vec(), list),
+            (b"dict".to_vec(), BencodeValue::Dict(BTreeMap::new())),
+        ]));
+        assert_size(dict.clone());
+        assert_eq!(
+            dict.to_bytes(),
+            b"d3:cow3:moo4:dictde4:listl4:spami42elee4:spam4:eggse"
+        );
+    }
+
+    #[test]
+    fn test_se_de_value() {
+        let data = b"d3:cow3:moo4:dictde4:listl4:spami-42elee4:spam4:eggse";
+        let value: BencodeValue = de::from_bytes(data).unwrap();
+        assert_eq!(value, BencodeValue::from_bytes(data).unwrap());
+        assert_eq!(ser::to_bytes(&value).unwrap(), data);
+        assert_eq!(value.to_bytes(), data);
+
+        let mut parser = BencodeParser::new(data);
+        let object = parser.parse().unwrap().unwrap();
+        assert_eq!(BencodeValue::try_from(object).unwrap(), value);
+
+        // Unknown parts of a typed struct can be kept as they are
+        #[derive(serde::Deserialize)]
+        struct Meta {
+            info: BencodeValue,
+        }
+        let data = std::fs::read("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let meta: Meta = de::from_bytes(&data).unwrap();
+        let BencodeValue::Dict(info) = &meta.info else {
+            unreachable!()
+        };
+        assert_eq!(info[b"length".as_slice()], BencodeValue::Int(659554304));
+        let info_bytes = ser::to_bytes(&meta.info).unwrap();
+        let info: crate::Info = de::from_bytes(&info_bytes).unwrap();
+        assert_eq!(info.total_length(), 659554304);
+    }
+
+    #[test]
+    fn test_pointer() {
+        let value = BencodeValue::from_bytes(
+            b"d4:infod5:filesld6:lengthi1e4:pathl1:a1:beee4:name4:teste3:a/bi1e3:~/~i2ee",
+        )
+        .unwrap();
+        assert_eq!(value.pointer(""), Some(&value));
+        assert_eq!(
+            value.pointer("/info/files/0/path/1"),
+            Some(&BencodeValue::Bytes(b"b".to_vec()))
+        );
+        assert_eq!(
+            value.pointer("/info/files/0/length"),
+            Some(&BencodeValue::Int(1))
+        );
+        assert_eq!(value.pointer("/a~1b"), Some(&BencodeValue::Int(1)));
+        assert_eq!(value.pointer("/~0~1~0"), Some(&BencodeValue::Int(2)));
+        for missing in [
+            "info",
+            "/info/files/1",
+            "/info/files/-1",
+            "/info/files/first",
+            "/info/name/0",
+            "/info/",
+        ] {
+            assert_eq!(value.pointer(missing), None, "{}", missing);
+        }
+    }
+
+    #[test]
+    fn test_edit() {
+        let data = b"d8:announce3:old4:infod6:lengthi1ee3:urlll1:aeee";
+        let mut value = BencodeValue::from_bytes(data).unwrap();
+        let bytes = |str: &str| BencodeValue::Bytes(str.as_bytes().to_vec());
+
+        assert_eq!(
+            value.insert("/announce", bytes("new")).unwrap(),
+            Some(bytes("old"))
+        );
+        assert_eq!(value.insert("/a~1b", BencodeValue::Int(1)).unwrap(), None);
+        assert_eq!(value.insert("/url/1", bytes("b")).unwrap(), None);
+        assert_eq!(
+            value.insert("/url/0", BencodeValue::List(vec![])).unwrap(),
+            Some(BencodeValue::List(vec![bytes("a")]))
+        );
+        assert!(value.insert("/url/3", bytes("c")).is_err());
+        assert!(value.insert("/missing/key", bytes("c")).is_err());
+        assert!(value.insert("/info/length/x", bytes("c")).is_err());
+        assert!(value.insert("", bytes("c")).is_err());
+        assert_eq!(
+            value.to_bytes(),
+            b"d3:a/bi1e8:announce3:new4:infod6:lengthi1ee3:urllle1:bee"
+        );
+
+        assert_eq!(value.remove("/url/0"), Some(BencodeValue::List(vec![])));
+        assert_eq!(value.remove("/a~1b"), Some(BencodeValue::Int(1)));
+        assert_eq!(value.remove("/url/5"), None);
+        assert_eq!(value.remove("/missing"), None);
+        assert_eq!(value.remove(""), None);
+        *value.pointer_mut("/info/length").unwrap() = BencodeValue::Int(2);
+        assert_eq!(
+            value.to_bytes(),
+            b"d8:announce3:new4:infod6:lengthi2ee3:urll1:bee"
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let value = BencodeValue::from_bytes(b"d4:spaml1:ai1eee").unwrap();
+        assert_eq!(value.to_string(), "{\n  spam: [\n    \"a\",\n    1\n  ]\n}");
+    }
+
+    #[test]
+    fn test_to_from_value() {
+        let data = std::fs::read("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let meta: crate::MetaInfo = de::from_bytes(&data).unwrap();
+        let mut value = to_value(&meta).unwrap();
+        assert_eq!(
+            value.pointer("/info/name"),
+            Some(&BencodeValue::Bytes(
+                b"debian-12.5.0-amd64-netinst.iso".to_vec()
+            ))
+        );
+        assert_eq!(
+            value.pointer("/creation date"),
+            Some(&BencodeValue::Int(1707570148))
+        );
+
+        // Edit a key the typed struct doesn't know, and one it knows
+        let BencodeValue::Dict(dict) = &mut value else {
+            unreachable!()
+        };
+        dict.insert(b"comment".to_vec(), BencodeValue::Bytes(b"edited".to_vec()));
+        dict.insert(b"x-custom".to_vec(), BencodeValue::Int(1));
+        let edited: crate::MetaInfo = from_value(&value).unwrap();
+        assert_eq!(edited.comment.as_deref(), Some("edited"));
+        assert_eq!(edited.info.pieces, meta.info.pieces);
+
+        assert_eq!(to_value(&vec![1, 2]).unwrap().to_bytes(), b"li1ei2ee");
+        assert!(from_value::<i64>(&BencodeValue::List(vec![])).is_err());
+    }
+}
@@ -0,0 +1,25 @@
+use super::*;
+
+/// Stand-in for `std::io::Write` without the "std" feature, for the serializer and the
+/// pretty-printer. Only `Vec<u8>` can be written to.
+pub trait Write {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()>;
+
+    /// Used by `write!`
+    fn write_fmt(&mut self, args: core::fmt::Arguments<'_>) -> Result<()> {
+        self.write_all(alloc::fmt::format(args).as_bytes())
+    }
+}
+
+impl Write for Vec<u8> {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Write for &mut W {
+    fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+        (**self).write_all(bytes)
+    }
+}
@@ -1,3 +1,11 @@
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::{
+    boxed::Box,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 pub use result::*;
 
 mod result;
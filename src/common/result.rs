@@ -1,27 +1,213 @@
-use std::fmt::{Display, Formatter};
+use core::fmt::{Display, Formatter};
 
+#[cfg(not(feature = "std"))]
+use super::*;
+
+/// Errors of the crate.
+///
+/// Errors raised while deserializing a value inside a list or dict are wrapped in
+/// [Error::Path], whatever their kind, so matching on the variant of a decode error needs
+/// [Error::root_cause]:
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use ytorrent::{de, Error};
+///
+/// let err = de::from_bytes::<BTreeMap<String, u8>>(b"d1:ai1x2ee").unwrap_err();
+/// assert_eq!(err.path(), Some("a"));
+/// assert!(matches!(err.root_cause(), Error::BencodeDecode { offset: 6, .. }));
+/// ```
 #[derive(Debug)]
 pub enum Error {
-    BencodeDecode(String),
+    /// Malformed bencode, `offset` is the position in the data where decoding failed
+    BencodeDecode {
+        message: String,
+        offset: usize,
+    },
+    /// Bencode nested deeper than the parser allows, see `ParserOptions::max_depth`
+    DepthLimitExceeded {
+        max_depth: usize,
+        offset: usize,
+    },
     Request(String),
     SerdeCustom(String),
+    /// An argument doesn't match the data it is applied to
+    InvalidArgument(String),
+    /// The tracker refused to serve the torrent, retrying won't help
+    TrackerRejection(String),
+    /// The tracker answered with a failure reason, retrying won't help
+    TrackerFailure(String),
+    /// The server couldn't be reached or didn't answer in time, retrying may help
+    Connection(String),
+    /// The server answered with an HTTP error status, retrying won't help
+    HttpStatus(u16),
+    /// A peer sent something that doesn't follow the wire protocol
+    PeerProtocol(String),
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// Deserializing the value at `path`, e.g. `info.files[3].length`, failed with `source`.
+    ///
+    /// `offset` is where that value starts. Any other variant may be the `source`, it's never
+    /// a `Path` itself, see [Error::root_cause].
+    Path {
+        path: String,
+        offset: usize,
+        source: Box<Error>,
+    },
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl Error {
+    /// Position in the data of a [Error::BencodeDecode] or [Error::DepthLimitExceeded]
+    pub fn decode_offset(&self) -> Option<usize> {
+        match self {
+            Error::BencodeDecode { offset, .. } | Error::DepthLimitExceeded { offset, .. } => {
+                Some(*offset)
+            }
+            Error::Path { offset, source, .. } => source.decode_offset().or(Some(*offset)),
+            _ => None,
+        }
+    }
+
+    /// Key path of the value which failed to deserialize, see [Error::Path]
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Path { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// The error without the path context
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Error::Path { source, .. } => source,
+            other => other,
+        }
+    }
+
+    /// Add the bytes of `data` around the decode offset to the message, as hex and ASCII,
+    /// `window` bytes on each side.
+    ///
+    /// `data` must be what was being decoded. Errors without an offset are returned as is.
+    pub fn with_hex_context(self, data: &[u8], window: usize) -> Error {
+        match self.decode_offset() {
+            Some(offset) => {
+                let context = hex_context(data, offset, window);
+                self.map_message(|message| format!("{} near {}", message, context))
+            }
+            None => self,
+        }
+    }
+
+    fn map_message(self, f: impl FnOnce(String) -> String) -> Error {
+        match self {
+            Error::BencodeDecode { message, offset } => Error::BencodeDecode {
+                message: f(message),
+                offset,
+            },
+            Error::SerdeCustom(message) => Error::SerdeCustom(f(message)),
+            Error::Path {
+                path,
+                offset,
+                source,
+            } => Error::Path {
+                path,
+                offset,
+                source: Box::new(source.map_message(f)),
+            },
+            other => other,
+        }
+    }
+
+    /// Put `segment`, a dict key or a `[index]`, in front of the path of the error
+    pub(crate) fn with_path_segment(self, segment: &str, offset: usize) -> Error {
+        match self {
+            Error::Path {
+                path,
+                offset,
+                source,
+            } => {
+                let separator = if path.starts_with('[') { "" } else { "." };
+                Error::Path {
+                    path: format!("{}{}{}", segment, separator, path),
+                    offset,
+                    source,
+                }
+            }
+            other => Error::Path {
+                path: segment.to_string(),
+                offset,
+                source: Box::new(other),
+            },
+        }
+    }
+}
+
+/// Format the bytes around `offset` like `69 [78] 32 |i[x]2|`, the byte at `offset` in brackets
+fn hex_context(data: &[u8], offset: usize, window: usize) -> String {
+    let start = offset.saturating_sub(window).min(data.len());
+    let end = offset.saturating_add(window + 1).min(data.len());
+    let mut hex = vec![];
+    let mut ascii = String::new();
+    for (position, byte) in data.iter().enumerate().take(end).skip(start) {
+        let char = if byte.is_ascii_graphic() || *byte == b' ' {
+            *byte as char
+        } else {
+            '.'
+        };
+        if position == offset {
+            hex.push(format!("[{:02x}]", byte));
+            ascii.push_str(&format!("[{}]", char));
+        } else {
+            hex.push(format!("{:02x}", byte));
+            ascii.push(char);
+        }
+    }
+    format!("{} |{}|", hex.join(" "), ascii)
+}
 
+#[cfg(feature = "tracker")]
 impl From<reqwest::Error> for Error {
     fn from(err: reqwest::Error) -> Self {
-        Error::Request(format!("{:?}", err))
+        if err.is_timeout() || err.is_connect() || err.is_request() || err.is_body() {
+            Error::Connection(format!("{:?}", err))
+        } else {
+            Error::Request(format!("{:?}", err))
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
     }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            #[cfg(feature = "std")]
+            Error::Io(err) => Some(err),
+            Error::Path { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 impl Display for Error {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
-            Error::BencodeDecode(str) => {
-                write!(f, "Decode error: {}", str)
+            Error::BencodeDecode { message, offset } => {
+                write!(f, "Decode error: {} at offset {}", message, offset)
+            }
+            Error::DepthLimitExceeded { max_depth, offset } => {
+                write!(
+                    f,
+                    "Decode error: max recursion depth {} exceeded at offset {}",
+                    max_depth, offset
+                )
             }
             Error::Request(str) => {
                 write!(f, "Request error: {}", str)
@@ -29,6 +215,31 @@ impl Display for Error {
             Error::SerdeCustom(str) => {
                 write!(f, "Serde custom error: {}", str)
             }
+            Error::InvalidArgument(str) => {
+                write!(f, "Invalid argument: {}", str)
+            }
+            Error::TrackerRejection(str) => {
+                write!(f, "Tracker rejection: {}", str)
+            }
+            Error::TrackerFailure(str) => {
+                write!(f, "Tracker failure: {}", str)
+            }
+            Error::Connection(str) => {
+                write!(f, "Connection error: {}", str)
+            }
+            Error::HttpStatus(status) => {
+                write!(f, "HTTP status error: {}", status)
+            }
+            Error::PeerProtocol(str) => {
+                write!(f, "Peer protocol error: {}", str)
+            }
+            #[cfg(feature = "std")]
+            Error::Io(err) => {
+                write!(f, "IO error: {}", err)
+            }
+            Error::Path { path, source, .. } => {
+                write!(f, "{} at path {}", source, path)
+            }
         }
     }
 }
@@ -41,3 +252,33 @@ impl serde::de::Error for Error {
         Error::SerdeCustom(msg.to_string())
     }
 }
+
+impl serde::ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error::SerdeCustom(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BencodeValue;
+
+    #[test]
+    fn test_decode_offset() {
+        // `BencodeDecode` used to be a tuple variant of the message only, matching it is a
+        // breaking change
+        let err = BencodeValue::from_bytes(b"i12").unwrap_err();
+        assert!(matches!(
+            &err,
+            Error::BencodeDecode { message, offset: 3 } if message == "unexpected EOF"
+        ));
+        assert_eq!(err.decode_offset(), Some(3));
+        assert_eq!(err.to_string(), "Decode error: unexpected EOF at offset 3");
+
+        assert_eq!(Error::Request("down".to_string()).decode_offset(), None);
+    }
+}
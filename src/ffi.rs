@@ -0,0 +1,411 @@
+//! C interface to the bencode parser and encoder, behind the "ffi" feature. The crate is only
+//! an rlib, build the shared library with `cargo rustc --release --features ffi --crate-type
+//! cdylib`. The declarations are in `include/ytorrent.h`.
+//!
+//! Values are opaque `YtValue` handles. A handle from [yt_parse] is freed with
+//! [yt_value_free], handles from [yt_get_path] borrow from their root and must not be freed.
+//!
+//! ```c
+//! YtError error;
+//! YtValue *torrent = yt_parse(data, len, &error);
+//! if (!torrent && error.has_offset) {
+//!     fprintf(stderr, "%s at %zu\n", error.message, error.offset);
+//! }
+//! const YtValue *name = yt_get_path(torrent, "/info/name");
+//! YtSlice bytes;
+//! if (name && yt_value_bytes(name, &bytes)) { /* ... */ }
+//! yt_value_free(torrent);
+//! ```
+use std::ffi::{c_char, CStr};
+use std::ptr;
+
+use super::*;
+
+/// Opaque handle to a bencode value
+#[repr(transparent)]
+pub struct YtValue(BencodeValue);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YtErrorCode {
+    Ok = 0,
+    /// Invalid bencode, see [YtError::offset]
+    Decode = 1,
+    /// A null pointer or a path which isn't UTF-8
+    InvalidArgument = 2,
+}
+
+/// Why a call failed
+#[repr(C)]
+pub struct YtError {
+    pub code: YtErrorCode,
+    /// Whether [Self::offset] is set
+    pub has_offset: bool,
+    /// Where the data is invalid, for [YtErrorCode::Decode]
+    pub offset: usize,
+    /// NUL terminated, cut to fit
+    pub message: [c_char; 256],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YtType {
+    Int = 0,
+    Bytes = 1,
+    List = 2,
+    Dict = 3,
+}
+
+/// Bytes borrowed from a value
+#[repr(C)]
+pub struct YtSlice {
+    pub data: *const u8,
+    pub len: usize,
+}
+
+/// Bytes owned by the caller, freed with [yt_buffer_free]
+#[repr(C)]
+pub struct YtBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+}
+
+impl YtError {
+    fn set(error: *mut YtError, code: YtErrorCode, offset: Option<usize>, message: &str) {
+        // SAFETY: the caller passes null or a valid pointer
+        let Some(error) = (unsafe { error.as_mut() }) else {
+            return;
+        };
+        error.code = code;
+        error.has_offset = offset.is_some();
+        error.offset = offset.unwrap_or(0);
+        let len = message.len().min(error.message.len() - 1);
+        for (dst, src) in error.message.iter_mut().zip(&message.as_bytes()[..len]) {
+            *dst = *src as c_char;
+        }
+        error.message[len] = 0;
+    }
+}
+
+/// Parse the first bencode value of `len` bytes at `data`, null on failure.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes, `error` must be null or point to a [YtError].
+#[no_mangle]
+pub unsafe extern "C" fn yt_parse(
+    data: *const u8,
+    len: usize,
+    error: *mut YtError,
+) -> *mut YtValue {
+    if data.is_null() {
+        YtError::set(error, YtErrorCode::InvalidArgument, None, "data is null");
+        return ptr::null_mut();
+    }
+    let data = std::slice::from_raw_parts(data, len);
+    match BencodeValue::from_bytes(data) {
+        Ok(value) => {
+            YtError::set(error, YtErrorCode::Ok, None, "");
+            Box::into_raw(Box::new(YtValue(value)))
+        }
+        Err(e) => {
+            YtError::set(
+                error,
+                YtErrorCode::Decode,
+                e.decode_offset(),
+                &e.to_string(),
+            );
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a value returned by [yt_parse]
+///
+/// # Safety
+///
+/// `value` must be null or returned by [yt_parse], and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn yt_value_free(value: *mut YtValue) {
+    if !value.is_null() {
+        drop(Box::from_raw(value));
+    }
+}
+
+/// The value at a NUL terminated `path` such as `/info/files/0`, see [BencodeValue::pointer].
+/// Null if there is none.
+///
+/// # Safety
+///
+/// `value` must be a live handle, `path` a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn yt_get_path(value: *const YtValue, path: *const c_char) -> *const YtValue {
+    if value.is_null() || path.is_null() {
+        return ptr::null();
+    }
+    let Ok(path) = CStr::from_ptr(path).to_str() else {
+        return ptr::null();
+    };
+    match (*value).0.pointer(path) {
+        // YtValue is a transparent wrapper, a nested value can be handed out as one
+        Some(found) => found as *const BencodeValue as *const YtValue,
+        None => ptr::null(),
+    }
+}
+
+/// The type of `value`, [YtType::Int] for null
+///
+/// # Safety
+///
+/// `value` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn yt_value_type(value: *const YtValue) -> YtType {
+    let Some(value) = value.as_ref() else {
+        return YtType::Int;
+    };
+    match &value.0 {
+        BencodeValue::Int(_) => YtType::Int,
+        BencodeValue::Bytes(_) => YtType::Bytes,
+        BencodeValue::List(_) => YtType::List,
+        BencodeValue::Dict(_) => YtType::Dict,
+    }
+}
+
+/// Store the integer in `out`, returns false if `value` isn't an integer or a pointer is null
+///
+/// # Safety
+///
+/// `value` must be null or a live handle, `out` null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn yt_value_int(value: *const YtValue, out: *mut i64) -> bool {
+    if value.is_null() || out.is_null() {
+        return false;
+    }
+    match &(*value).0 {
+        BencodeValue::Int(int) => {
+            *out = *int;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Store the bytes in `out`, valid as long as `value`. Returns false if `value` isn't a string
+/// or a pointer is null.
+///
+/// # Safety
+///
+/// `value` must be null or a live handle, `out` null or a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn yt_value_bytes(value: *const YtValue, out: *mut YtSlice) -> bool {
+    if value.is_null() || out.is_null() {
+        return false;
+    }
+    match &(*value).0 {
+        BencodeValue::Bytes(bytes) => {
+            *out = YtSlice {
+                data: bytes.as_ptr(),
+                len: bytes.len(),
+            };
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Number of items of a list or pairs of a dict, 0 for other values and null
+///
+/// # Safety
+///
+/// `value` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn yt_value_len(value: *const YtValue) -> usize {
+    let Some(value) = value.as_ref() else {
+        return 0;
+    };
+    match &value.0 {
+        BencodeValue::List(list) => list.len(),
+        BencodeValue::Dict(dict) => dict.len(),
+        _ => 0,
+    }
+}
+
+/// Encode `value` in canonical bencode, an empty buffer with null `data` for null
+///
+/// # Safety
+///
+/// `value` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn yt_encode(value: *const YtValue) -> YtBuffer {
+    let Some(value) = value.as_ref() else {
+        return YtBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+        };
+    };
+    let bytes = value.0.to_bytes().into_boxed_slice();
+    let len = bytes.len();
+    YtBuffer {
+        data: Box::into_raw(bytes) as *mut u8,
+        len,
+    }
+}
+
+/// Free a buffer returned by [yt_encode]
+///
+/// # Safety
+///
+/// `buffer` must be returned by [yt_encode], and not freed before. An empty buffer with null
+/// `data` may be freed.
+#[no_mangle]
+pub unsafe extern "C" fn yt_buffer_free(buffer: YtBuffer) {
+    if !buffer.data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(
+            buffer.data,
+            buffer.len,
+        )));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_error() -> YtError {
+        YtError {
+            code: YtErrorCode::Ok,
+            has_offset: false,
+            offset: 0,
+            message: [0; 256],
+        }
+    }
+
+    #[test]
+    fn test_parse_and_get() {
+        let data = b"d4:infod6:lengthi42e4:name4:testee";
+        let mut error = new_error();
+        unsafe {
+            let value = yt_parse(data.as_ptr(), data.len(), &mut error);
+            assert!(!value.is_null());
+            assert_eq!(error.code, YtErrorCode::Ok);
+            assert_eq!(yt_value_type(value), YtType::Dict);
+            assert_eq!(yt_value_len(value), 1);
+
+            let length = yt_get_path(value, c"/info/length".as_ptr());
+            let mut int = 0;
+            assert!(yt_value_int(length, &mut int));
+            assert_eq!(int, 42);
+
+            let name = yt_get_path(value, c"/info/name".as_ptr());
+            let mut slice = YtSlice {
+                data: ptr::null(),
+                len: 0,
+            };
+            assert!(yt_value_bytes(name, &mut slice));
+            assert_eq!(std::slice::from_raw_parts(slice.data, slice.len), b"test");
+            assert!(!yt_value_int(name, &mut int));
+            assert!(yt_get_path(value, c"/missing".as_ptr()).is_null());
+
+            let buffer = yt_encode(value);
+            assert_eq!(std::slice::from_raw_parts(buffer.data, buffer.len), data);
+            yt_buffer_free(buffer);
+            yt_value_free(value);
+        }
+    }
+
+    #[test]
+    fn test_null() {
+        unsafe {
+            let mut int = 0;
+            assert_eq!(yt_value_type(ptr::null()), YtType::Int);
+            assert!(!yt_value_int(ptr::null(), &mut int));
+            assert!(!yt_value_bytes(ptr::null(), ptr::null_mut()));
+            assert_eq!(yt_value_len(ptr::null()), 0);
+            let buffer = yt_encode(ptr::null());
+            assert!(buffer.data.is_null());
+            assert_eq!(buffer.len, 0);
+            yt_buffer_free(buffer);
+
+            let value = yt_parse(b"i1e".as_ptr(), 3, ptr::null_mut());
+            assert!(!yt_value_int(value, ptr::null_mut()));
+            yt_value_free(value);
+        }
+        let mut error = new_error();
+        unsafe { yt_parse(ptr::null(), 0, &mut error) };
+        assert_eq!(error.code, YtErrorCode::InvalidArgument);
+        assert!(!error.has_offset);
+    }
+
+    /// The C declaration of each function in `source`, with its parameter names
+    fn c_declarations(source: &str) -> Vec<String> {
+        let c_type = |rust: &str| match rust.trim() {
+            "" => "void ".to_string(),
+            "*const u8" => "const uint8_t *".to_string(),
+            "*const c_char" => "const char *".to_string(),
+            "*mut i64" => "int64_t *".to_string(),
+            "usize" => "size_t ".to_string(),
+            "bool" => "bool ".to_string(),
+            rust => match rust.split_once(' ') {
+                Some(("*const", name)) => format!("const {} *", name),
+                Some(("*mut", name)) => format!("{} *", name),
+                _ => format!("{} ", rust),
+            },
+        };
+        source
+            .split("pub unsafe extern \"C\" fn ")
+            .skip(1)
+            .map(|item| {
+                let (name, rest) = item.split_once('(').unwrap();
+                let (params, rest) = rest.split_once(')').unwrap();
+                let ret = rest.split('{').next().unwrap().trim();
+                let params: Vec<_> = params
+                    .split(',')
+                    .filter(|param| !param.trim().is_empty())
+                    .map(|param| {
+                        let (name, ty) = param.split_once(':').unwrap();
+                        format!("{}{}", c_type(ty), name.trim())
+                    })
+                    .collect();
+                let ret = c_type(ret.trim_start_matches("->"));
+                format!("{}{}({});", ret, name, params.join(", "))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_header() {
+        let header = include_str!("../include/ytorrent.h");
+        let declarations = c_declarations(include_str!("ffi.rs"));
+        assert_eq!(declarations.len(), 9);
+        for declaration in &declarations {
+            assert!(header.contains(declaration.as_str()), "{}", declaration);
+        }
+        let header_functions = header
+            .lines()
+            .filter(|line| line.contains(" yt_") || line.contains("*yt_"));
+        assert_eq!(header_functions.count(), declarations.len());
+        for field in [
+            "YtErrorCode code;",
+            "bool has_offset;",
+            "size_t offset;",
+            "char message[256];",
+        ] {
+            assert!(header.contains(field), "{}", field);
+        }
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let data = b"d4:infoi-0ee";
+        let mut error = new_error();
+        unsafe {
+            assert!(yt_parse(data.as_ptr(), data.len(), &mut error).is_null());
+            assert!(yt_parse(ptr::null(), 0, ptr::null_mut()).is_null());
+        }
+        assert_eq!(error.code, YtErrorCode::Decode);
+        assert!(error.has_offset);
+        assert_eq!(error.offset, 9);
+        let message = unsafe { CStr::from_ptr(error.message.as_ptr()) };
+        assert!(!message.to_bytes().is_empty());
+    }
+}
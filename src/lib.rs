@@ -1,4 +1,4 @@
-//! This lib provides deserialize impl for bencode.
+//! This lib provides serialize and deserialize impl for bencode.
 //!
 //! Example:
 //!
@@ -24,26 +24,49 @@
 //! ```
 //!
 //! Also provides deserialize impl for torrent file.
+//! Tracker clients are behind the default "tracker" feature.
 //!
 //! Example:
 //!
 //! ```
-//! use ytorrent::{Client, MetaInfo};
+//! use ytorrent::{MetaInfo, Torrent};
 //!
-//! let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent");
-//! let meta: MetaInfo = client.torrent.meta_info;
+//! let torrent = Torrent::parse("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+//! let meta: MetaInfo = torrent.meta_info;
 //! assert_eq!(meta.announce, Some("http://bttracker.debian.org:6969/announce".into()));
 //! ```
 //!
+//! Without the default "std" feature, only the bencode parser, serializer and values are built,
+//! on `core` and `alloc`, e.g. for embedded targets.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
 pub use bencode::*;
 pub use common::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+#[cfg(feature = "std")]
 pub use meta::*;
+#[cfg(feature = "std")]
+pub use peer::*;
+#[cfg(feature = "tracker")]
 pub use tracker::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
 
 mod bencode;
 mod common;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "std")]
 mod meta;
+#[cfg(feature = "std")]
+mod peer;
+#[cfg(feature = "tracker")]
 mod tracker;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 #[cfg(test)]
 mod tests {}
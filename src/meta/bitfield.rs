@@ -0,0 +1,83 @@
+/// Set of piece indices, e.g. the pieces a peer has.
+///
+/// Uses the layout of the peer wire protocol `bitfield` message: the high bit of the first byte
+/// is piece 0, and spare bits at the end are cleared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bitfield {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitfield {
+    /// Construct an empty [Bitfield] for `len` pieces
+    pub fn new(len: usize) -> Self {
+        Self {
+            bytes: vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// Construct a [Bitfield] for `len` pieces from raw bytes, spare bits are ignored.
+    ///
+    /// Returns `None` if the number of bytes doesn't match `len`.
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Option<Self> {
+        if bytes.len() != len.div_ceil(8) {
+            return None;
+        }
+        let mut bitfield = Self {
+            bytes: bytes.to_vec(),
+            len,
+        };
+        if !len.is_multiple_of(8) {
+            if let Some(last) = bitfield.bytes.last_mut() {
+                *last &= 0xffu8 << (8 - len % 8);
+            }
+        }
+        Some(bitfield)
+    }
+
+    /// Number of pieces
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Whether piece `index` is set, `false` if out of range
+    pub fn has(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Mark piece `index`, out of range indices are ignored
+    pub fn set(&mut self, index: usize) {
+        if index < self.len {
+            self.bytes[index / 8] |= 0x80 >> (index % 8);
+        }
+    }
+
+    /// Unmark piece `index`, out of range indices are ignored
+    pub fn unset(&mut self, index: usize) {
+        if index < self.len {
+            self.bytes[index / 8] &= !(0x80 >> (index % 8));
+        }
+    }
+
+    /// Number of marked pieces
+    pub fn count(&self) -> usize {
+        self.bytes
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// Indices of marked pieces in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len).filter(|index| self.has(*index))
+    }
+}
@@ -0,0 +1,209 @@
+use super::*;
+
+/// A contiguous byte range of one file which is covered by a piece (or a byte range).
+#[derive(Debug, PartialEq, Clone)]
+pub struct FileSlice {
+    /// Index of the file in [FileLayout::files]
+    pub file_index: usize,
+    /// Offset of the slice from the beginning of the file
+    pub file_offset: u64,
+    /// Offset of the slice from the beginning of the piece (or the requested byte range)
+    pub piece_offset: u64,
+    /// Number of bytes of the slice
+    pub length: u64,
+}
+
+/// Maps pieces to the files they are written to.
+///
+/// All files of a torrent are treated as one logical byte stream, concatenated in the order
+/// they appear in the `info` dict, which is then split into pieces of `piece_length` bytes.
+#[derive(Debug, Clone)]
+pub struct FileLayout {
+    files: Vec<FileInfo>,
+    piece_length: u64,
+    total_size: u64,
+}
+
+impl FileLayout {
+    pub fn from_info(info: &Info) -> Self {
+        let files = match &info.mode {
+            FileMode::Single { length } => vec![FileInfo {
+                length: *length,
+                path: info.name.iter().cloned().collect(),
+            }],
+            FileMode::Multiple { files } => files.clone(),
+        };
+        let total_size = files.iter().map(|file| file.length).sum();
+        Self {
+            files,
+            piece_length: info.piece_length,
+            total_size,
+        }
+    }
+
+    pub fn files(&self) -> &[FileInfo] {
+        &self.files
+    }
+
+    pub fn piece_length(&self) -> u64 {
+        self.piece_length
+    }
+
+    pub fn total_size(&self) -> u64 {
+        self.total_size
+    }
+
+    /// File slices covered by the piece at `piece_index`, empty if the index is out of range.
+    pub fn for_piece(&self, piece_index: usize) -> Vec<FileSlice> {
+        let start = (piece_index as u64).saturating_mul(self.piece_length);
+        let end = start.saturating_add(self.piece_length);
+        self.for_byte_range(start, end)
+    }
+
+    /// File slices covered by the byte range `start..end` of the concatenated files.
+    ///
+    /// The range is clamped to the total size, and [FileSlice::piece_offset] is relative to
+    /// `start`. Zero-length files never produce a slice.
+    pub fn for_byte_range(&self, start: u64, end: u64) -> Vec<FileSlice> {
+        let end = end.min(self.total_size);
+        let mut slices = vec![];
+        if start >= end {
+            return slices;
+        }
+
+        let mut file_start = 0u64;
+        for (file_index, file) in self.files.iter().enumerate() {
+            let file_end = file_start + file.length;
+            if file.length > 0 && file_end > start && file_start < end {
+                let slice_start = start.max(file_start);
+                let slice_end = end.min(file_end);
+                slices.push(FileSlice {
+                    file_index,
+                    file_offset: slice_start - file_start,
+                    piece_offset: slice_start - start,
+                    length: slice_end - slice_start,
+                });
+            }
+            if file_end >= end {
+                break;
+            }
+            file_start = file_end;
+        }
+        slices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_layout() -> FileLayout {
+        // Files: a (5 bytes), b (7 bytes), c (4 bytes); pieces of 4 bytes:
+        // |aaaa|abbb|bbbb|cccc|
+        let info = Info {
+            mode: FileMode::Multiple {
+                files: vec![
+                    FileInfo {
+                        length: 5,
+                        path: vec!["a".into()],
+                    },
+                    FileInfo {
+                        length: 7,
+                        path: vec!["b".into()],
+                    },
+                    FileInfo {
+                        length: 4,
+                        path: vec!["c".into()],
+                    },
+                ],
+            },
+            name: Some("test".into()),
+            piece_length: 4,
+            pieces: PieceList(vec![]),
+            private: None,
+            meta_version: None,
+        };
+        FileLayout::from_info(&info)
+    }
+
+    #[test]
+    fn test_for_piece() {
+        let layout = build_layout();
+        assert_eq!(layout.total_size(), 16);
+        assert_eq!(
+            layout.for_piece(0),
+            vec![FileSlice {
+                file_index: 0,
+                file_offset: 0,
+                piece_offset: 0,
+                length: 4,
+            }]
+        );
+        assert_eq!(
+            layout.for_piece(1),
+            vec![
+                FileSlice {
+                    file_index: 0,
+                    file_offset: 4,
+                    piece_offset: 0,
+                    length: 1,
+                },
+                FileSlice {
+                    file_index: 1,
+                    file_offset: 0,
+                    piece_offset: 1,
+                    length: 3,
+                },
+            ]
+        );
+        assert_eq!(
+            layout.for_piece(2),
+            vec![FileSlice {
+                file_index: 1,
+                file_offset: 3,
+                piece_offset: 0,
+                length: 4,
+            }]
+        );
+        assert_eq!(
+            layout.for_piece(3),
+            vec![FileSlice {
+                file_index: 2,
+                file_offset: 0,
+                piece_offset: 0,
+                length: 4,
+            }]
+        );
+        assert!(layout.for_piece(4).is_empty());
+    }
+
+    #[test]
+    fn test_for_byte_range() {
+        let layout = build_layout();
+        assert_eq!(
+            layout.for_byte_range(3, 14),
+            vec![
+                FileSlice {
+                    file_index: 0,
+                    file_offset: 3,
+                    piece_offset: 0,
+                    length: 2,
+                },
+                FileSlice {
+                    file_index: 1,
+                    file_offset: 0,
+                    piece_offset: 2,
+                    length: 7,
+                },
+                FileSlice {
+                    file_index: 2,
+                    file_offset: 0,
+                    piece_offset: 9,
+                    length: 2,
+                },
+            ]
+        );
+        assert_eq!(layout.for_byte_range(14, 100).len(), 1);
+        assert!(layout.for_byte_range(8, 8).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Magnet links, see [BEP-0009](https://www.bittorrent.org/beps/bep_0009.html#magnet-uri-format)
+//!
+//! Example:
+//! ```
+//! use ytorrent::MagnetLink;
+//!
+//! let link = MagnetLink::parse(
+//!     "magnet:?xt=urn:btih:a9993e364706816aba3e25717850c26c9cd0d89d&dn=demo%20file\
+//!      &tr=http%3A%2F%2Ftracker.example.com%2Fannounce",
+//! )
+//! .unwrap();
+//! assert_eq!(link.display_name.as_deref(), Some("demo file"));
+//! assert_eq!(link.trackers, vec!["http://tracker.example.com/announce"]);
+//! ```
+use url::form_urlencoded;
+
+use super::*;
+
+const BTIH_PREFIX: &str = "urn:btih:";
+/// The multihash of a v2 info hash: 0x12 for SHA-256, 0x20 for its 32 bytes
+const BTMH_PREFIX: &str = "urn:btmh:1220";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MagnetLink {
+    /// From the `btih` hash, the `btmh` hash of v2 torrents, or both for a hybrid torrent
+    pub info_hash: TorrentInfoHash,
+    /// The `dn` parameter, a name to display while the metadata is unknown
+    pub display_name: Option<String>,
+    /// The `tr` parameters
+    pub trackers: Vec<String>,
+    /// The `x.pe` peer addresses
+    pub nodes: Vec<Node>,
+}
+
+impl MagnetLink {
+    pub fn parse(uri: &str) -> Result<MagnetLink> {
+        let query = uri
+            .strip_prefix("magnet:?")
+            .ok_or_else(|| Error::InvalidArgument(format!("{} is not a magnet link", uri)))?;
+        let mut v1 = None;
+        let mut v2 = None;
+        let mut display_name = None;
+        let mut trackers = vec![];
+        let mut nodes = vec![];
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "xt" => {
+                    if let Some(hash) = value.strip_prefix(BTIH_PREFIX) {
+                        v1 = Some(parse_btih(hash)?);
+                    } else if let Some(hash) = value.strip_prefix(BTMH_PREFIX) {
+                        v2 = Some(Sha256Digest::from_hex(hash)?);
+                    }
+                }
+                "dn" => display_name = Some(value.into_owned()),
+                "tr" => trackers.push(value.into_owned()),
+                "x.pe" => nodes.push(parse_peer(&value)?),
+                _ => {}
+            }
+        }
+        let info_hash = match (v1, v2) {
+            (Some(v1), Some(v2)) => TorrentInfoHash::Hybrid { v1, v2 },
+            (Some(v1), None) => TorrentInfoHash::V1(v1),
+            (None, Some(v2)) => TorrentInfoHash::V2(v2),
+            (None, None) => {
+                return Err(Error::InvalidArgument(format!(
+                    "no info hash in magnet link {}",
+                    uri
+                )))
+            }
+        };
+        Ok(MagnetLink {
+            info_hash,
+            display_name,
+            trackers,
+            nodes,
+        })
+    }
+}
+
+/// 40 hex characters, or 32 Base32 characters
+fn parse_btih(hash: &str) -> Result<Sha1Digest> {
+    if hash.len() == 32 {
+        return decode_base32(hash)
+            .map(Sha1Digest)
+            .ok_or_else(|| Error::InvalidArgument(format!("invalid base32 info hash {}", hash)));
+    }
+    Sha1Digest::from_hex(hash)
+}
+
+/// Decode the RFC 4648 alphabet, without padding, since 32 characters are exactly 20 bytes
+fn decode_base32(str: &str) -> Option<[u8; Sha1Digest::LENGTH]> {
+    let mut bytes = [0u8; Sha1Digest::LENGTH];
+    let mut buffer = 0u64;
+    let mut bits = 0;
+    let mut index = 0;
+    for c in str.bytes() {
+        let value = match c.to_ascii_uppercase() {
+            c @ b'A'..=b'Z' => c - b'A',
+            c @ b'2'..=b'7' => c - b'2' + 26,
+            _ => return None,
+        };
+        buffer = buffer << 5 | value as u64;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes[index] = (buffer >> bits) as u8;
+            index += 1;
+        }
+    }
+    Some(bytes)
+}
+
+/// `HOST:PORT`, where an IPv6 host is enclosed in brackets
+fn parse_peer(peer: &str) -> Result<Node> {
+    let invalid = || Error::InvalidArgument(format!("invalid peer address {}", peer));
+    let (host, port) = peer.rsplit_once(':').ok_or_else(invalid)?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    let port = port.parse().map_err(|_| invalid())?;
+    if host.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Node {
+        host: host.to_string(),
+        port,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX_HASH: &str = "a9993e364706816aba3e25717850c26c9cd0d89d";
+    const BASE32_HASH: &str = "VGMT4NSHA2AWVOR6EVYXQUGCNSONBWE5";
+
+    #[test]
+    fn test_parse_magnet() {
+        let uri = format!(
+            "magnet:?xt=urn:btih:{}&dn=debian+12%2E5&tr=http%3A%2F%2Fa%2Fannounce\
+             &tr=udp%3A%2F%2Fb%3A1337&x.pe=10.0.0.1:6881&x.pe=%5B%3A%3A1%5D:51413",
+            HEX_HASH
+        );
+        let link = MagnetLink::parse(&uri).unwrap();
+        assert_eq!(
+            link.info_hash,
+            TorrentInfoHash::V1(HEX_HASH.parse().unwrap())
+        );
+        assert_eq!(link.display_name.as_deref(), Some("debian 12.5"));
+        assert_eq!(link.trackers, vec!["http://a/announce", "udp://b:1337"]);
+        assert_eq!(
+            link.nodes,
+            vec![
+                Node {
+                    host: "10.0.0.1".into(),
+                    port: 6881
+                },
+                Node {
+                    host: "::1".into(),
+                    port: 51413
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_base32_hash() {
+        let uri = format!("magnet:?xt=urn:btih:{}", BASE32_HASH);
+        let link = MagnetLink::parse(&uri).unwrap();
+        assert_eq!(
+            link.info_hash,
+            TorrentInfoHash::V1(HEX_HASH.parse().unwrap())
+        );
+        assert_eq!(link.display_name, None);
+        assert!(link.trackers.is_empty());
+
+        let uri = format!("magnet:?xt=urn:btih:{}", BASE32_HASH.to_lowercase());
+        assert!(MagnetLink::parse(&uri).is_ok());
+    }
+
+    #[test]
+    fn test_parse_v2_hash() {
+        let v2 = "cb8c0e7c1d0e4a7a4b1e1b9d3f0b5d1a6e5c2f9b8a7d6c5b4a3f2e1d0c9b8a7d";
+        let uri = format!("magnet:?xt=urn:btmh:1220{}", v2);
+        let link = MagnetLink::parse(&uri).unwrap();
+        assert_eq!(link.info_hash, TorrentInfoHash::V2(v2.parse().unwrap()));
+
+        let uri = format!("magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}", HEX_HASH, v2);
+        let link = MagnetLink::parse(&uri).unwrap();
+        assert_eq!(link.info_hash.to_string(), format!("{},{}", HEX_HASH, v2));
+    }
+
+    #[test]
+    fn test_parse_invalid_magnet() {
+        for uri in [
+            format!("http://example.com/?xt=urn:btih:{}", HEX_HASH),
+            "magnet:?dn=name".to_string(),
+            "magnet:?xt=urn:btih:1234".to_string(),
+            format!("magnet:?xt=urn:btih:{}", BASE32_HASH.replace('V', "1")),
+            format!("magnet:?xt=urn:btih:{}&x.pe=host", HEX_HASH),
+            format!("magnet:?xt=urn:btih:{}&x.pe=:6881", HEX_HASH),
+            format!("magnet:?xt=urn:btmh:1220{}", HEX_HASH),
+        ] {
+            assert!(
+                matches!(MagnetLink::parse(&uri), Err(Error::InvalidArgument(_))),
+                "{}",
+                uri
+            );
+        }
+    }
+}
@@ -1,12 +1,17 @@
-use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::de::{Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::rust::unwrap_or_skip;
-use serde_with::SerializeAs;
+use serde_with::{DeserializeAs, SerializeAs};
 
 use super::*;
 
 pub type AnnounceList = Vec<Vec<String>>;
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MetaInfo {
     /// The URL of the tracker.
     #[serde(
@@ -53,16 +58,33 @@ pub struct MetaInfo {
         with = "unwrap_or_skip"
     )]
     pub nodes: Option<Vec<Node>>,
+    /// [BEP-0019](https://www.bittorrent.org/beps/bep_0019.html) web seeds
     #[serde(
         rename = "url-list",
         skip_serializing_if = "Option::is_none",
         default,
         with = "unwrap_or_skip"
     )]
-    pub url_list: Option<Vec<String>>,
+    pub url_list: Option<UrlList>,
+}
+
+impl MetaInfo {
+    /// SHA-1 of the concatenated piece hashes, identifying the content of the torrent.
+    ///
+    /// This is **not** the info hash used by trackers and peers: it ignores every other key of
+    /// the `info` dict (e.g. `private` or `source`), so torrents re-created for different
+    /// trackers from the same data have the same content hash. Useful for cross-seeding.
+    pub fn content_hash(&self) -> Sha1Digest {
+        Sha1Digest::digest(self.info.pieces.to_raw_bytes())
+    }
+
+    /// The `meta version` of the `info` dict, `None` for v1 torrents
+    pub fn meta_version(&self) -> Option<u32> {
+        self.info.meta_version
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Info {
     /// Single or Multiple files
     #[serde(flatten)]
@@ -95,33 +117,234 @@ pub struct Info {
         with = "unwrap_or_skip"
     )]
     pub private: Option<bool>,
+    /// `2` for [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) v2 and hybrid torrents
+    #[serde(
+        rename = "meta version",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub meta_version: Option<u32>,
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
-#[serde(untagged)]
+impl Info {
+    /// Size of all files in bytes
+    pub fn total_length(&self) -> u64 {
+        self.iter_files().map(|(length, _)| length).sum()
+    }
+
+    pub fn file_count(&self) -> usize {
+        match &self.mode {
+            FileMode::Single { .. } => 1,
+            FileMode::Multiple { files } => files.len(),
+        }
+    }
+
+    /// `(length, path)` of each file, the path of a single-file torrent is empty since the file
+    /// is saved as [Info::name]
+    pub fn iter_files(&self) -> impl Iterator<Item = (u64, &[String])> {
+        let (single, files) = match &self.mode {
+            FileMode::Single { length } => (Some(*length), &[][..]),
+            FileMode::Multiple { files } => (None, files.as_slice()),
+        };
+        single
+            .map(|length| (length, &[][..]))
+            .into_iter()
+            .chain(files.iter().map(|file| (file.length, file.path.as_slice())))
+    }
+
+    pub fn piece_count(&self) -> usize {
+        self.pieces.piece_count()
+    }
+
+    /// The pieces overlapping each file, in the order of [Info::iter_files].
+    ///
+    /// A zero-length file is given the piece its offset falls in, even though no byte of that
+    /// piece belongs to it.
+    pub fn file_piece_ranges(&self) -> Vec<FilePieceRange<'_>> {
+        let files: Vec<Cow<FileInfo>> = match &self.mode {
+            // Named like in [FileLayout::from_info]
+            FileMode::Single { length } => vec![Cow::Owned(FileInfo {
+                length: *length,
+                path: self.name.iter().cloned().collect(),
+            })],
+            FileMode::Multiple { files } => files.iter().map(Cow::Borrowed).collect(),
+        };
+        let piece_length = self.piece_length.max(1);
+        let mut byte_offset = 0u64;
+        let mut ranges = Vec::with_capacity(files.len());
+        for file in files {
+            let length = file.length;
+            ranges.push(FilePieceRange {
+                byte_offset,
+                start_piece: (byte_offset / piece_length) as usize,
+                end_piece_inclusive: ((byte_offset + length.saturating_sub(1)) / piece_length)
+                    as usize,
+                file,
+            });
+            byte_offset += length;
+        }
+        ranges
+    }
+}
+
+/// See [Info::file_piece_ranges]
+#[derive(Debug, PartialEq, Clone)]
+pub struct FilePieceRange<'a> {
+    /// Borrowed from [FileMode::Multiple], or made up for a single-file torrent
+    pub file: Cow<'a, FileInfo>,
+    /// Offset of the file in the concatenated files
+    pub byte_offset: u64,
+    pub start_piece: usize,
+    pub end_piece_inclusive: usize,
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum FileMode {
     Single { length: u64 },
     Multiple { files: Vec<FileInfo> },
 }
 
-#[derive(Debug, PartialEq)]
+impl Serialize for FileMode {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Written as entries of the surrounding `info` dict, since it's flattened
+        let mut map = serializer.serialize_map(Some(1))?;
+        match self {
+            FileMode::Single { length } => map.serialize_entry("length", length)?,
+            FileMode::Multiple { files } => map.serialize_entry("files", files)?,
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for FileMode {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FileModeVisitor;
+
+        impl<'de> Visitor<'de> for FileModeVisitor {
+            type Value = FileMode;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("'length' for single-file or 'files' for multi-file torrent")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> std::result::Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut mode = None;
+                let mut other_keys = vec![];
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "length" if mode.is_none() => {
+                            mode = Some(FileMode::Single {
+                                length: map.next_value()?,
+                            })
+                        }
+                        "files" if mode.is_none() => {
+                            mode = Some(FileMode::Multiple {
+                                files: map.next_value()?,
+                            })
+                        }
+                        "length" | "files" => {
+                            return Err(A::Error::custom(
+                                "both 'length' and 'files' found in torrent info",
+                            ))
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                            other_keys.push(key);
+                        }
+                    }
+                }
+                mode.ok_or_else(|| {
+                    A::Error::custom(format!(
+                        "expected 'length' for single-file or 'files' for multi-file torrent, \
+                         got {:?}",
+                        other_keys
+                    ))
+                })
+            }
+        }
+
+        deserializer.deserialize_map(FileModeVisitor)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct PieceList(
     /// SHA-1 digest
     pub Vec<Sha1Digest>,
 );
 
-impl Serialize for PieceList {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        let mut bytes = Vec::with_capacity(self.0.len() * Sha1Digest::LENGTH);
+impl PieceList {
+    pub fn piece_count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Expected SHA-1 digest of the piece at `index`
+    pub fn get(&self, index: usize) -> Option<&Sha1Digest> {
+        self.0.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sha1Digest> {
+        self.0.iter()
+    }
+
+    /// Check downloaded `data` of the piece at `index` against its expected digest
+    pub fn verify_piece(&self, index: usize, data: &[u8]) -> Result<bool> {
+        let expected = self.get(index).ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "piece index {} out of range, {} pieces",
+                index,
+                self.piece_count()
+            ))
+        })?;
+        Ok(Sha1Digest::digest(data) == *expected)
+    }
 
+    /// The concatenated piece hashes, as stored in the `pieces` key
+    pub fn to_raw_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.0.len() * Sha1Digest::LENGTH);
         for piece in self.0.as_slice() {
             bytes.extend_from_slice(piece);
         }
+        bytes
+    }
+
+    /// Copy the hashes of the pieces marked in `which_pieces` from `other`, other hashes are
+    /// kept.
+    ///
+    /// Fails without modifying anything if `other` or `which_pieces` doesn't cover the same
+    /// number of pieces.
+    pub fn merge_from(&mut self, other: &PieceList, which_pieces: &Bitfield) -> Result<()> {
+        if other.0.len() != self.0.len() || which_pieces.len() != self.0.len() {
+            return Err(Error::InvalidArgument(format!(
+                "can't merge {} pieces with bitfield of {} into {} pieces",
+                other.0.len(),
+                which_pieces.len(),
+                self.0.len()
+            )));
+        }
+        for index in which_pieces.iter() {
+            self.0[index] = other.0[index].clone();
+        }
+        Ok(())
+    }
+}
 
-        serde_with::Bytes::serialize_as(&bytes, serializer)
+impl Serialize for PieceList {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_with::Bytes::serialize_as(&self.to_raw_bytes(), serializer)
     }
 }
 
@@ -130,8 +353,9 @@ impl<'de> Deserialize<'de> for PieceList {
     where
         D: Deserializer<'de>,
     {
-        let bytes = <&[u8]>::deserialize(deserializer)?;
-        if bytes.len() % Sha1Digest::LENGTH != 0 {
+        // Borrowed from the data when it's in memory, copied when it's read from a stream
+        let bytes: Cow<'de, [u8]> = serde_with::Bytes::deserialize_as(deserializer)?;
+        if !bytes.len().is_multiple_of(Sha1Digest::LENGTH) {
             return Err(D::Error::custom(format!(
                 "buffer length {} is not a multiple of {}",
                 bytes.len(),
@@ -148,7 +372,7 @@ impl<'de> Deserialize<'de> for PieceList {
     }
 }
 
-#[derive(Deserialize, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub struct FileInfo {
     pub length: u64,
     pub path: Vec<String>,
@@ -176,6 +400,76 @@ impl<'de> Deserialize<'de> for Node {
     }
 }
 
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Encoded as a two-element list: "l<host>i<port>ee"
+        (&self.host, self.port).serialize(serializer)
+    }
+}
+
+/// Web seed URLs, encoded as a single string when there is only one
+#[derive(Debug, PartialEq, Clone)]
+pub struct UrlList(pub Vec<String>);
+
+impl Serialize for UrlList {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UrlList {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct UrlListVisitor;
+
+        impl<'de> Visitor<'de> for UrlListVisitor {
+            type Value = UrlList;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a URL or a list of URLs")
+            }
+
+            fn visit_str<E>(self, url: &str) -> std::result::Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(UrlList(vec![url.to_string()]))
+            }
+
+            fn visit_bytes<E>(self, bytes: &[u8]) -> std::result::Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                let url = std::str::from_utf8(bytes)
+                    .map_err(|e| E::custom(format!("invalid url-list: {}", e)))?;
+                self.visit_str(url)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut urls = vec![];
+                while let Some(url) = seq.next_element()? {
+                    urls.push(url);
+                }
+                Ok(UrlList(urls))
+            }
+        }
+
+        // A string or a list, told apart by the next token
+        deserializer.deserialize_any(UrlListVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::fs::File;
@@ -226,35 +520,65 @@ mod tests {
     );
 
     trait ToBencode {
-        fn to_bencode(&self) -> serde_bencode::Result<Vec<u8>>;
+        fn to_bencode(&self) -> crate::Result<Vec<u8>>;
     }
 
     impl ToBencode for bool {
-        fn to_bencode(&self) -> serde_bencode::Result<Vec<u8>> {
-            serde_bencode::to_bytes(self)
+        fn to_bencode(&self) -> crate::Result<Vec<u8>> {
+            crate::to_bytes(self)
         }
     }
 
     impl ToBencode for &str {
-        fn to_bencode(&self) -> serde_bencode::Result<Vec<u8>> {
-            serde_bencode::to_bytes(self)
+        fn to_bencode(&self) -> crate::Result<Vec<u8>> {
+            crate::to_bytes(self)
         }
     }
 
     impl ToBencode for i32 {
-        fn to_bencode(&self) -> serde_bencode::Result<Vec<u8>> {
-            serde_bencode::to_bytes(self)
+        fn to_bencode(&self) -> crate::Result<Vec<u8>> {
+            crate::to_bytes(self)
         }
     }
 
     #[test]
     fn test_se_de_piece_list() {
         let piece_list = PieceList([Sha1Digest::new(SAMPLE_SHA1_DIGEST)].into());
-        let bytes = serde_bencode::to_bytes(&piece_list).unwrap();
+        let bytes = crate::to_bytes(&piece_list).unwrap();
         let piece_list: PieceList = de::from_bytes(&bytes).unwrap();
         assert_eq!(piece_list.0.first().unwrap().as_ref(), SAMPLE_SHA1_DIGEST);
     }
 
+    #[test]
+    fn test_se_de_nodes() {
+        let nodes = vec![
+            Node::new("127.0.0.1".into(), 6881),
+            Node::new("your.router.node".into(), 4804),
+            Node::new("2001:db8:100:0:d5c8:db3f:995e:c0f7".into(), 1941),
+        ];
+        let bytes = crate::to_bytes(&nodes).unwrap();
+        assert_eq!(bytes, SAMPLE_RAW_NODES.as_bytes());
+        let ret: Vec<Node> = de::from_bytes(&bytes).unwrap();
+        assert_eq!(ret, nodes);
+    }
+
+    #[test]
+    fn test_merge_piece_list() {
+        let digest = |byte: u8| Sha1Digest::new([byte; Sha1Digest::LENGTH]);
+        let mut pieces = PieceList(vec![digest(0), digest(1), digest(2)]);
+        let other = PieceList(vec![digest(10), digest(11), digest(12)]);
+        let mut which_pieces = Bitfield::new(3);
+        which_pieces.set(0);
+        which_pieces.set(2);
+        pieces.merge_from(&other, &which_pieces).unwrap();
+        assert_eq!(pieces, PieceList(vec![digest(10), digest(1), digest(12)]));
+
+        let short = PieceList(vec![digest(10)]);
+        assert!(pieces.merge_from(&short, &which_pieces).is_err());
+        assert!(pieces.merge_from(&other, &Bitfield::new(2)).is_err());
+        assert_eq!(pieces, PieceList(vec![digest(10), digest(1), digest(12)]));
+    }
+
     fn build_info_data() -> Vec<u8> {
         let mut info: Vec<u8> = vec![];
         info.push(b'd');
@@ -266,7 +590,7 @@ mod tests {
         info.extend(4096.to_bencode().unwrap());
         info.extend(TAG_PIECES.to_bencode().unwrap());
         let piece_list = PieceList([Sha1Digest::new(SAMPLE_SHA1_DIGEST.to_owned())].into());
-        info.extend(serde_bencode::to_bytes(&piece_list).unwrap());
+        info.extend(crate::to_bytes(&piece_list).unwrap());
         info.extend(TAG_PRIVATE.to_bencode().unwrap());
         info.extend(false.to_bencode().unwrap());
         info.push(b'e');
@@ -287,6 +611,64 @@ mod tests {
         assert_eq!(ret.private, Some(false));
     }
 
+    #[test]
+    fn test_se_file_mode() {
+        let single = FileMode::Single { length: 1024 };
+        assert_eq!(crate::to_bytes(&single).unwrap(), b"d6:lengthi1024ee");
+
+        let multiple = FileMode::Multiple {
+            files: vec![FileInfo {
+                length: 1,
+                path: vec!["a".into()],
+            }],
+        };
+        assert_eq!(
+            crate::to_bytes(&multiple).unwrap(),
+            b"d5:filesld6:lengthi1e4:pathl1:aeeee"
+        );
+    }
+
+    #[test]
+    fn test_se_info() {
+        let info = Info {
+            mode: FileMode::Single { length: 1024 },
+            name: Some(SAMPLE_NAME.into()),
+            piece_length: 512,
+            pieces: PieceList(vec![Sha1Digest(SAMPLE_SHA1_DIGEST)]),
+            private: Some(true),
+            meta_version: None,
+        };
+        let mut expected =
+            b"d6:lengthi1024e4:name9:test-name12:piece lengthi512e6:pieces20:".to_vec();
+        expected.extend_from_slice(&SAMPLE_SHA1_DIGEST);
+        expected.extend_from_slice(b"7:privatei1ee");
+        let bytes = crate::to_bytes(&info).unwrap();
+        assert_eq!(bytes, expected);
+
+        let ret: Info = de::from_bytes(&bytes).unwrap();
+        assert_eq!(ret.mode, info.mode);
+        assert_eq!(ret.private, Some(true));
+    }
+
+    #[test]
+    fn test_de_file_mode() {
+        let info = b"d5:filesld6:lengthi1e4:pathl1:aeee4:name4:test12:piece lengthi4e6:pieces0:e";
+        let ret: Info = de::from_bytes(info).unwrap();
+        assert_eq!(
+            ret.mode,
+            FileMode::Multiple {
+                files: vec![FileInfo {
+                    length: 1,
+                    path: vec!["a".into()]
+                }]
+            }
+        );
+
+        let info = b"d4:name4:test12:piece lengthi4e6:pieces0:e";
+        let err = de::from_bytes::<Info>(info).unwrap_err().to_string();
+        assert!(err.contains("expected 'length' for single-file or 'files' for multi-file"));
+    }
+
     #[test]
     fn test_meta_announce() {
         let mut meta: Vec<u8> = vec![];
@@ -334,6 +716,29 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_content_hash() {
+        let build_meta = |info: &[u8]| {
+            let mut meta: Vec<u8> = vec![];
+            meta.push(b'd');
+            meta.extend(TAG_INFO.to_bencode().unwrap());
+            meta.extend(info);
+            meta.push(b'e');
+            de::from_bytes::<MetaInfo>(&meta).unwrap()
+        };
+        let public = build_meta(&build_info_data());
+        let private_info = String::from_utf8(build_info_data())
+            .unwrap()
+            .replace("7:privatei0e", "7:privatei1e");
+        let private = build_meta(private_info.as_bytes());
+        assert_eq!(private.info.private, Some(true));
+        assert_eq!(public.content_hash(), private.content_hash());
+        assert_eq!(
+            public.content_hash(),
+            Sha1Digest::digest(SAMPLE_SHA1_DIGEST)
+        );
+    }
+
     #[test]
     fn test_decode_debian_torrent() {
         let mut file = File::open("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
@@ -354,4 +759,149 @@ mod tests {
         assert_eq!(meta.info.piece_length, 262144);
         assert_eq!(meta.info.pieces.0.len(), 50320 / 20);
     }
+
+    #[test]
+    fn test_verify_piece() {
+        // SHA-1 of "abc"
+        let abc = Sha1Digest([
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+            0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ]);
+        let pieces = PieceList(vec![Sha1Digest(SAMPLE_SHA1_DIGEST), abc.clone()]);
+        assert_eq!(pieces.piece_count(), 2);
+        assert_eq!(pieces.get(1), Some(&abc));
+        assert_eq!(pieces.get(2), None);
+        assert_eq!(pieces.iter().count(), 2);
+
+        assert!(pieces.verify_piece(1, b"abc").unwrap());
+        assert!(!pieces.verify_piece(1, b"abd").unwrap());
+        assert!(!pieces.verify_piece(0, b"abc").unwrap());
+        assert!(matches!(
+            pieces.verify_piece(2, b"abc"),
+            Err(Error::InvalidArgument(_))
+        ));
+    }
+
+    fn sample_info(mode: FileMode) -> Info {
+        Info {
+            mode,
+            name: Some(SAMPLE_NAME.into()),
+            piece_length: 512,
+            pieces: PieceList(vec![Sha1Digest(SAMPLE_SHA1_DIGEST); 3]),
+            private: None,
+            meta_version: None,
+        }
+    }
+
+    #[test]
+    fn test_info_single_file() {
+        let info = sample_info(FileMode::Single { length: 1024 });
+        assert_eq!(info.total_length(), 1024);
+        assert_eq!(info.file_count(), 1);
+        assert_eq!(info.piece_count(), 3);
+        let files: Vec<_> = info.iter_files().collect();
+        assert_eq!(files, vec![(1024, &[][..])]);
+    }
+
+    #[test]
+    fn test_info_multiple_files() {
+        let file = |length: u64, name: &str| FileInfo {
+            length,
+            path: vec!["dir".into(), name.into()],
+        };
+        let info = sample_info(FileMode::Multiple {
+            files: vec![
+                file(0, "empty"),
+                file(u32::MAX as u64, "a"),
+                file(2, "b"),
+                file(0, "empty2"),
+            ],
+        });
+        assert_eq!(info.total_length(), u32::MAX as u64 + 2);
+        assert_eq!(info.file_count(), 4);
+        let files: Vec<_> = info.iter_files().collect();
+        assert_eq!(files.len(), 4);
+        assert_eq!(files[0].0, 0);
+        assert_eq!(
+            files[1],
+            (u32::MAX as u64, &["dir".to_string(), "a".to_string()][..])
+        );
+
+        let info = sample_info(FileMode::Multiple { files: vec![] });
+        assert_eq!(info.total_length(), 0);
+        assert_eq!(info.file_count(), 0);
+        assert_eq!(info.iter_files().count(), 0);
+    }
+
+    #[test]
+    fn test_file_piece_ranges_single() {
+        let info = sample_info(FileMode::Single { length: 1100 });
+        let ranges = info.file_piece_ranges();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].file.length, 1100);
+        assert_eq!(ranges[0].file.path, vec![SAMPLE_NAME.to_string()]);
+        assert_eq!(ranges[0].byte_offset, 0);
+        assert_eq!(ranges[0].start_piece, 0);
+        // The last piece is 76 bytes
+        assert_eq!(ranges[0].end_piece_inclusive, 2);
+    }
+
+    #[test]
+    fn test_file_piece_ranges_multiple() {
+        let file = |length: u64| FileInfo {
+            length,
+            path: vec![length.to_string()],
+        };
+        let info = sample_info(FileMode::Multiple {
+            files: vec![file(0), file(512), file(0), file(100), file(500), file(1)],
+        });
+        let ranges: Vec<_> = info
+            .file_piece_ranges()
+            .into_iter()
+            .map(|range| {
+                assert!(matches!(range.file, Cow::Borrowed(_)));
+                (
+                    range.byte_offset,
+                    range.start_piece,
+                    range.end_piece_inclusive,
+                )
+            })
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![
+                // Zero-length files
+                (0, 0, 0),
+                // Ends exactly at a piece boundary
+                (0, 0, 0),
+                (512, 1, 1),
+                // Starts exactly at a piece boundary
+                (512, 1, 1),
+                // Crosses a piece boundary
+                (612, 1, 2),
+                // Alone in the short last piece
+                (1112, 2, 2),
+            ]
+        );
+
+        let info = sample_info(FileMode::Multiple { files: vec![] });
+        assert!(info.file_piece_ranges().is_empty());
+    }
+
+    #[test]
+    fn test_de_url_list() {
+        let single: UrlList = de::from_bytes(b"17:http://a/file.iso").unwrap();
+        assert_eq!(single, UrlList(vec!["http://a/file.iso".into()]));
+        assert_eq!(crate::to_bytes(&single).unwrap(), b"l17:http://a/file.isoe");
+
+        let list: UrlList = de::from_bytes(b"l8:http://a8:http://be").unwrap();
+        assert_eq!(list, UrlList(vec!["http://a".into(), "http://b".into()]));
+        assert!(de::from_bytes::<UrlList>(b"i1e").is_err());
+
+        let mut meta = b"d4:info".to_vec();
+        meta.extend(build_info_data());
+        meta.extend_from_slice(b"8:url-list8:http://ae");
+        let meta: MetaInfo = de::from_bytes(&meta).unwrap();
+        assert_eq!(meta.url_list, Some(UrlList(vec!["http://a".into()])));
+    }
 }
@@ -1,10 +1,18 @@
+pub use bitfield::*;
+pub use file_layout::*;
+pub use magnet::*;
 pub use meta_info::*;
 pub use sha1_digest::*;
+pub use sha256_digest::*;
 pub use torrent::*;
 
 use super::bencode::*;
 use super::common::*;
 
+mod bitfield;
+mod file_layout;
+mod magnet;
 mod meta_info;
 mod sha1_digest;
+mod sha256_digest;
 mod torrent;
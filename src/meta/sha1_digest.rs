@@ -2,12 +2,15 @@ use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::hash::Hash;
 use std::ops::Deref;
+use std::str::FromStr;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use serde_with::DeserializeAs;
+use serde_with::{DeserializeAs, SerializeAs};
 use sha1_smol::Sha1;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+use crate::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Sha1Digest(pub [u8; Self::LENGTH]);
 
 impl Sha1Digest {
@@ -20,6 +23,50 @@ impl Sha1Digest {
     pub(super) fn digest(data: impl AsRef<[u8]>) -> Self {
         Sha1::from(data).digest().into()
     }
+
+    /// Parse 40 hex characters, in lower or upper case
+    pub fn from_hex(s: &str) -> crate::Result<Self> {
+        decode_hex(s).map(Self)
+    }
+}
+
+/// Parse `N * 2` hex characters, in lower or upper case
+pub(super) fn decode_hex<const N: usize>(s: &str) -> crate::Result<[u8; N]> {
+    if s.len() != N * 2 {
+        return Err(Error::InvalidArgument(format!(
+            "expect {} hex characters but get {}",
+            N * 2,
+            s.len()
+        )));
+    }
+    let mut bytes = [0u8; N];
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        let position = index * 2;
+        let hex = &s.as_bytes()[position..position + 2];
+        // Not `u8::from_str_radix`, which accepts a leading '+'
+        *byte = match (hex_value(hex[0]), hex_value(hex[1])) {
+            (Some(high), Some(low)) => high << 4 | low,
+            _ => {
+                return Err(Error::InvalidArgument(format!(
+                    "invalid hex character in {} at {}",
+                    s, position
+                )))
+            }
+        };
+    }
+    Ok(bytes)
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|value| value as u8)
+}
+
+impl FromStr for Sha1Digest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_hex(s)
+    }
 }
 
 impl From<sha1_smol::Digest> for Sha1Digest {
@@ -60,6 +107,47 @@ impl Serialize for Sha1Digest {
     where
         S: Serializer,
     {
-        self.0.serialize(serializer)
+        serde_with::Bytes::serialize_as(&self.0, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_hex() {
+        let expected = Sha1Digest([
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e, 0x25, 0x71, 0x78, 0x50,
+            0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ]);
+        let lower = "a9993e364706816aba3e25717850c26c9cd0d89d";
+        assert_eq!(Sha1Digest::from_hex(lower).unwrap(), expected);
+        assert_eq!(
+            Sha1Digest::from_hex(&lower.to_uppercase()).unwrap(),
+            expected
+        );
+        assert_eq!(lower.parse::<Sha1Digest>().unwrap(), expected);
+        assert_eq!(
+            Sha1Digest::from_hex(&expected.to_string()).unwrap(),
+            expected
+        );
+
+        for invalid in [
+            &lower[..39],
+            &format!("{}0", lower),
+            &format!("{}g", &lower[..39]),
+            "+a993e364706816aba3e25717850c26c9cd0d89d",
+            "é993e364706816aba3e25717850c26c9cd0d89d",
+        ] {
+            assert!(
+                matches!(
+                    Sha1Digest::from_hex(invalid),
+                    Err(Error::InvalidArgument(_))
+                ),
+                "{}",
+                invalid
+            );
+        }
     }
 }
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::hash::Hash;
+use std::ops::Deref;
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{DeserializeAs, SerializeAs};
+use sha2::{Digest, Sha256};
+
+use super::decode_hex;
+use crate::Error;
+
+/// SHA-256 digest of [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) v2 torrents
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sha256Digest(pub [u8; Self::LENGTH]);
+
+impl Sha256Digest {
+    pub const LENGTH: usize = 32;
+
+    pub(super) fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub(super) fn digest(data: impl AsRef<[u8]>) -> Self {
+        Self(Sha256::digest(data).into())
+    }
+
+    /// Parse 64 hex characters, in lower or upper case
+    pub fn from_hex(s: &str) -> crate::Result<Self> {
+        decode_hex(s).map(Self)
+    }
+}
+
+impl FromStr for Sha256Digest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        Self::from_hex(s)
+    }
+}
+
+impl Deref for Sha256Digest {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for Sha256Digest {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for Sha256Digest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = serde_with::Bytes::deserialize_as(deserializer)?;
+        Ok(Sha256Digest::new(bytes))
+    }
+}
+
+impl Serialize for Sha256Digest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serde_with::Bytes::serialize_as(&self.0, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::de;
+
+    #[test]
+    fn test_sha256_digest() {
+        let digest = Sha256Digest::digest(b"abc");
+        assert_eq!(
+            digest.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(digest.len(), 32);
+
+        let mut data = b"32:".to_vec();
+        data.extend_from_slice(&digest);
+        assert_eq!(de::from_bytes::<Sha256Digest>(&data).unwrap(), digest);
+        assert_eq!(crate::to_bytes(&digest).unwrap(), data);
+        assert!(de::from_bytes::<Sha256Digest>(b"3:abc").is_err());
+    }
+}
@@ -1,47 +1,387 @@
+use std::fmt::{Display, Formatter};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
+use std::str::FromStr;
+
+use serde::de::IgnoredAny;
+use serde::Deserialize;
 
 use super::*;
 
 /// Parsed torrent file
+#[derive(Debug, Clone)]
 pub struct Torrent {
     pub meta_info: MetaInfo,
-    pub info_hash: Sha1Digest,
+    pub info_hash: TorrentInfoHash,
+}
+
+/// Info hash of v1, v2 or hybrid torrents, see
+/// [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html#infohash)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TorrentInfoHash {
+    V1(Sha1Digest),
+    V2(Sha256Digest),
+    /// Valid as both v1 and v2 torrent
+    Hybrid {
+        v1: Sha1Digest,
+        v2: Sha256Digest,
+    },
+}
+
+impl TorrentInfoHash {
+    /// Info hash of the torrent file `data`, reading nothing but the `info` dict.
+    ///
+    /// [Torrent::from_bytes] computes it while parsing, this is for when only the hash is
+    /// needed.
+    pub fn from_torrent_bytes(data: &[u8]) -> Result<Self> {
+        info_hash(data)
+    }
+
+    pub fn v1(&self) -> Option<&Sha1Digest> {
+        match self {
+            TorrentInfoHash::V1(v1) | TorrentInfoHash::Hybrid { v1, .. } => Some(v1),
+            TorrentInfoHash::V2(_) => None,
+        }
+    }
+
+    pub fn v2(&self) -> Option<&Sha256Digest> {
+        match self {
+            TorrentInfoHash::V2(v2) | TorrentInfoHash::Hybrid { v2, .. } => Some(v2),
+            TorrentInfoHash::V1(_) => None,
+        }
+    }
+
+    /// The SHA-256 hash truncated to 20 bytes, which identifies the v2 swarm to trackers and
+    /// peers
+    pub fn truncated_v2(&self) -> Option<Sha1Digest> {
+        self.v2()
+            .map(|v2| Sha1Digest::new(v2[..Sha1Digest::LENGTH].try_into().unwrap()))
+    }
+
+    pub fn is_hybrid(&self) -> bool {
+        matches!(self, TorrentInfoHash::Hybrid { .. })
+    }
+
+    /// The 20 bytes hash sent to trackers and peers: the SHA-1 hash if there is one, otherwise
+    /// the SHA-256 hash truncated to 20 bytes.
+    ///
+    /// A hybrid torrent is in two swarms, announce [Self::truncated_v2] as well to join the
+    /// v2 one.
+    pub fn short_hash(&self) -> Sha1Digest {
+        match self {
+            TorrentInfoHash::V1(v1) | TorrentInfoHash::Hybrid { v1, .. } => v1.clone(),
+            TorrentInfoHash::V2(_) => self.truncated_v2().unwrap(),
+        }
+    }
+}
+
+/// In hex, the hashes of a hybrid torrent are separated by a comma, v1 first
+impl Display for TorrentInfoHash {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TorrentInfoHash::V1(v1) => write!(f, "{}", v1),
+            TorrentInfoHash::V2(v2) => write!(f, "{}", v2),
+            TorrentInfoHash::Hybrid { v1, v2 } => write!(f, "{},{}", v1, v2),
+        }
+    }
+}
+
+/// Parse what [Display] writes: 40 hex characters for v1, 64 for v2, or both
+impl FromStr for TorrentInfoHash {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(',') {
+            Some((v1, v2)) => Ok(TorrentInfoHash::Hybrid {
+                v1: v1.parse()?,
+                v2: v2.parse()?,
+            }),
+            None if s.len() == Sha256Digest::LENGTH * 2 => s.parse().map(TorrentInfoHash::V2),
+            None => s.parse().map(TorrentInfoHash::V1),
+        }
+    }
 }
 
 impl Torrent {
+    /// Bytes shown on each side of a decode error
+    const ERROR_CONTEXT: usize = 16;
+
     /// Parse torrent file to rust struct
-    pub(crate) fn parse<P: AsRef<Path>>(path: P) -> Self {
-        let mut file = File::open(path.as_ref())
-            .unwrap_or_else(|_| panic!("Failed to open {:?}", path.as_ref()));
+    pub fn parse<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut file = File::open(path.as_ref())?;
         let mut buffer = vec![];
-        file.read_to_end(&mut buffer).expect("Failed to read file");
-        let info_hash = info_hash(&buffer).unwrap();
-        let meta_info: MetaInfo = de::from_bytes(&buffer).unwrap();
-        Self {
-            meta_info,
-            info_hash,
-        }
+        file.read_to_end(&mut buffer)?;
+        Self::from_bytes(&buffer)
+    }
+
+    /// Parse the content of a torrent file, anything following the metainfo dict is an error.
+    ///
+    /// Decode errors show the bytes around the failure, see [Error::with_hex_context].
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let decode = || {
+            // Take the span of the info dict while parsing, to hash it without another pass
+            let mut info = None;
+            let mut parser = BencodeParser::new(data);
+            parser.on_dict_value(|value| {
+                if value.depth == 1 && value.key == b"info" {
+                    info = Some(value.span);
+                }
+            });
+            let meta_info = MetaInfo::deserialize(&mut parser)?;
+            de::check_trailing(data, parser.bytes_consumed())?;
+            drop(parser);
+            let info = info.ok_or_else(|| {
+                Error::SerdeCustom("Failed to calculate info hash, no info dict".to_string())
+            })?;
+            let info_hash = hash_info(&data[info])?;
+            Ok(Self {
+                meta_info,
+                info_hash,
+            })
+        };
+        decode().map_err(|e: Error| e.with_hex_context(data, Self::ERROR_CONTEXT))
+    }
+
+    /// SHA-1 info hash of a v1 or hybrid torrent
+    pub fn info_hash_v1(&self) -> Option<&Sha1Digest> {
+        self.info_hash.v1()
+    }
+
+    /// SHA-256 info hash of a v2 or hybrid torrent
+    pub fn info_hash_v2(&self) -> Option<&Sha256Digest> {
+        self.info_hash.v2()
+    }
+
+    /// See [TorrentInfoHash::truncated_v2]
+    pub fn info_hash_v2_truncated(&self) -> Option<Sha1Digest> {
+        self.info_hash.truncated_v2()
+    }
+
+    /// Bytes still to download when the pieces in `have` are already downloaded.
+    ///
+    /// This is the `left` parameter of announce requests.
+    pub fn estimated_download_size(&self, have: &Bitfield) -> u64 {
+        let info = &self.meta_info.info;
+        let total_size = info.total_length();
+        let piece_count = info.piece_count();
+        let have_size: u64 = have
+            .iter()
+            .take_while(|index| *index < piece_count)
+            .map(|index| {
+                // Saturates for pieces past the end, which count for nothing
+                let piece_start = (index as u64).saturating_mul(info.piece_length);
+                // The last piece may be truncated
+                info.piece_length
+                    .min(total_size.saturating_sub(piece_start))
+            })
+            .sum();
+        total_size.saturating_sub(have_size)
     }
 }
 
-fn info_hash<D: AsRef<[u8]>>(data: D) -> Result<Sha1Digest> {
-    let mut decoder = BencodeParser::new(data.as_ref());
-    let obj = decoder.parse()?;
-    if let Some(Object::Dict(mut meta_dict)) = obj {
-        while let Some((name, obj)) = meta_dict.next_pair()? {
-            if std::str::from_utf8(name) == Ok("info") {
-                return if let Object::Dict(info_decoder) = obj {
-                    let raw_info: &[u8] = info_decoder.try_into()?;
-                    Ok(Sha1Digest::digest(raw_info))
-                } else {
-                    Err(Error::BencodeDecode("info data type not dict".to_string()))
-                };
+fn info_hash<D: AsRef<[u8]>>(data: D) -> Result<TorrentInfoHash> {
+    hash_info(raw_info(data.as_ref())?)
+}
+
+/// Info hash of the bytes of the `info` dict
+fn hash_info(raw_info: &[u8]) -> Result<TorrentInfoHash> {
+    let v1 = || Sha1Digest::digest(raw_info);
+    // Every key is looked at, the keys of a torrent in the wild aren't always sorted
+    match de::from_bytes_at::<BencodeValue>(raw_info, "meta version")? {
+        None => Ok(TorrentInfoHash::V1(v1())),
+        Some(BencodeValue::Int(2)) => {
+            let v2 = Sha256Digest::digest(raw_info);
+            // Hybrid torrents keep the v1 `pieces` next to the v2 `file tree`
+            if de::from_bytes_at::<IgnoredAny>(raw_info, "pieces")?.is_some() {
+                Ok(TorrentInfoHash::Hybrid { v1: v1(), v2 })
+            } else {
+                Ok(TorrentInfoHash::V2(v2))
             }
         }
+        Some(version) => Err(Error::SerdeCustom(format!(
+            "unsupported meta version {:?}",
+            version
+        ))),
+    }
+}
+
+/// The bytes of the `info` dict, as they are in the torrent file
+fn raw_info(data: &[u8]) -> Result<&[u8]> {
+    #[derive(Deserialize)]
+    struct RawTorrent<'a> {
+        #[serde(borrow, default)]
+        info: Option<RawValue<'a>>,
+    }
+
+    match de::from_bytes::<RawTorrent>(data)?.info {
+        Some(info) if info.get().starts_with(b"d") => Ok(info.get()),
+        Some(info) => Err(Error::BencodeDecode {
+            message: "info data type not dict".to_string(),
+            // The raw value is borrowed from `data`
+            offset: info.get().as_ptr() as usize - data.as_ptr() as usize,
+        }),
+        None => Err(Error::SerdeCustom(
+            "Failed to calculate info hash, no info dict".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimated_download_size() {
+        // 10 bytes in 3 pieces of 4, 4 and 2 bytes
+        let mut data = b"d4:infod6:lengthi10e4:name4:test12:piece lengthi4e6:pieces60:".to_vec();
+        data.extend_from_slice(&[0; 60]);
+        data.extend_from_slice(b"ee");
+        let torrent = Torrent {
+            meta_info: de::from_bytes(&data).unwrap(),
+            info_hash: info_hash(&data).unwrap(),
+        };
+
+        let mut have = Bitfield::new(3);
+        assert_eq!(torrent.estimated_download_size(&have), 10);
+        have.set(2);
+        assert_eq!(torrent.estimated_download_size(&have), 8);
+        have.set(0);
+        assert_eq!(torrent.estimated_download_size(&have), 4);
+        have.set(1);
+        assert_eq!(torrent.estimated_download_size(&have), 0);
+
+        // The start of the third piece doesn't fit in a u64
+        let mut data = format!(
+            "d4:infod6:lengthi{}e4:name4:test12:piece lengthi{}e6:pieces60:",
+            i64::MAX,
+            1u64 << 63
+        )
+        .into_bytes();
+        data.extend_from_slice(&[0; 60]);
+        data.extend_from_slice(b"ee");
+        let torrent = Torrent {
+            meta_info: de::from_bytes(&data).unwrap(),
+            info_hash: info_hash(&data).unwrap(),
+        };
+        let mut have = Bitfield::new(3);
+        have.set(2);
+        assert_eq!(torrent.estimated_download_size(&have), i64::MAX as u64);
+        have.set(0);
+        assert_eq!(torrent.estimated_download_size(&have), 0);
+    }
+
+    #[test]
+    fn test_reserialize_info() {
+        let torrent =
+            Torrent::parse("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let bytes = crate::to_bytes(&torrent.meta_info.info).unwrap();
+        assert_eq!(
+            TorrentInfoHash::V1(Sha1Digest::digest(bytes)),
+            torrent.info_hash
+        );
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let ret = Torrent::parse("./resources/not-exist.torrent");
+        assert!(matches!(ret, Err(Error::Io(_))));
+
+        let ret = Torrent::parse("./Cargo.toml");
+        assert!(matches!(ret, Err(Error::BencodeDecode { .. })));
+
+        let mut data =
+            std::fs::read("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        assert!(Torrent::from_bytes(&data).is_ok());
+        data.extend_from_slice(b"i0e");
+        let err = Torrent::from_bytes(&data).unwrap_err();
+        assert_eq!(err.decode_offset(), Some(data.len() - 3));
+        let message = err.to_string();
+        assert!(message.contains(" 65 [69] 30 65 |"));
+        assert!(message.contains("e[i]0e| at offset"));
+    }
+
+    #[test]
+    fn test_reserialize_meta_info() {
+        let torrent =
+            Torrent::parse("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let bytes = crate::to_bytes(&torrent.meta_info).unwrap();
+        assert_eq!(info_hash(&bytes).unwrap(), torrent.info_hash);
+
+        let meta_info: MetaInfo = de::from_bytes(&bytes).unwrap();
+        assert_eq!(meta_info.announce, torrent.meta_info.announce);
+        assert_eq!(meta_info.url_list, torrent.meta_info.url_list);
+        assert_eq!(meta_info.info.pieces, torrent.meta_info.info.pieces);
+    }
+
+    /// A torrent of `info`, returning the bytes of `info` too
+    fn build_torrent(info: &[u8]) -> (Vec<u8>, &[u8]) {
+        let mut data = b"d4:info".to_vec();
+        data.extend_from_slice(info);
+        data.push(b'e');
+        (data, info)
+    }
+
+    #[test]
+    fn test_info_hash_versions() {
+        let mut v1 = b"d6:lengthi10e4:name4:test12:piece lengthi4e6:pieces20:".to_vec();
+        v1.extend_from_slice(&[0; 20]);
+        v1.push(b'e');
+        let (data, info) = build_torrent(&v1);
+        let hash = info_hash(&data).unwrap();
+        assert_eq!(hash, TorrentInfoHash::V1(Sha1Digest::digest(info)));
+        assert_eq!(hash.v2(), None);
+        assert_eq!(&hash.short_hash(), hash.v1().unwrap());
+
+        let mut v2 = b"d9:file treed4:testd0:d6:lengthi10e11:pieces root32:".to_vec();
+        v2.extend_from_slice(&[0; 32]);
+        v2.extend_from_slice(b"eee12:meta versioni2e4:name4:test12:piece lengthi16384ee");
+        let (data, info) = build_torrent(&v2);
+        let hash = info_hash(&data).unwrap();
+        let sha256 = Sha256Digest::digest(info);
+        assert_eq!(hash, TorrentInfoHash::V2(sha256.clone()));
+        assert_eq!(hash.v1(), None);
+        assert_eq!(hash.short_hash().0, sha256[..20]);
+        assert_eq!(hash.truncated_v2(), Some(hash.short_hash()));
+        assert_eq!(hash.to_string().parse::<TorrentInfoHash>().unwrap(), hash);
+
+        let mut hybrid = b"d9:file treed4:testd0:d6:lengthi10e11:pieces root32:".to_vec();
+        hybrid.extend_from_slice(&[0; 32]);
+        hybrid.extend_from_slice(
+            b"eee6:lengthi10e12:meta versioni2e4:name4:test12:piece lengthi16384e6:pieces20:",
+        );
+        hybrid.extend_from_slice(&[0; 20]);
+        hybrid.push(b'e');
+        let (data, info) = build_torrent(&hybrid);
+        let hash = info_hash(&data).unwrap();
+        assert_eq!(
+            hash,
+            TorrentInfoHash::Hybrid {
+                v1: Sha1Digest::digest(info),
+                v2: Sha256Digest::digest(info),
+            }
+        );
+        assert_eq!(&hash.short_hash(), hash.v1().unwrap());
+        assert!(hash.is_hybrid());
+        assert_eq!(hash.to_string().parse::<TorrentInfoHash>().unwrap(), hash);
+        let torrent = Torrent::from_bytes(&data).unwrap();
+        assert_eq!(torrent.info_hash_v1(), Some(&Sha1Digest::digest(info)));
+        assert_eq!(
+            torrent.info_hash_v2_truncated().unwrap().0,
+            Sha256Digest::digest(info)[..20]
+        );
+        let meta_info: MetaInfo = de::from_bytes(&data).unwrap();
+        assert_eq!(meta_info.meta_version(), Some(2));
+
+        // Unsorted keys, `meta version` comes after `name`
+        let unsorted = b"d4:name4:test12:piece lengthi16384e12:meta versioni2ee";
+        let (data, info) = build_torrent(unsorted);
+        let hash = info_hash(&data).unwrap();
+        assert_eq!(hash, TorrentInfoHash::V2(Sha256Digest::digest(info)));
+
+        let (data, _) = build_torrent(b"d12:meta versioni3e4:name4:teste");
+        assert!(matches!(info_hash(&data), Err(Error::SerdeCustom(_))));
+        let err = info_hash(b"d8:announce0:4:infoi1ee").unwrap_err();
+        assert_eq!(err.decode_offset(), Some(19));
     }
-    Err(Error::BencodeDecode(
-        "Failed to calculate info hash".to_string(),
-    ))
 }
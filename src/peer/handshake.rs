@@ -0,0 +1,100 @@
+//! The handshake opening a peer connection, see [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html#peer-protocol)
+use super::*;
+
+const PROTOCOL: &[u8; 19] = b"BitTorrent protocol";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handshake {
+    pub info_hash: Sha1Digest,
+    pub peer_id: [u8; 20],
+    /// Extension bits, all zero if no extension is supported
+    pub reserved: [u8; 8],
+}
+
+impl Handshake {
+    pub const LENGTH: usize = 68;
+
+    /// `pstrlen`, `pstr`, reserved, info-hash, peer-id
+    pub fn encode(&self) -> [u8; Self::LENGTH] {
+        let mut buf = [0u8; Self::LENGTH];
+        buf[0] = PROTOCOL.len() as u8;
+        buf[1..20].copy_from_slice(PROTOCOL);
+        buf[20..28].copy_from_slice(&self.reserved);
+        buf[28..48].copy_from_slice(&self.info_hash.0);
+        buf[48..68].copy_from_slice(&self.peer_id);
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf.len() < Self::LENGTH {
+            return Err(Error::PeerProtocol(format!(
+                "expect {} bytes of handshake but get {}",
+                Self::LENGTH,
+                buf.len()
+            )));
+        }
+        if buf[0] as usize != PROTOCOL.len() {
+            return Err(Error::PeerProtocol(format!(
+                "invalid protocol string length {}",
+                buf[0]
+            )));
+        }
+        if &buf[1..20] != PROTOCOL {
+            return Err(Error::PeerProtocol(format!(
+                "invalid protocol string {:?}",
+                String::from_utf8_lossy(&buf[1..20])
+            )));
+        }
+        Ok(Handshake {
+            info_hash: Sha1Digest(buf[28..48].try_into().unwrap()),
+            peer_id: buf[48..68].try_into().unwrap(),
+            reserved: buf[20..28].try_into().unwrap(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_handshake() -> Handshake {
+        Handshake {
+            info_hash: Sha1Digest([0xab; 20]),
+            peer_id: *b"-YT0001-123456789012",
+            reserved: [0, 0, 0, 0, 0, 0x10, 0, 0x01],
+        }
+    }
+
+    #[test]
+    fn test_handshake_round_trip() {
+        let handshake = sample_handshake();
+        let buf = handshake.encode();
+        assert_eq!(buf[0], 19);
+        assert_eq!(&buf[1..20], b"BitTorrent protocol");
+        assert_eq!(&buf[48..], b"-YT0001-123456789012");
+        assert_eq!(Handshake::decode(&buf).unwrap(), handshake);
+    }
+
+    #[test]
+    fn test_handshake_invalid() {
+        let mut buf = sample_handshake().encode();
+        buf[1] = b'b';
+        assert!(matches!(
+            Handshake::decode(&buf),
+            Err(Error::PeerProtocol(_))
+        ));
+
+        let mut buf = sample_handshake().encode();
+        buf[0] = 18;
+        assert!(matches!(
+            Handshake::decode(&buf),
+            Err(Error::PeerProtocol(_))
+        ));
+
+        let buf = sample_handshake().encode();
+        assert!(matches!(
+            Handshake::decode(&buf[..67]),
+            Err(Error::PeerProtocol(_))
+        ));
+    }
+}
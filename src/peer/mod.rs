@@ -0,0 +1,6 @@
+pub use handshake::*;
+
+use super::common::*;
+use super::meta::*;
+
+mod handshake;
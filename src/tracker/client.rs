@@ -1,81 +1,881 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use log::warn;
 use rand::random;
-use url::form_urlencoded::byte_serialize;
+use rand::seq::SliceRandom;
 
 use super::*;
 
+/// How [Client] reacts when an announce fails
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconnectPolicy {
+    /// Report the failure immediately
+    NoReconnect,
+    /// Retry forever, waiting the same delay before each attempt
+    FixedDelay(Duration),
+    /// Retry up to `max_attempts` times, doubling the delay from `initial` up to `max`
+    ExponentialBackoff {
+        initial: Duration,
+        max: Duration,
+        max_attempts: u32,
+    },
+}
+
+impl ReconnectPolicy {
+    /// Delay before the retry following the failed `attempt` (starting from 0), or `None` if
+    /// no more retries should be made.
+    pub fn delay(&self, attempt: u32) -> Option<Duration> {
+        match self {
+            ReconnectPolicy::NoReconnect => None,
+            ReconnectPolicy::FixedDelay(delay) => Some(*delay),
+            ReconnectPolicy::ExponentialBackoff {
+                initial,
+                max,
+                max_attempts,
+            } => {
+                if attempt >= *max_attempts {
+                    return None;
+                }
+                let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+                Some(initial.saturating_mul(factor).min(*max))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub reconnect_policy: ReconnectPolicy,
+    /// The port the client listens on, advertised to trackers. `0` asks the tracker to ignore it.
+    pub port: u16,
+    /// How long to wait for the response of an HTTP announce
+    pub announce_timeout: Duration,
+    /// How long to wait for the response of an HTTP scrape
+    pub scrape_timeout: Duration,
+    /// How long to wait for a piece from a web seed, body included
+    pub webseed_timeout: Duration,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            reconnect_policy: ReconnectPolicy::NoReconnect,
+            port: 6881,
+            announce_timeout: Duration::from_secs(30),
+            scrape_timeout: Duration::from_secs(30),
+            webseed_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The state of the download reported in an announce
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnounceRequest {
+    /// The port the client is listening on
+    pub port: u16,
+    pub uploaded: u64,
+    pub downloaded: u64,
+    /// Bytes the client still has to download
+    pub left: u64,
+    pub event: AnnounceEvent,
+    /// Number of peers wanted, the tracker decides when `None`
+    pub num_want: Option<u32>,
+    pub compact: bool,
+}
+
+impl AnnounceRequest {
+    /// The `started` announce of a new download, asking for compact peers
+    pub fn new_start(left: u64, port: u16) -> AnnounceRequest {
+        AnnounceRequest {
+            port,
+            uploaded: 0,
+            downloaded: 0,
+            left,
+            event: AnnounceEvent::Started,
+            num_want: None,
+            compact: true,
+        }
+    }
+}
+
 pub struct Client {
     pub torrent: Torrent,
+    tracker: HttpTrackerClient,
+    udp_tracker: UdpTrackerClient,
+    /// Trackers of [BEP-0012](https://www.bittorrent.org/beps/bep_0012.html), shuffled within
+    /// each tier and reordered on every successful announce
+    announce_tiers: Mutex<AnnounceList>,
+    /// Sent in announces, a random one is generated for each announce when `None`
+    peer_id: Option<[u8; 20]>,
+    /// Sent as `left` in announces instead of the length of the torrent, for magnet links
+    /// whose length isn't known yet
+    left: Option<u64>,
 }
 
+/// `left` announced before the metadata of a magnet link is fetched. Any nonzero value keeps
+/// trackers from taking the client for a seeder, the size of one metadata piece is the usual
+/// choice.
+const MAGNET_LEFT: u64 = 16384;
+
 impl Client {
     /// Construct a [Client] from a torrent file
-    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self::from_torrent(Torrent::parse(path)?))
+    }
+
+    /// Construct a [Client] from a magnet link, so trackers can be contacted without the
+    /// torrent file.
+    ///
+    /// The `info` of the torrent is a placeholder of an empty single file, until the metadata is
+    /// fetched from peers. Announces report a nonzero `left` meanwhile, not the length of the
+    /// placeholder, see [Self::with_left]. Without `tr` parameters in the link, announces and
+    /// scrapes fail with [Error::Request].
+    pub fn from_magnet(link: &MagnetLink) -> Result<Self> {
+        let info = Info {
+            mode: FileMode::Single { length: 0 },
+            name: link.display_name.clone(),
+            piece_length: 0,
+            pieces: PieceList(vec![]),
+            private: None,
+            meta_version: None,
+        };
+        let meta_info = MetaInfo {
+            announce: link.trackers.first().cloned(),
+            announce_list: (!link.trackers.is_empty()).then(|| vec![link.trackers.clone()]),
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            info,
+            nodes: (!link.nodes.is_empty()).then(|| link.nodes.clone()),
+            url_list: None,
+        };
+        Ok(Self::from_torrent(Torrent {
+            meta_info,
+            info_hash: link.info_hash.clone(),
+        })
+        .with_left(MAGNET_LEFT))
+    }
+
+    fn from_torrent(torrent: Torrent) -> Self {
+        let announce_tiers = Mutex::new(announce_tiers(&torrent.meta_info));
         Self {
-            torrent: Torrent::parse(path),
+            torrent,
+            tracker: HttpTrackerClient::new(ClientConfig::default()),
+            udp_tracker: UdpTrackerClient::default(),
+            announce_tiers,
+            peer_id: None,
+            left: None,
         }
     }
 
-    pub async fn connect_announce(&self) -> Result<TrackerResponseCompat> {
-        let peer_id: [u8; 20] = random();
-        let info_hash_query: String = byte_serialize(self.torrent.info_hash.as_ref()).collect();
-        let peer_id_query: String = byte_serialize(&peer_id).collect();
-        let http_url = format!(
-            "{}?info_hash={}&peer_id={}&compact=1",
-            self.torrent.meta_info.announce.as_ref().unwrap(),
-            info_hash_query,
-            peer_id_query
-        );
-        if cfg!(test) {
-            println!("url: {}", http_url);
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        self.tracker = HttpTrackerClient::new(config);
+        self
+    }
+
+    /// Set the port advertised to trackers
+    pub fn listen_port(self, port: u16) -> Self {
+        let config = ClientConfig {
+            port,
+            ..self.config().clone()
+        };
+        self.with_config(config)
+    }
+
+    /// Advertise port `0`, so the tracker ignores the port and uses the one it sees the
+    /// request coming from, which works behind NAT.
+    pub fn with_nat_detection(self) -> Self {
+        self.listen_port(0)
+    }
+
+    /// Identify the client with `peer_id`, see [peer_id_azureus]
+    pub fn with_peer_id(mut self, peer_id: [u8; 20]) -> Self {
+        self.peer_id = Some(peer_id);
+        self
+    }
+
+    /// Report `left` bytes to download in [Self::announce_request] instead of the length of the
+    /// torrent
+    pub fn with_left(mut self, left: u64) -> Self {
+        self.left = Some(left);
+        self
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        self.tracker.config()
+    }
+
+    /// Announce to the tracker, retrying failed attempts according to
+    /// [ClientConfig::reconnect_policy].
+    ///
+    /// The tracker is `announce`, or the first one of [Self::announce_tiers] without it.
+    /// Only [Error::Connection] and [Error::Io] are retried, a tracker which answered, even
+    /// with a failure reason or a malformed response, won't answer better next time.
+    pub async fn connect_announce(&self, req: &AnnounceRequest) -> Result<TrackerResponseCompat> {
+        let params = self.announce_params_from(req);
+        let announce_url = self.announce_url()?;
+        self.announce_with_retry(&announce_url, &params).await
+    }
+
+    /// `announce` of the torrent, or the first tracker of the tiers without it
+    fn announce_url(&self) -> Result<String> {
+        match &self.torrent.meta_info.announce {
+            Some(announce) => Ok(announce.clone()),
+            None => self
+                .announce_tiers()
+                .into_iter()
+                .flatten()
+                .next()
+                .ok_or_else(|| Error::Request("no tracker in torrent".to_string())),
         }
-        let ret = reqwest::get(http_url).await?;
-        let bytes = ret.bytes().await?;
-        if cfg!(test) {
-            println!("response {:?}", bytes);
+    }
+
+    /// Announce to the trackers of all tiers in order, until one of them succeeds.
+    ///
+    /// Each tracker gets one attempt, so a dead tracker doesn't hold back the next tiers. When
+    /// all of them fail and one of them couldn't be reached, the whole sweep is retried
+    /// according to [ClientConfig::reconnect_policy].
+    ///
+    /// The tracker which succeeded is moved to the front of its tier, so it is tried first next
+    /// time. Returns the error of the last tracker if all of them fail.
+    pub async fn connect_announce_all(
+        &self,
+        req: &AnnounceRequest,
+    ) -> Result<TrackerResponseCompat> {
+        let params = self.announce_params_from(req);
+        let mut attempt = 0;
+        loop {
+            let tiers = self.announce_tiers();
+            let mut last_error = Error::Request("no tracker in torrent".to_string());
+            let mut transient = false;
+            for (tier_index, tier) in tiers.iter().enumerate() {
+                for url in tier {
+                    match self.announce_once(url, &params).await {
+                        Ok(response) => {
+                            self.move_to_tier_front(tier_index, url);
+                            return Ok(response);
+                        }
+                        Err(err) => {
+                            warn!("announce to {} failed: {}", url, err);
+                            transient |= is_transient(&err);
+                            last_error = err;
+                        }
+                    }
+                }
+            }
+            let delay = match transient {
+                true => self.config().reconnect_policy.delay(attempt),
+                false => None,
+            };
+            match delay {
+                Some(delay) => {
+                    warn!("announce to all trackers failed, retry in {:?}", delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                None => return Err(last_error),
+            }
         }
-        let response: TrackerResponseCompat = de::from_bytes(&bytes)?;
-        Ok(response)
     }
 
-    pub async fn connect_scrape(&self) -> Result<ScrapeFile> {
-        let announce_url = self.torrent.meta_info.announce.as_ref().unwrap();
-        let scrape_url = announce_url.replacen("announce", "scrape", 1);
-        let info_hash_query: String = byte_serialize(self.torrent.info_hash.as_ref()).collect();
+    /// Trackers in the order [Self::connect_announce_all] tries them
+    pub fn announce_tiers(&self) -> AnnounceList {
+        self.announce_tiers.lock().unwrap().clone()
+    }
+
+    fn move_to_tier_front(&self, tier_index: usize, url: &str) {
+        let mut tiers = self.announce_tiers.lock().unwrap();
+        if let Some(tier) = tiers.get_mut(tier_index) {
+            if let Some(index) = tier.iter().position(|tracker| tracker == url) {
+                let tracker = tier.remove(index);
+                tier.insert(0, tracker);
+            }
+        }
+    }
 
-        let http_url = format!("{}?info_hash={}", scrape_url, info_hash_query);
-        if cfg!(test) {
-            println!("url: {}", http_url);
+    async fn announce_with_retry(
+        &self,
+        announce_url: &str,
+        params: &AnnounceParams,
+    ) -> Result<TrackerResponseCompat> {
+        let mut attempt = 0;
+        loop {
+            match self.announce_once(announce_url, params).await {
+                Err(err) if is_transient(&err) => {
+                    match self.config().reconnect_policy.delay(attempt) {
+                        Some(delay) => {
+                            warn!("announce failed: {}, retry in {:?}", err, delay);
+                            tokio::time::sleep(delay).await;
+                            attempt += 1;
+                        }
+                        None => return Err(err),
+                    }
+                }
+                ret => return ret,
+            }
         }
-        let ret = reqwest::get(http_url).await?;
-        let bytes = ret.bytes().await?;
-        if cfg!(test) {
-            println!("response {:?}", bytes);
+    }
+
+    /// A request for `event` with nothing transferred yet, advertising [ClientConfig::port]
+    pub fn announce_request(&self, event: AnnounceEvent) -> AnnounceRequest {
+        let left = self
+            .left
+            .unwrap_or_else(|| self.torrent.meta_info.info.total_length());
+        AnnounceRequest {
+            event,
+            ..AnnounceRequest::new_start(left, self.config().port)
         }
-        let mut response: ScrapeResponse = de::from_bytes(bytes.as_ref())?;
+    }
+
+    fn announce_params_from(&self, req: &AnnounceRequest) -> AnnounceParams {
+        AnnounceParams {
+            info_hash: self.torrent.info_hash.short_hash(),
+            peer_id: self.peer_id.unwrap_or_else(random),
+            port: req.port,
+            uploaded: req.uploaded,
+            downloaded: req.downloaded,
+            left: req.left,
+            event: req.event,
+            compact: req.compact,
+            no_peer_id: false,
+            numwant: req.num_want,
+            tracker_id: None,
+        }
+    }
+
+    async fn announce_once(
+        &self,
+        announce_url: &str,
+        params: &AnnounceParams,
+    ) -> Result<TrackerResponseCompat> {
+        if announce_url.starts_with("udp://") {
+            return self.connect_announce_udp(announce_url, params).await;
+        }
+        self.tracker.announce(announce_url, params).await
+    }
+
+    async fn connect_announce_udp(
+        &self,
+        announce_url: &str,
+        params: &AnnounceParams,
+    ) -> Result<TrackerResponseCompat> {
+        self.udp_tracker.announce(announce_url, params).await
+    }
+
+    /// Download `length` bytes from `byte_offset` of a
+    /// [BEP-0019](https://www.bittorrent.org/beps/bep_0019.html) web seed file
+    pub async fn fetch_webseed_piece(
+        &self,
+        file_url: &str,
+        byte_offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        self.tracker
+            .fetch_range(file_url, byte_offset, length)
+            .await
+    }
+
+    /// Scrape the tracker [Self::connect_announce] would announce to
+    pub async fn connect_scrape(&self) -> Result<ScrapeFile> {
+        let announce_url = self.announce_url()?;
+        let scrape_url = scrape_url_from_announce(&announce_url)
+            .ok_or_else(|| Error::Request("scrape not supported by this tracker".to_string()))?;
+        let info_hash = self.torrent.info_hash.short_hash();
+        let mut response = self
+            .tracker
+            .scrape(&scrape_url, std::slice::from_ref(&info_hash))
+            .await?;
         response
             .files
-            .remove(&self.torrent.info_hash)
+            .remove(&info_hash)
             .ok_or(Error::Request("Failed to fetch file info".to_string()))
     }
 }
 
+/// Whether a failed request may succeed if sent again: the server wasn't reached, or a UDP
+/// tracker didn't answer
+fn is_transient(err: &Error) -> bool {
+    matches!(err, Error::Connection(_) | Error::Io(_))
+}
+
+/// Scrape `hashes` from the tracker of `announce_url` in a single request, independent of any
+/// [Client]
+pub async fn scrape_many(
+    announce_url: &str,
+    hashes: &[Sha1Digest],
+) -> Result<HashMap<Sha1Digest, ScrapeFile>> {
+    let scrape_url = scrape_url_from_announce(announce_url)
+        .ok_or_else(|| Error::Request("scrape not supported by this tracker".to_string()))?;
+    let tracker = HttpTrackerClient::new(ClientConfig::default());
+    Ok(tracker.scrape(&scrape_url, hashes).await?.files)
+}
+
+/// An Azureus-style peer id, `-` + `client_id` + `version` + `-` followed by 12 random bytes,
+/// e.g. `-YT0001-` for version 0.0.0.1 of this crate
+pub fn peer_id_azureus(client_id: [u8; 2], version: [u8; 4]) -> [u8; 20] {
+    let mut peer_id = [0u8; 20];
+    peer_id[0] = b'-';
+    peer_id[1..3].copy_from_slice(&client_id);
+    peer_id[3..7].copy_from_slice(&version);
+    peer_id[7] = b'-';
+    peer_id[8..].copy_from_slice(&random::<[u8; 12]>());
+    peer_id
+}
+
+/// Tiers of `announce-list`, or a single tier of `announce` without it, shuffled within each
+/// tier as BEP-0012 requires
+fn announce_tiers(meta_info: &MetaInfo) -> AnnounceList {
+    let mut tiers = match (&meta_info.announce_list, &meta_info.announce) {
+        (Some(announce_list), _) => announce_list.clone(),
+        (None, Some(announce)) => vec![vec![announce.clone()]],
+        (None, None) => vec![],
+    };
+    tiers.retain(|tier| !tier.is_empty());
+    let mut rng = rand::thread_rng();
+    for tier in tiers.iter_mut() {
+        tier.shuffle(&mut rng);
+    }
+    tiers
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::tracker::client::Client;
+    use std::time::Duration;
+
+    use crate::tracker::client::*;
+
+    #[test]
+    fn test_reconnect_policy() {
+        assert_eq!(ReconnectPolicy::NoReconnect.delay(0), None);
+
+        let fixed = ReconnectPolicy::FixedDelay(Duration::from_secs(5));
+        assert_eq!(fixed.delay(0), Some(Duration::from_secs(5)));
+        assert_eq!(fixed.delay(100), Some(Duration::from_secs(5)));
+
+        let backoff = ReconnectPolicy::ExponentialBackoff {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(10),
+            max_attempts: 5,
+        };
+        assert_eq!(backoff.delay(0), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.delay(1), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.delay(3), Some(Duration::from_secs(8)));
+        assert_eq!(backoff.delay(4), Some(Duration::from_secs(10)));
+        assert_eq!(backoff.delay(5), None);
+    }
+
+    fn announce_query(client: &Client) -> String {
+        let req = client.announce_request(AnnounceEvent::Empty);
+        client.announce_params_from(&req).to_query_string()
+    }
+
+    #[test]
+    fn test_announce_request() {
+        let req = AnnounceRequest::new_start(1024, 51413);
+        assert_eq!(req.event, AnnounceEvent::Started);
+        assert_eq!((req.uploaded, req.downloaded, req.left), (0, 0, 1024));
+        assert!(req.compact);
+
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let req = client.announce_request(AnnounceEvent::Empty);
+        assert_eq!(req.left, 659554304);
+        assert!(!announce_query(&client).contains("event="));
+        let params = client.announce_params_from(&AnnounceRequest {
+            event: AnnounceEvent::Completed,
+            ..req
+        });
+        assert!(params.to_query_string().contains("&event=completed"));
+    }
+
+    #[test]
+    fn test_announce_port() {
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let query = announce_query(&client);
+        assert!(query.contains("&port=6881&"));
+
+        let client = client.listen_port(51413);
+        let query = announce_query(&client);
+        assert!(query.contains("&port=51413&"));
+
+        let client = client.with_nat_detection();
+        let query = announce_query(&client);
+        assert!(query.contains("&port=0&"));
+    }
+
+    #[test]
+    fn test_peer_id() {
+        let peer_id = peer_id_azureus(*b"YT", *b"0001");
+        assert_eq!(&peer_id[..8], b"-YT0001-");
+        assert_ne!(peer_id_azureus(*b"YT", *b"0001"), peer_id);
+
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        assert_ne!(announce_query(&client), announce_query(&client));
+
+        let client = client.with_peer_id(*b"-YT0001-abcdefghijkl");
+        let query = announce_query(&client);
+        assert!(query.contains("&peer_id=-YT0001-abcdefghijkl&"));
+        assert_eq!(announce_query(&client), query);
+    }
+
+    #[tokio::test]
+    async fn test_announce_failure_reason() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/announce"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(b"d14:failure reason11:banned peere".to_vec()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        Mock::given(path("/malformed/announce"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"d8:interval".to_vec()))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        // Retrying forever must not loop on a tracker which answered
+        let mut client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent")
+            .unwrap()
+            .with_config(ClientConfig {
+                reconnect_policy: ReconnectPolicy::FixedDelay(Duration::from_millis(10)),
+                ..ClientConfig::default()
+            });
+        client.torrent.meta_info.announce = Some(format!("{}/announce", server.uri()));
+        let req = client.announce_request(AnnounceEvent::Started);
+        let resp = client.connect_announce(&req).await;
+        assert!(matches!(resp, Err(Error::TrackerFailure(reason)) if reason == "banned peer"));
+
+        client.torrent.meta_info.announce = Some(format!("{}/malformed/announce", server.uri()));
+        let resp = client.connect_announce(&req).await;
+        let err = resp.unwrap_err();
+        assert!(matches!(err.root_cause(), Error::BencodeDecode { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_connect_announce_all() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/fail/announce"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_bytes(b"d14:failure reason4:downe".to_vec()),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(path("/ok/announce"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(b"d8:intervali900e5:peers6:\x7f\x00\x00\x01\x1a\xe1e".to_vec()),
+            )
+            .mount(&server)
+            .await;
+        let fail_url = format!("{}/fail/announce", server.uri());
+        let ok_url = format!("{}/ok/announce", server.uri());
+
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        *client.announce_tiers.lock().unwrap() = vec![
+            vec![fail_url.clone()],
+            vec![fail_url.clone(), ok_url.clone()],
+        ];
+        let req = AnnounceRequest {
+            port: 6881,
+            uploaded: 0,
+            downloaded: 0,
+            left: 100,
+            event: AnnounceEvent::Started,
+            num_want: Some(10),
+            compact: true,
+        };
+        let resp = client.connect_announce_all(&req).await.unwrap();
+        assert_eq!(resp.interval, Some(900));
+        assert_eq!(
+            client.announce_tiers(),
+            vec![
+                vec![fail_url.clone()],
+                vec![ok_url.clone(), fail_url.clone()]
+            ]
+        );
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 3);
+        let query = requests[2].url.query().unwrap();
+        assert!(query.contains("&left=100&"));
+        assert!(query.contains("&event=started&numwant=10"));
+
+        *client.announce_tiers.lock().unwrap() = vec![vec![fail_url]];
+        let resp = client.connect_announce_all(&req).await;
+        assert!(matches!(resp, Err(Error::TrackerFailure(reason)) if reason == "down"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_announce_all_failover() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/announce"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(b"d8:intervali900e5:peers6:\x7f\x00\x00\x01\x1a\xe1e".to_vec()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        // Nothing listens on the port once the listener is dropped
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let dead_url = format!("http://{}/announce", listener.local_addr().unwrap());
+        drop(listener);
+        let ok_url = format!("{}/announce", server.uri());
+
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent")
+            .unwrap()
+            .with_config(ClientConfig {
+                reconnect_policy: ReconnectPolicy::FixedDelay(Duration::from_millis(10)),
+                ..ClientConfig::default()
+            });
+        *client.announce_tiers.lock().unwrap() = vec![vec![dead_url.clone()], vec![ok_url]];
+        let req = client.announce_request(AnnounceEvent::Started);
+        let resp = tokio::time::timeout(Duration::from_secs(5), client.connect_announce_all(&req))
+            .await
+            .expect("tier 1 is never tried");
+        assert_eq!(resp.unwrap().interval, Some(900));
+
+        // The policy applies to the sweep, which fails once it gives up
+        let client = client.with_config(ClientConfig {
+            reconnect_policy: ReconnectPolicy::ExponentialBackoff {
+                initial: Duration::from_millis(1),
+                max: Duration::from_millis(1),
+                max_attempts: 2,
+            },
+            ..ClientConfig::default()
+        });
+        *client.announce_tiers.lock().unwrap() = vec![vec![dead_url]];
+        let resp = client.connect_announce_all(&req).await;
+        assert!(matches!(resp, Err(Error::Connection(_))));
+    }
+
+    #[tokio::test]
+    async fn test_announce_timeout_retry() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        let ok = b"d8:intervali900e5:peers6:\x7f\x00\x00\x01\x1a\xe1e".to_vec();
+        Mock::given(path("/slow/announce"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_bytes(ok.clone())
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/slow/announce"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(ok))
+            .mount(&server)
+            .await;
+        Mock::given(path("/error/announce"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&server)
+            .await;
+
+        let mut client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent")
+            .unwrap()
+            .with_config(ClientConfig {
+                reconnect_policy: ReconnectPolicy::FixedDelay(Duration::from_millis(10)),
+                announce_timeout: Duration::from_millis(200),
+                ..ClientConfig::default()
+            });
+        let req = client.announce_request(AnnounceEvent::Started);
+        client.torrent.meta_info.announce = Some(format!("{}/slow/announce", server.uri()));
+        let resp = client.connect_announce(&req).await.unwrap();
+        assert_eq!(resp.interval, Some(900));
+        assert_eq!(server.received_requests().await.unwrap().len(), 2);
+
+        client.torrent.meta_info.announce = Some(format!("{}/error/announce", server.uri()));
+        let resp = client.connect_announce(&req).await;
+        assert!(matches!(resp, Err(Error::HttpStatus(500))));
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_scrape_many() {
+        use wiremock::matchers::path;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let hashes = [
+            Sha1Digest([0x01; 20]),
+            Sha1Digest([b'a'; 20]),
+            Sha1Digest([0xff; 20]),
+        ];
+        let mut body = b"d5:filesd20:".to_vec();
+        body.extend_from_slice(&hashes[0].0);
+        body.extend_from_slice(b"d8:completei5e10:downloadedi50e10:incompletei10eeee");
+        let server = MockServer::start().await;
+        Mock::given(path("/scrape"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(body))
+            .mount(&server)
+            .await;
+
+        let announce_url = format!("{}/announce", server.uri());
+        let files = scrape_many(&announce_url, &hashes).await.unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[&hashes[0]].complete, 5);
+
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].url.query(),
+            Some(
+                "info_hash=%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01%01\
+                 &info_hash=aaaaaaaaaaaaaaaaaaaa\
+                 &info_hash=%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF%FF"
+            )
+        );
+
+        let err = scrape_many(&format!("{}/tracker", server.uri()), &hashes).await;
+        assert!(matches!(err, Err(Error::Request(_))));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_webseed_piece() {
+        use wiremock::matchers::{header, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(path("/file.iso"))
+            .and(header("Range", "bytes=1024-1033"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(b"0123456789".to_vec()))
+            .mount(&server)
+            .await;
+        Mock::given(path("/full.iso"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"0123456789".to_vec()))
+            .mount(&server)
+            .await;
+
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let url = format!("{}/file.iso", server.uri());
+        let piece = client.fetch_webseed_piece(&url, 1024, 10).await.unwrap();
+        assert_eq!(piece, b"0123456789");
+        assert!(client.fetch_webseed_piece(&url, 1024, 5).await.is_err());
+        assert!(matches!(
+            client.fetch_webseed_piece(&url, 1024, 0).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        assert!(matches!(
+            client.fetch_webseed_piece(&url, u64::MAX, 2).await,
+            Err(Error::InvalidArgument(_))
+        ));
+        let url = format!("{}/full.iso", server.uri());
+        assert!(matches!(
+            client.fetch_webseed_piece(&url, 0, 10).await,
+            Err(Error::Request(_))
+        ));
+
+        Mock::given(path("/slow.iso"))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .set_body_bytes(b"0123456789".to_vec())
+                    .set_delay(Duration::from_secs(5)),
+            )
+            .mount(&server)
+            .await;
+        let client = client.with_config(ClientConfig {
+            webseed_timeout: Duration::from_millis(200),
+            ..ClientConfig::default()
+        });
+        let url = format!("{}/slow.iso", server.uri());
+        assert!(matches!(
+            client.fetch_webseed_piece(&url, 0, 10).await,
+            Err(Error::Connection(_))
+        ));
+    }
+
+    #[test]
+    fn test_announce_tiers() {
+        let mut client =
+            Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        assert_eq!(
+            client.announce_tiers(),
+            vec![vec!["http://bttracker.debian.org:6969/announce".to_string()]]
+        );
+
+        let meta_info = &mut client.torrent.meta_info;
+        meta_info.announce_list = Some(vec![
+            vec!["a".into(), "b".into(), "c".into()],
+            vec![],
+            vec!["d".into()],
+        ]);
+        let mut tiers = announce_tiers(meta_info);
+        assert_eq!(tiers.len(), 2);
+        tiers[0].sort();
+        assert_eq!(tiers[0], vec!["a", "b", "c"]);
+        assert_eq!(tiers[1], vec!["d"]);
+    }
+
+    #[test]
+    fn test_from_magnet() {
+        let link = MagnetLink::parse(
+            "magnet:?xt=urn:btih:a9993e364706816aba3e25717850c26c9cd0d89d&dn=demo\
+             &tr=http%3A%2F%2Fa%2Fannounce&tr=http%3A%2F%2Fb%2Fannounce",
+        )
+        .unwrap();
+        let client = Client::from_magnet(&link).unwrap();
+        assert_eq!(client.torrent.info_hash, link.info_hash);
+        assert_eq!(client.torrent.meta_info.info.name.as_deref(), Some("demo"));
+        assert_eq!(
+            client.torrent.meta_info.announce.as_deref(),
+            Some("http://a/announce")
+        );
+        let mut tiers = client.announce_tiers();
+        tiers[0].sort();
+        assert_eq!(tiers, vec![vec!["http://a/announce", "http://b/announce"]]);
+
+        // The client isn't a seeder of the placeholder info
+        let query = announce_query(&client);
+        assert!(query.contains("&left=16384&"), "{}", query);
+        let query = announce_query(&client.with_left(1000));
+        assert!(query.contains("&left=1000&"), "{}", query);
+    }
+
+    #[tokio::test]
+    async fn test_from_magnet_without_trackers() {
+        let link =
+            MagnetLink::parse("magnet:?xt=urn:btih:a9993e364706816aba3e25717850c26c9cd0d89d")
+                .unwrap();
+        let client = Client::from_magnet(&link).unwrap();
+        let req = client.announce_request(AnnounceEvent::Started);
+        assert!(matches!(
+            client.connect_announce(&req).await,
+            Err(Error::Request(_))
+        ));
+        assert!(matches!(
+            client.connect_scrape().await,
+            Err(Error::Request(_))
+        ));
+    }
 
     #[tokio::test]
     async fn test_connect_tracker() {
-        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent");
-        let resp = client.connect_announce().await;
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let resp = client
+            .connect_announce(&AnnounceRequest::new_start(0, 6881))
+            .await;
         println!("{:?}", resp);
         assert!(resp.is_ok());
     }
 
     #[tokio::test]
     async fn test_connect_scrape() {
-        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent");
+        let client = Client::new("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
         let resp = client.connect_scrape().await;
         println!("{:?}", resp);
         assert!(resp.is_ok());
@@ -0,0 +1,344 @@
+use std::time::Duration;
+
+use url::form_urlencoded::byte_serialize;
+
+use super::*;
+
+/// The `event` parameter of an announce request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnounceEvent {
+    /// The first request to the tracker
+    Started,
+    /// The download is complete
+    Completed,
+    /// The client is shutting down gracefully
+    Stopped,
+    /// A regular announce, no `event` is sent
+    #[default]
+    Empty,
+}
+
+impl AnnounceEvent {
+    fn as_str(&self) -> Option<&'static str> {
+        match self {
+            AnnounceEvent::Started => Some("started"),
+            AnnounceEvent::Completed => Some("completed"),
+            AnnounceEvent::Stopped => Some("stopped"),
+            AnnounceEvent::Empty => None,
+        }
+    }
+}
+
+/// Parameters of an announce request, see
+/// [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html#trackers)
+#[derive(Debug, Clone)]
+pub struct AnnounceParams {
+    pub info_hash: Sha1Digest,
+    pub peer_id: [u8; 20],
+    /// The port the client is listening on
+    pub port: u16,
+    /// Total bytes uploaded since the `started` event
+    pub uploaded: u64,
+    /// Total bytes downloaded since the `started` event
+    pub downloaded: u64,
+    /// Bytes the client still has to download
+    pub left: u64,
+    pub event: AnnounceEvent,
+    /// Ask for the compact peer list of [BEP-0023](https://www.bittorrent.org/beps/bep_0023.html)
+    pub compact: bool,
+    /// Ask the tracker to omit peer ids in a non-compact peer list
+    pub no_peer_id: bool,
+    /// Number of peers wanted, the tracker decides when `None`
+    pub numwant: Option<u32>,
+    /// The `tracker id` of a previous announce response
+    pub tracker_id: Option<String>,
+}
+
+impl AnnounceParams {
+    /// Percent-encoded query string, without the leading `?`
+    pub fn to_query_string(&self) -> String {
+        let mut pairs = vec![
+            ("info_hash", percent_encode(self.info_hash.as_ref())),
+            ("peer_id", percent_encode(&self.peer_id)),
+            ("port", self.port.to_string()),
+            ("uploaded", self.uploaded.to_string()),
+            ("downloaded", self.downloaded.to_string()),
+            ("left", self.left.to_string()),
+            ("compact", if self.compact { "1" } else { "0" }.to_string()),
+        ];
+        if self.no_peer_id {
+            pairs.push(("no_peer_id", "1".to_string()));
+        }
+        if let Some(event) = self.event.as_str() {
+            pairs.push(("event", event.to_string()));
+        }
+        if let Some(numwant) = self.numwant {
+            pairs.push(("numwant", numwant.to_string()));
+        }
+        if let Some(tracker_id) = &self.tracker_id {
+            pairs.push(("trackerid", percent_encode(tracker_id.as_bytes())));
+        }
+        pairs
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+}
+
+fn percent_encode(bytes: &[u8]) -> String {
+    byte_serialize(bytes).collect()
+}
+
+/// Talks to HTTP trackers, independent of any torrent file.
+pub struct HttpTrackerClient {
+    client: reqwest::Client,
+    config: ClientConfig,
+}
+
+impl HttpTrackerClient {
+    pub fn new(config: ClientConfig) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Send a single announce request to the tracker at `url`
+    pub async fn announce(
+        &self,
+        url: &str,
+        params: &AnnounceParams,
+    ) -> Result<TrackerResponseCompat> {
+        let http_url = join_query(url, &params.to_query_string());
+        let bytes = self.get(http_url, self.config.announce_timeout).await?;
+        parse_announce_response(&bytes)
+    }
+
+    /// Send a single scrape request for `info_hashes` to the scrape `url`
+    pub async fn scrape(&self, url: &str, info_hashes: &[Sha1Digest]) -> Result<ScrapeResponse> {
+        let query = info_hashes
+            .iter()
+            .map(|info_hash| format!("info_hash={}", percent_encode(info_hash.as_ref())))
+            .collect::<Vec<_>>()
+            .join("&");
+        let bytes = self
+            .get(join_query(url, &query), self.config.scrape_timeout)
+            .await?;
+        de::from_bytes(&bytes)
+    }
+
+    /// Download `length` bytes from `byte_offset` of the file at `url` with a `Range` request
+    pub(super) async fn fetch_range(
+        &self,
+        url: &str,
+        byte_offset: u64,
+        length: u64,
+    ) -> Result<Vec<u8>> {
+        if length == 0 {
+            return Err(Error::InvalidArgument("empty range".to_string()));
+        }
+        let last_byte = byte_offset.checked_add(length - 1).ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "range of {} bytes from {} overflows",
+                length, byte_offset
+            ))
+        })?;
+        let range = format!("bytes={}-{}", byte_offset, last_byte);
+        let ret = self
+            .client
+            .get(url)
+            .header(reqwest::header::RANGE, range)
+            .timeout(self.config.webseed_timeout)
+            .send()
+            .await?;
+        // A server ignoring the range answers 200 with the whole file
+        if ret.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+            return Err(Error::Request(format!(
+                "expect partial content from {} but get {}",
+                url,
+                ret.status()
+            )));
+        }
+        let bytes = ret.bytes().await?;
+        if bytes.len() as u64 != length {
+            return Err(Error::Request(format!(
+                "expect {} bytes from {} but get {}",
+                length,
+                url,
+                bytes.len()
+            )));
+        }
+        Ok(bytes.to_vec())
+    }
+
+    async fn get(&self, http_url: String, timeout: Duration) -> Result<Vec<u8>> {
+        if cfg!(test) {
+            println!("url: {}", http_url);
+        }
+        let ret = self.client.get(http_url).timeout(timeout).send().await?;
+        // The body of an error status isn't bencode
+        if ret.status().is_client_error() || ret.status().is_server_error() {
+            return Err(Error::HttpStatus(ret.status().as_u16()));
+        }
+        let bytes = ret.bytes().await?;
+        if cfg!(test) {
+            println!("response {}", dump(&bytes));
+        }
+        Ok(bytes.to_vec())
+    }
+}
+
+/// Derive the scrape URL from an announce URL, following the scrape convention: "announce" in
+/// the last path component is replaced by "scrape".
+///
+/// Returns `None` if the last path component doesn't contain "announce", which means the
+/// tracker doesn't support scrape.
+pub fn scrape_url_from_announce(announce_url: &str) -> Option<String> {
+    let (path, query) = match announce_url.find('?') {
+        Some(index) => announce_url.split_at(index),
+        None => (announce_url, ""),
+    };
+    let last_slash = path.rfind('/')?;
+    let (base, last_component) = path.split_at(last_slash + 1);
+    if !last_component.contains("announce") {
+        return None;
+    }
+    Some(format!(
+        "{}{}{}",
+        base,
+        last_component.replacen("announce", "scrape", 1),
+        query
+    ))
+}
+
+/// Append `query` to `url`, which may already carry a query (e.g. a passkey)
+fn join_query(url: &str, query: &str) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", url, separator, query)
+}
+
+fn parse_announce_response(bytes: &[u8]) -> Result<TrackerResponseCompat> {
+    let response: TrackerResponseCompat = de::from_bytes(bytes)?;
+    match response.failure_reason {
+        // Private trackers reject torrents which are removed or not allowed for the user
+        Some(reason) if reason.contains("unregistered torrent") => {
+            Err(Error::TrackerRejection(reason))
+        }
+        Some(reason) => Err(Error::TrackerFailure(reason)),
+        None => Ok(response),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_announce_query_string() {
+        let mut params = AnnounceParams {
+            info_hash: Sha1Digest([0xab; 20]),
+            peer_id: *b"-YT0001-abcdefghijkl",
+            port: 6881,
+            uploaded: 1,
+            downloaded: 2,
+            left: 3,
+            event: AnnounceEvent::Empty,
+            compact: true,
+            no_peer_id: false,
+            numwant: None,
+            tracker_id: None,
+        };
+        let info_hash = "%AB".repeat(20);
+        assert_eq!(
+            params.to_query_string(),
+            format!(
+                "info_hash={}&peer_id=-YT0001-abcdefghijkl&port=6881&uploaded=1&downloaded=2\
+                 &left=3&compact=1",
+                info_hash
+            )
+        );
+
+        params.event = AnnounceEvent::Started;
+        params.compact = false;
+        params.no_peer_id = true;
+        params.numwant = Some(50);
+        params.tracker_id = Some("a b".into());
+        assert_eq!(
+            params.to_query_string(),
+            format!(
+                "info_hash={}&peer_id=-YT0001-abcdefghijkl&port=6881&uploaded=1&downloaded=2\
+                 &left=3&compact=0&no_peer_id=1&event=started&numwant=50&trackerid=a+b",
+                info_hash
+            )
+        );
+    }
+
+    #[test]
+    fn test_scrape_url_from_announce() {
+        let cases = [
+            (
+                "http://example.com/announce",
+                Some("http://example.com/scrape"),
+            ),
+            (
+                "http://example.com/x/announce",
+                Some("http://example.com/x/scrape"),
+            ),
+            (
+                "http://example.com/announce.php",
+                Some("http://example.com/scrape.php"),
+            ),
+            (
+                "http://example.com/announce?x2%0644",
+                Some("http://example.com/scrape?x2%0644"),
+            ),
+            ("http://example.com/announce/abc?passkey=announce", None),
+            ("http://example.com/a", None),
+            (
+                "http://example.com/announce?x=2/4",
+                Some("http://example.com/scrape?x=2/4"),
+            ),
+            (
+                "http://example.com/x%064announce",
+                Some("http://example.com/x%064scrape"),
+            ),
+        ];
+        for (announce, scrape) in cases {
+            assert_eq!(
+                scrape_url_from_announce(announce).as_deref(),
+                scrape,
+                "{}",
+                announce
+            );
+        }
+    }
+
+    #[test]
+    fn test_join_query() {
+        assert_eq!(
+            join_query("http://tracker/announce", "a=1"),
+            "http://tracker/announce?a=1"
+        );
+        assert_eq!(
+            join_query("http://tracker/announce?passkey=abc", "a=1"),
+            "http://tracker/announce?passkey=abc&a=1"
+        );
+    }
+
+    #[test]
+    fn test_parse_announce_failure() {
+        let ret = parse_announce_response(b"d14:failure reason20:unregistered torrente");
+        assert!(matches!(ret, Err(Error::TrackerRejection(_))));
+
+        let ret = parse_announce_response(b"d14:failure reason11:banned peere");
+        assert!(matches!(ret, Err(Error::TrackerFailure(reason)) if reason == "banned peer"));
+
+        let ret = parse_announce_response(b"d8:intervali900e5:peers6:\x7f\x00\x00\x01\x1a\xe1e");
+        assert_eq!(ret.unwrap().interval, Some(900));
+    }
+}
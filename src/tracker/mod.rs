@@ -1,9 +1,13 @@
 pub use client::*;
+pub use http::*;
 pub use response::*;
+pub use udp::*;
 
 use super::bencode::*;
 use super::common::*;
 use super::meta::*;
 
 mod client;
+mod http;
 mod response;
+mod udp;
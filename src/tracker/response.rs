@@ -1,8 +1,9 @@
 use std::collections::HashMap;
-use std::net::{Ipv4Addr, SocketAddrV4};
+use std::fmt;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6, ToSocketAddrs};
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use serde::de::Error;
+use serde::de::{Error, SeqAccess, Visitor};
 use serde_with::{DeserializeAs, SerializeAs};
 use serde_with::rust::unwrap_or_skip;
 
@@ -10,6 +11,22 @@ use super::*;
 
 #[derive(Deserialize, Debug)]
 pub struct TrackerResponseCompat {
+    /// Set when the tracker failed to process the request, other keys may be missing then
+    #[serde(
+        rename = "failure reason",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub failure_reason: Option<String>,
+    /// Advisory text the response was processed with, which should be shown to the user
+    #[serde(
+        rename = "warning message",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub warning_message: Option<String>,
     #[serde(
         skip_serializing_if = "Option::is_none",
         default,
@@ -22,11 +39,155 @@ pub struct TrackerResponseCompat {
         with = "unwrap_or_skip"
     )]
     pub incomplete: Option<u64>,
-    pub interval: u64,
-    pub peers: CompactPeers,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub interval: Option<u64>,
+    /// Seconds the client must wait at least before re-announcing
+    #[serde(
+        rename = "min interval",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub min_interval: Option<u64>,
+    /// Should be sent back as [AnnounceParams::tracker_id] in the following announces
+    #[serde(
+        rename = "tracker id",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub tracker_id: Option<String>,
+    /// Absent when the tracker only returns IPv6 peers
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub peers: Option<PeerList>,
+    /// [BEP-0007](https://www.bittorrent.org/beps/bep_0007.html) IPv6 peers
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub peers6: Option<CompactPeersV6>,
+}
+
+/// Peers of an announce response, in compact or dict format
+#[derive(Debug, PartialEq)]
+pub enum PeerList {
+    /// [BEP-0023](https://www.bittorrent.org/beps/bep_0023.html) compact format
+    Compact(CompactPeers),
+    /// The original format of [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html#trackers)
+    Dict(Vec<DictPeer>),
+}
+
+impl PeerList {
+    /// Addresses of all peers, host names of dict peers are resolved synchronously.
+    ///
+    /// Peers which can't be resolved are skipped.
+    pub fn to_socket_addrs(&self) -> Vec<SocketAddr> {
+        match self {
+            PeerList::Compact(peers) => peers.0.iter().copied().map(SocketAddr::V4).collect(),
+            PeerList::Dict(peers) => peers
+                .iter()
+                .filter_map(|peer| {
+                    (peer.ip.as_str(), peer.port)
+                        .to_socket_addrs()
+                        .ok()
+                        .and_then(|mut addrs| addrs.next())
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Serialize for PeerList {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            PeerList::Compact(peers) => peers.serialize(serializer),
+            PeerList::Dict(peers) => peers.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerList {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct PeerListVisitor;
+
+        impl<'de> Visitor<'de> for PeerListVisitor {
+            type Value = PeerList;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("compact peers bytes or a list of peer dicts")
+            }
+
+            fn visit_bytes<E>(self, bytes: &[u8]) -> std::result::Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                CompactPeers::from_compact(bytes)
+                    .map(PeerList::Compact)
+                    .ok_or_else(|| {
+                        E::custom(format!(
+                            "buffer length {} is not a multiple of {}",
+                            bytes.len(),
+                            6
+                        ))
+                    })
+            }
+
+            fn visit_str<E>(self, str: &str) -> std::result::Result<Self::Value, E>
+            where
+                E: Error,
+            {
+                // Compact peers which happen to be valid UTF-8
+                self.visit_bytes(str.as_bytes())
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut peers = vec![];
+                while let Some(peer) = seq.next_element()? {
+                    peers.push(peer);
+                }
+                Ok(PeerList::Dict(peers))
+            }
+        }
+
+        deserializer.deserialize_any(PeerListVisitor)
+    }
+}
+
+/// A peer of the dict format peer list
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct DictPeer {
+    /// IP address or DNS name
+    pub ip: String,
+    pub port: u16,
+    /// Omitted when the client asked for `no_peer_id`
+    #[serde(
+        rename = "peer id",
+        skip_serializing_if = "Option::is_none",
+        default,
+        with = "unwrap_or_skip"
+    )]
+    pub peer_id: Option<Sha1Digest>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct CompactPeers(pub Vec<SocketAddrV4>);
 
 impl Serialize for CompactPeers {
@@ -49,12 +210,22 @@ impl<'de> Deserialize<'de> for CompactPeers {
         D: Deserializer<'de>,
     {
         let bytes: &[u8] = serde_with::Bytes::deserialize_as(deserializer)?;
-        if bytes.len() % 6 != 0 {
-            return Err(Error::custom(format!(
+        CompactPeers::from_compact(bytes).ok_or_else(|| {
+            Error::custom(format!(
                 "buffer length {} is not a multiple of {}",
                 bytes.len(),
                 6
-            )));
+            ))
+        })
+    }
+}
+
+impl CompactPeers {
+    /// Parse the 6 bytes per peer compact format of
+    /// [BEP-0023](https://www.bittorrent.org/beps/bep_0023.html)
+    pub fn from_compact(bytes: &[u8]) -> Option<Self> {
+        if !bytes.len().is_multiple_of(6) {
+            return None;
         }
         let address_list = bytes
             .chunks_exact(6)
@@ -66,7 +237,61 @@ impl<'de> Deserialize<'de> for CompactPeers {
                 SocketAddrV4::new(ip, port)
             })
             .collect();
-        Ok(Self(address_list))
+        Some(Self(address_list))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct CompactPeersV6(pub Vec<SocketAddrV6>);
+
+impl Serialize for CompactPeersV6 {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut bytes = Vec::with_capacity(self.0.len() * 18);
+        for addr in self.0.as_slice() {
+            bytes.extend_from_slice(&addr.ip().octets());
+            bytes.extend_from_slice(&addr.port().to_be_bytes());
+        }
+        serde_with::Bytes::serialize_as(&bytes, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for CompactPeersV6 {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes: &[u8] = serde_with::Bytes::deserialize_as(deserializer)?;
+        CompactPeersV6::from_compact(bytes).ok_or_else(|| {
+            Error::custom(format!(
+                "buffer length {} is not a multiple of {}",
+                bytes.len(),
+                18
+            ))
+        })
+    }
+}
+
+impl CompactPeersV6 {
+    /// Parse the 18 bytes per peer compact format of
+    /// [BEP-0007](https://www.bittorrent.org/beps/bep_0007.html)
+    pub fn from_compact(bytes: &[u8]) -> Option<Self> {
+        if !bytes.len().is_multiple_of(18) {
+            return None;
+        }
+        let address_list = bytes
+            .chunks_exact(18)
+            .map(|chunk| {
+                let ip_slice: &[u8; 16] = &chunk[0..16].try_into().unwrap();
+                let ip = Ipv6Addr::from(*ip_slice);
+                let port_slice: &[u8; 2] = &chunk[16..18].try_into().unwrap();
+                let port = u16::from_be_bytes(*port_slice);
+                SocketAddrV6::new(ip, port, 0, 0)
+            })
+            .collect();
+        Some(Self(address_list))
     }
 }
 
@@ -81,3 +306,136 @@ pub struct ScrapeFile {
     pub downloaded: i64,
     pub incomplete: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_se_de_compact_peers() {
+        let peers = CompactPeers(vec![
+            SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 6881),
+            SocketAddrV4::new(Ipv4Addr::new(10, 1, 2, 3), 51413),
+        ]);
+        let bytes = ser::to_bytes(&peers).unwrap();
+        assert_eq!(
+            bytes,
+            b"12:\x7f\x00\x00\x01\x1a\xe1\x0a\x01\x02\x03\xc8\xd5"
+        );
+        assert_eq!(de::from_bytes::<CompactPeers>(&bytes).unwrap(), peers);
+
+        let ret = de::from_bytes::<CompactPeers>(b"7:\x7f\x00\x00\x01\x1a\xe1\x00");
+        assert!(
+            matches!(ret, Err(crate::Error::SerdeCustom(msg)) if msg.contains("multiple of 6"))
+        );
+    }
+
+    #[test]
+    fn test_se_de_compact_peers_v6() {
+        let peers = CompactPeersV6(vec![
+            SocketAddrV6::new(Ipv6Addr::LOCALHOST, 6881, 0, 0),
+            SocketAddrV6::new(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1), 51413, 0, 0),
+        ]);
+        let bytes = ser::to_bytes(&peers).unwrap();
+        assert_eq!(&bytes[..3], b"36:");
+        assert_eq!(&bytes[3..19], &Ipv6Addr::LOCALHOST.octets());
+        assert_eq!(&bytes[19..21], &6881u16.to_be_bytes());
+        assert_eq!(de::from_bytes::<CompactPeersV6>(&bytes).unwrap(), peers);
+
+        let ret = de::from_bytes::<CompactPeersV6>(b"6:\x7f\x00\x00\x01\x1a\xe1");
+        assert!(
+            matches!(ret, Err(crate::Error::SerdeCustom(msg)) if msg.contains("multiple of 18"))
+        );
+    }
+
+    #[test]
+    fn test_de_compact_peer_list() {
+        let data = b"d8:intervali900e5:peers6:\x7f\x00\x00\x01\x1a\xe1e";
+        let resp: TrackerResponseCompat = de::from_bytes(data).unwrap();
+        let peers = resp.peers.unwrap();
+        assert_eq!(
+            peers,
+            PeerList::Compact(CompactPeers(vec![SocketAddrV4::new(
+                Ipv4Addr::LOCALHOST,
+                6881
+            )]))
+        );
+        assert_eq!(
+            peers.to_socket_addrs(),
+            vec!["127.0.0.1:6881".parse().unwrap()]
+        );
+
+        // Valid UTF-8 by chance
+        let data = b"d8:intervali900e5:peers6:AAAA!!e";
+        let resp: TrackerResponseCompat = de::from_bytes(data).unwrap();
+        assert_eq!(
+            resp.peers.unwrap().to_socket_addrs(),
+            vec!["65.65.65.65:8481".parse().unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_de_dict_peer_list() {
+        let data = b"d8:intervali900e5:peersld2:ip9:127.0.0.17:peer id20:-YT0001-abcdefghijkl\
+                     4:porti6881eed2:ip3:::14:porti51413eeee";
+        let resp: TrackerResponseCompat = de::from_bytes(data).unwrap();
+        let peers = resp.peers.unwrap();
+        assert_eq!(
+            peers,
+            PeerList::Dict(vec![
+                DictPeer {
+                    ip: "127.0.0.1".into(),
+                    port: 6881,
+                    peer_id: Some(Sha1Digest(*b"-YT0001-abcdefghijkl")),
+                },
+                DictPeer {
+                    ip: "::1".into(),
+                    port: 51413,
+                    peer_id: None,
+                },
+            ])
+        );
+        assert_eq!(
+            peers.to_socket_addrs(),
+            vec![
+                "127.0.0.1:6881".parse().unwrap(),
+                "[::1]:51413".parse().unwrap()
+            ]
+        );
+        assert_eq!(ser::to_bytes(&peers).unwrap(), &data[23..data.len() - 1]);
+    }
+
+    #[test]
+    fn test_de_peers6_only() {
+        let mut data = b"d8:intervali900e6:peers618:".to_vec();
+        data.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        data.extend_from_slice(&[0x1a, 0xe1]);
+        data.push(b'e');
+        let resp: TrackerResponseCompat = de::from_bytes(&data).unwrap();
+        assert!(resp.peers.is_none());
+        assert_eq!(
+            resp.peers6.unwrap().0,
+            vec![SocketAddrV6::new(Ipv6Addr::LOCALHOST, 6881, 0, 0)]
+        );
+    }
+
+    #[test]
+    fn test_de_optional_fields() {
+        let resp: TrackerResponseCompat =
+            de::from_bytes(b"d8:intervali900e15:warning message4:slowe").unwrap();
+        assert_eq!(resp.warning_message.as_deref(), Some("slow"));
+        assert_eq!(resp.min_interval, None);
+        assert_eq!(resp.tracker_id, None);
+
+        let resp: TrackerResponseCompat =
+            de::from_bytes(b"d8:intervali900e12:min intervali60ee").unwrap();
+        assert_eq!(resp.interval, Some(900));
+        assert_eq!(resp.min_interval, Some(60));
+        assert_eq!(resp.warning_message, None);
+
+        let resp: TrackerResponseCompat =
+            de::from_bytes(b"d8:intervali900e10:tracker id3:abce").unwrap();
+        assert_eq!(resp.tracker_id.as_deref(), Some("abc"));
+        assert_eq!(resp.min_interval, None);
+    }
+}
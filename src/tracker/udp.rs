@@ -0,0 +1,379 @@
+//! UDP tracker protocol, see [BEP-0015](https://www.bittorrent.org/beps/bep_0015.html)
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use log::warn;
+use rand::random;
+use tokio::net::UdpSocket;
+
+use super::*;
+
+const PROTOCOL_ID: u64 = 0x41727101980;
+const ACTION_CONNECT: u32 = 0;
+const ACTION_ANNOUNCE: u32 = 1;
+const ACTION_ERROR: u32 = 3;
+
+const CONNECT_REQUEST_LEN: usize = 16;
+const ANNOUNCE_REQUEST_LEN: usize = 98;
+/// action, transaction_id, interval, leechers and seeders
+const ANNOUNCE_RESPONSE_HEADER_LEN: usize = 20;
+
+/// Talks to UDP trackers, independent of any torrent file.
+#[derive(Debug, Clone)]
+pub struct UdpTrackerClient {
+    /// Timeout of the first attempt, doubled after every retransmission
+    pub timeout: Duration,
+    /// Retransmissions after the first attempt times out
+    pub max_retransmits: u32,
+}
+
+impl Default for UdpTrackerClient {
+    /// 15 seconds, up to 8 retransmissions, as recommended by BEP-0015
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(15),
+            max_retransmits: 8,
+        }
+    }
+}
+
+impl UdpTrackerClient {
+    /// Send a single announce to the `udp://` tracker at `url`, retransmitting timed out
+    /// requests.
+    ///
+    /// Only IPv4 trackers are supported, since the peers of an IPv6 response don't fit in
+    /// [CompactPeers].
+    pub async fn announce(
+        &self,
+        url: &str,
+        params: &AnnounceParams,
+    ) -> Result<TrackerResponseCompat> {
+        let addr = resolve(url).await?;
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(addr).await?;
+
+        // An expired connection id is dropped silently by the tracker, so a fresh one is
+        // requested whenever the announce has to be retransmitted.
+        let mut buffer = [0u8; 2048];
+        for attempt in 0..=self.max_retransmits {
+            let timeout = self.timeout_of(attempt);
+            let transaction_id = random();
+            let request = connect_request(transaction_id);
+            let exchanged = exchange(&socket, &request, transaction_id, &mut buffer, timeout);
+            let Some(len) = exchanged.await? else {
+                warn!("udp connect timed out after {:?}", timeout);
+                continue;
+            };
+            let connection_id = parse_connect_response(&buffer[..len], transaction_id)?;
+
+            let transaction_id = random();
+            let request = announce_request(connection_id, transaction_id, random(), params);
+            let exchanged = exchange(&socket, &request, transaction_id, &mut buffer, timeout);
+            let Some(len) = exchanged.await? else {
+                warn!("udp announce timed out after {:?}", timeout);
+                continue;
+            };
+            return parse_announce_response(&buffer[..len], transaction_id);
+        }
+        Err(Error::Connection(format!("udp tracker {} timed out", url)))
+    }
+
+    fn timeout_of(&self, attempt: u32) -> Duration {
+        let factor = 2u32.checked_pow(attempt).unwrap_or(u32::MAX);
+        self.timeout.saturating_mul(factor)
+    }
+}
+
+async fn resolve(url: &str) -> Result<SocketAddr> {
+    let parsed =
+        url::Url::parse(url).map_err(|e| Error::Request(format!("invalid url {}, {}", url, e)))?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| Error::Request(format!("no host in url {}", url)))?;
+    let port = parsed
+        .port()
+        .ok_or_else(|| Error::Request(format!("no port in url {}", url)))?;
+    let addr = tokio::net::lookup_host((host, port))
+        .await?
+        .find(SocketAddr::is_ipv4);
+    addr.ok_or_else(|| Error::Request(format!("no IPv4 address for {}", host)))
+}
+
+/// Send `request` and wait for the response of `transaction_id`, `None` on timeout.
+///
+/// Datagrams of other transactions, e.g. late responses to an earlier attempt, are dropped
+/// and the wait goes on until `timeout`.
+async fn exchange(
+    socket: &UdpSocket,
+    request: &[u8],
+    transaction_id: u32,
+    buffer: &mut [u8],
+    timeout: Duration,
+) -> Result<Option<usize>> {
+    socket.send(request).await?;
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        let len = match tokio::time::timeout_at(deadline, socket.recv(buffer)).await {
+            Ok(len) => len?,
+            Err(_) => return Ok(None),
+        };
+        if len >= 8 && read_u32(buffer, 4) == transaction_id {
+            return Ok(Some(len));
+        }
+        warn!(
+            "dropped udp datagram of {} bytes of another transaction",
+            len
+        );
+    }
+}
+
+fn connect_request(transaction_id: u32) -> [u8; CONNECT_REQUEST_LEN] {
+    let mut request = [0u8; CONNECT_REQUEST_LEN];
+    request[0..8].copy_from_slice(&PROTOCOL_ID.to_be_bytes());
+    request[8..12].copy_from_slice(&ACTION_CONNECT.to_be_bytes());
+    request[12..16].copy_from_slice(&transaction_id.to_be_bytes());
+    request
+}
+
+fn announce_request(
+    connection_id: u64,
+    transaction_id: u32,
+    key: u32,
+    params: &AnnounceParams,
+) -> [u8; ANNOUNCE_REQUEST_LEN] {
+    let event: u32 = match params.event {
+        AnnounceEvent::Empty => 0,
+        AnnounceEvent::Completed => 1,
+        AnnounceEvent::Started => 2,
+        AnnounceEvent::Stopped => 3,
+    };
+    let num_want = params
+        .numwant
+        .map_or(-1, |numwant| numwant.min(i32::MAX as u32) as i32);
+
+    let mut request = Vec::with_capacity(ANNOUNCE_REQUEST_LEN);
+    request.extend_from_slice(&connection_id.to_be_bytes());
+    request.extend_from_slice(&ACTION_ANNOUNCE.to_be_bytes());
+    request.extend_from_slice(&transaction_id.to_be_bytes());
+    request.extend_from_slice(&params.info_hash);
+    request.extend_from_slice(&params.peer_id);
+    request.extend_from_slice(&params.downloaded.to_be_bytes());
+    request.extend_from_slice(&params.left.to_be_bytes());
+    request.extend_from_slice(&params.uploaded.to_be_bytes());
+    request.extend_from_slice(&event.to_be_bytes());
+    // IP address, 0 lets the tracker use the sender address
+    request.extend_from_slice(&0u32.to_be_bytes());
+    request.extend_from_slice(&key.to_be_bytes());
+    request.extend_from_slice(&num_want.to_be_bytes());
+    request.extend_from_slice(&params.port.to_be_bytes());
+    request.try_into().unwrap()
+}
+
+/// Check the action and transaction id of a response, returning the payload after them
+fn check_response(bytes: &[u8], action: u32, transaction_id: u32) -> Result<&[u8]> {
+    if bytes.len() < 8 {
+        return Err(Error::Request(format!(
+            "udp response too short, {} bytes",
+            bytes.len()
+        )));
+    }
+    let response_action = read_u32(bytes, 0);
+    let response_transaction_id = read_u32(bytes, 4);
+    if response_transaction_id != transaction_id {
+        return Err(Error::Request(format!(
+            "expect transaction id {} but get {}",
+            transaction_id, response_transaction_id
+        )));
+    }
+    if response_action == ACTION_ERROR {
+        let message = String::from_utf8_lossy(&bytes[8..]).into_owned();
+        return if message.contains("unregistered torrent") {
+            Err(Error::TrackerRejection(message))
+        } else {
+            Err(Error::TrackerFailure(message))
+        };
+    }
+    if response_action != action {
+        return Err(Error::Request(format!(
+            "expect action {} but get {}",
+            action, response_action
+        )));
+    }
+    Ok(&bytes[8..])
+}
+
+fn parse_connect_response(bytes: &[u8], transaction_id: u32) -> Result<u64> {
+    let payload = check_response(bytes, ACTION_CONNECT, transaction_id)?;
+    let connection_id: [u8; 8] = payload
+        .get(..8)
+        .and_then(|id| id.try_into().ok())
+        .ok_or_else(|| Error::Request("udp connect response too short".to_string()))?;
+    Ok(u64::from_be_bytes(connection_id))
+}
+
+fn parse_announce_response(bytes: &[u8], transaction_id: u32) -> Result<TrackerResponseCompat> {
+    check_response(bytes, ACTION_ANNOUNCE, transaction_id)?;
+    if bytes.len() < ANNOUNCE_RESPONSE_HEADER_LEN {
+        return Err(Error::Request(format!(
+            "udp announce response too short, {} bytes",
+            bytes.len()
+        )));
+    }
+    let peers = CompactPeers::from_compact(&bytes[ANNOUNCE_RESPONSE_HEADER_LEN..])
+        .ok_or_else(|| Error::Request("invalid peer list in udp announce response".into()))?;
+    Ok(TrackerResponseCompat {
+        failure_reason: None,
+        warning_message: None,
+        complete: Some(read_u32(bytes, 16) as u64),
+        incomplete: Some(read_u32(bytes, 12) as u64),
+        interval: Some(read_u32(bytes, 8) as u64),
+        min_interval: None,
+        tracker_id: None,
+        peers: Some(PeerList::Compact(peers)),
+        peers6: None,
+    })
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    use super::*;
+
+    fn params() -> AnnounceParams {
+        AnnounceParams {
+            info_hash: Sha1Digest([0xab; 20]),
+            peer_id: *b"-YT0001-abcdefghijkl",
+            port: 6881,
+            uploaded: 1,
+            downloaded: 2,
+            left: 3,
+            event: AnnounceEvent::Started,
+            compact: true,
+            no_peer_id: false,
+            numwant: None,
+            tracker_id: None,
+        }
+    }
+
+    /// Fake tracker, answers one connect and one announce after dropping `drop_count` packets.
+    /// With `stale`, each response follows one with the wrong transaction id.
+    async fn serve(socket: UdpSocket, drop_count: usize, stale: bool) {
+        let mut buffer = [0u8; 2048];
+        for _ in 0..drop_count {
+            socket.recv_from(&mut buffer).await.unwrap();
+        }
+
+        let (len, from) = socket.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(len, CONNECT_REQUEST_LEN);
+        assert_eq!(&buffer[0..8], &PROTOCOL_ID.to_be_bytes());
+        assert_eq!(read_u32(&buffer, 8), ACTION_CONNECT);
+        let mut response = buffer[8..16].to_vec();
+        response.extend_from_slice(&0x1122334455667788u64.to_be_bytes());
+        if stale {
+            send_stale(&socket, &response, from).await;
+        }
+        socket.send_to(&response, from).await.unwrap();
+
+        let (len, from) = socket.recv_from(&mut buffer).await.unwrap();
+        assert_eq!(len, ANNOUNCE_REQUEST_LEN);
+        assert_eq!(&buffer[0..8], &0x1122334455667788u64.to_be_bytes());
+        assert_eq!(read_u32(&buffer, 8), ACTION_ANNOUNCE);
+        assert_eq!(&buffer[16..36], &[0xab; 20]);
+        assert_eq!(&buffer[36..56], b"-YT0001-abcdefghijkl");
+        assert_eq!(&buffer[56..64], &2u64.to_be_bytes());
+        assert_eq!(&buffer[64..72], &3u64.to_be_bytes());
+        assert_eq!(&buffer[72..80], &1u64.to_be_bytes());
+        assert_eq!(read_u32(&buffer, 80), 2);
+        assert_eq!(read_u32(&buffer, 84), 0);
+        assert_eq!(&buffer[92..96], &(-1i32).to_be_bytes());
+        assert_eq!(&buffer[96..98], &6881u16.to_be_bytes());
+        let mut response = buffer[8..16].to_vec();
+        response.extend_from_slice(&1800u32.to_be_bytes());
+        response.extend_from_slice(&5u32.to_be_bytes());
+        response.extend_from_slice(&7u32.to_be_bytes());
+        response.extend_from_slice(&[127, 0, 0, 1, 0x1a, 0xe1]);
+        if stale {
+            send_stale(&socket, &response, from).await;
+        }
+        socket.send_to(&response, from).await.unwrap();
+    }
+
+    /// Send `response` with another transaction id, as a late reply to an earlier request
+    async fn send_stale(socket: &UdpSocket, response: &[u8], to: SocketAddr) {
+        let mut stale = response.to_vec();
+        let transaction_id = read_u32(&stale, 4).wrapping_add(1);
+        stale[4..8].copy_from_slice(&transaction_id.to_be_bytes());
+        socket.send_to(&stale, to).await.unwrap();
+    }
+
+    async fn announce_to_fixture(drop_count: usize, stale: bool) -> Result<TrackerResponseCompat> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("udp://{}/announce", socket.local_addr().unwrap());
+        let server = tokio::spawn(serve(socket, drop_count, stale));
+        let client = UdpTrackerClient {
+            timeout: Duration::from_millis(100),
+            max_retransmits: 2,
+        };
+        let ret = client.announce(&url, &params()).await;
+        server.await.unwrap();
+        ret
+    }
+
+    #[tokio::test]
+    async fn test_udp_announce() {
+        let resp = announce_to_fixture(0, false).await.unwrap();
+        assert_eq!(resp.interval, Some(1800));
+        assert_eq!(resp.incomplete, Some(5));
+        assert_eq!(resp.complete, Some(7));
+        assert_eq!(
+            resp.peers.unwrap(),
+            PeerList::Compact(CompactPeers(vec![SocketAddrV4::new(
+                Ipv4Addr::LOCALHOST,
+                6881
+            )]))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_udp_retransmit() {
+        let resp = announce_to_fixture(1, false).await.unwrap();
+        assert_eq!(resp.interval, Some(1800));
+    }
+
+    #[tokio::test]
+    async fn test_udp_stale_response() {
+        let resp = announce_to_fixture(0, true).await.unwrap();
+        assert_eq!(resp.interval, Some(1800));
+        assert_eq!(resp.complete, Some(7));
+    }
+
+    #[test]
+    fn test_udp_error_response() {
+        let mut response = ACTION_ERROR.to_be_bytes().to_vec();
+        response.extend_from_slice(&9u32.to_be_bytes());
+        response.extend_from_slice(b"unregistered torrent");
+        let ret = parse_announce_response(&response, 9);
+        assert!(matches!(ret, Err(Error::TrackerRejection(_))));
+
+        let ret = parse_connect_response(&response, 10);
+        assert!(matches!(ret, Err(Error::Request(_))));
+
+        let mut response = ACTION_ERROR.to_be_bytes().to_vec();
+        response.extend_from_slice(&9u32.to_be_bytes());
+        response.extend_from_slice(b"invalid info_hash");
+        let ret = parse_announce_response(&response, 9);
+        assert!(matches!(ret, Err(Error::TrackerFailure(_))));
+    }
+
+    #[test]
+    fn test_udp_timeout() {
+        let client = UdpTrackerClient::default();
+        assert_eq!(client.timeout_of(0), Duration::from_secs(15));
+        assert_eq!(client.timeout_of(8), Duration::from_secs(3840));
+    }
+}
@@ -0,0 +1,72 @@
+//! [wasm-bindgen](https://rustwasm.github.io/docs/wasm-bindgen/) bindings, behind the "wasm"
+//! feature. The tracker clients don't run in a browser, build the module with
+//! `cargo rustc --release --no-default-features --features wasm --target wasm32-unknown-unknown
+//! --crate-type cdylib`.
+//!
+//! ```js
+//! const torrent = parseTorrent(new Uint8Array(await file.arrayBuffer()));
+//! console.log(torrent.name, torrent.infoHash, torrent.totalLength);
+//! ```
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = JSON, js_name = parse)]
+    fn json_parse(text: &str) -> JsValue;
+}
+
+/// Parse a .torrent file into an object with its name, info hash, trackers and files
+#[wasm_bindgen(js_name = parseTorrent)]
+pub fn parse_torrent(bytes: &[u8]) -> std::result::Result<JsValue, JsError> {
+    let torrent = Torrent::from_bytes(bytes).map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(json_parse(&summary(&torrent).to_string()))
+}
+
+/// What [parse_torrent] returns, as JSON
+fn summary(torrent: &Torrent) -> Value {
+    let meta = &torrent.meta_info;
+    let info = &meta.info;
+    let files: Vec<Value> = info
+        .iter_files()
+        .map(|(length, path)| json!({ "path": path, "length": length }))
+        .collect();
+    json!({
+        "name": info.name,
+        "infoHash": torrent.info_hash.short_hash().to_string(),
+        "infoHashV2": torrent.info_hash.v2().map(ToString::to_string),
+        "announce": meta.announce,
+        "announceList": meta.announce_list,
+        "comment": meta.comment,
+        "createdBy": meta.created_by,
+        "creationDate": meta.creation_date,
+        "private": info.private.unwrap_or(false),
+        "pieceLength": info.piece_length,
+        "pieceCount": info.piece_count(),
+        "totalLength": info.total_length(),
+        "files": files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let torrent =
+            Torrent::parse("./resources/debian-12.5.0-amd64-netinst.iso.torrent").unwrap();
+        let summary = summary(&torrent);
+        assert_eq!(summary["name"], "debian-12.5.0-amd64-netinst.iso");
+        assert_eq!(
+            summary["announce"],
+            "http://bttracker.debian.org:6969/announce"
+        );
+        assert_eq!(summary["infoHash"].as_str().unwrap().len(), 40);
+        assert_eq!(summary["infoHashV2"], Value::Null);
+        assert_eq!(summary["files"].as_array().unwrap().len(), 1);
+        assert_eq!(summary["files"][0]["length"], summary["totalLength"]);
+    }
+}